@@ -1,4 +1,7 @@
-use basealloc_alloc::classes::class_for;
+use basealloc_alloc::classes::{
+  class_for,
+  class_for_tiny_fast,
+};
 use criterion::{
   BenchmarkId,
   Criterion,
@@ -20,6 +23,22 @@ fn bench_class_for_tiny(c: &mut Criterion) {
   group.finish();
 }
 
+fn bench_class_for_small(c: &mut Criterion) {
+  let mut group = c.benchmark_group("class_for_small");
+  group.sample_size(50);
+
+  for size in [16, 64, 128] {
+    group.bench_with_input(BenchmarkId::new("class_for", size), &size, |b, &s| {
+      b.iter(|| class_for(black_box(s)));
+    });
+    group.bench_with_input(BenchmarkId::new("tiny_fast", size), &size, |b, &s| {
+      b.iter(|| class_for_tiny_fast(black_box(s)));
+    });
+  }
+
+  group.finish();
+}
+
 fn bench_class_for_regular(c: &mut Criterion) {
   let mut group = c.benchmark_group("class_for_regular");
   group.sample_size(50);
@@ -52,6 +71,7 @@ fn bench_class_for_mixed(c: &mut Criterion) {
 criterion_group!(
   benches,
   bench_class_for_tiny,
+  bench_class_for_small,
   bench_class_for_regular,
   bench_class_for_mixed
 );
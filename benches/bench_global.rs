@@ -28,8 +28,7 @@ fn bench_vec_push(c: &mut Criterion) {
 fn bench_vec_reserve(c: &mut Criterion) {
   c.bench_function("vec_reserve_1mb", |b| {
     b.iter(|| {
-      let mut v = Vec::<u8>::new();
-      v.reserve(1024 * 1024);
+      let v = Vec::<u8>::with_capacity(1024 * 1024);
       black_box(v);
     });
   });
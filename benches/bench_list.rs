@@ -56,8 +56,8 @@ fn bench_list_operations(c: &mut Criterion) {
         nodes
       },
       |mut nodes| {
-        for i in 1..nodes.len() {
-          List::remove(&mut nodes[i]);
+        for node in nodes.iter_mut().skip(1) {
+          List::remove(node);
         }
         black_box(&nodes);
       },
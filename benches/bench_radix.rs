@@ -12,7 +12,7 @@ fn bench_radix_operations(c: &mut Criterion) {
   c.bench_function("radix_insert", |b| {
     b.iter_batched(
       || BenchTree::new(4096),
-      |mut tree| {
+      |tree| {
         for i in 0..100 {
           let _ = tree.insert(i, i);
         }
@@ -25,7 +25,7 @@ fn bench_radix_operations(c: &mut Criterion) {
   c.bench_function("radix_lookup", |b| {
     b.iter_batched(
       || {
-        let mut tree = BenchTree::new(4096);
+        let tree = BenchTree::new(4096);
         for i in 0..100 {
           let _ = tree.insert(i, i * 2);
         }
@@ -47,13 +47,13 @@ fn bench_radix_operations(c: &mut Criterion) {
   c.bench_function("radix_remove", |b| {
     b.iter_batched(
       || {
-        let mut tree = BenchTree::new(4096);
+        let tree = BenchTree::new(4096);
         for i in 0..100 {
           let _ = tree.insert(i, i);
         }
         tree
       },
-      |mut tree| {
+      |tree| {
         let mut sum = 0;
         for i in 0..100 {
           if let Some(value) = tree.remove(i) {
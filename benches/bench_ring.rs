@@ -0,0 +1,43 @@
+use basealloc_ring::{
+  Ring,
+  UnsyncRing,
+};
+use core::hint::black_box;
+use criterion::{
+  Criterion,
+  criterion_group,
+  criterion_main,
+};
+
+const SLOTS: usize = 64;
+
+fn bench_ring_operations(c: &mut Criterion) {
+  c.bench_function("ring_push_pop", |b| {
+    let ring = Ring::new();
+    let mut buf = [0usize; SLOTS];
+    b.iter(|| {
+      for i in 0..SLOTS {
+        let _ = ring.push(&mut buf, black_box(i));
+      }
+      while let Some(val) = ring.pop(&buf) {
+        black_box(val);
+      }
+    });
+  });
+
+  c.bench_function("unsync_ring_push_pop", |b| {
+    let ring = UnsyncRing::new();
+    let mut buf = [0usize; SLOTS];
+    b.iter(|| {
+      for i in 0..SLOTS {
+        let _ = ring.push(&mut buf, black_box(i));
+      }
+      while let Some(val) = ring.pop(&buf) {
+        black_box(val);
+      }
+    });
+  });
+}
+
+criterion_group!(ring_benches, bench_ring_operations);
+criterion_main!(ring_benches);
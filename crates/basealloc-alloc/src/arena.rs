@@ -1,6 +1,15 @@
 use core::{
   alloc::Layout,
-  ptr::NonNull,
+  ops::AddAssign,
+  ptr::{
+    NonNull,
+    addr_of_mut,
+  },
+  sync::atomic::{
+    AtomicBool,
+    AtomicUsize,
+    Ordering,
+  },
 };
 
 use basealloc_extent::{
@@ -10,12 +19,18 @@ use basealloc_extent::{
 use basealloc_fixed::bump::{
   Bump,
   BumpError,
+  BumpMark,
+};
+use basealloc_ring::{
+  MpscRing,
+  RingError,
 };
 use getset::{
   CloneGetters,
+  CopyGetters,
   Getters,
-  MutGetters,
 };
+use spin::Mutex;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct ArenaId(pub usize);
@@ -37,12 +52,22 @@ use crate::{
   static_::ARENA_MAP,
 };
 
+use basealloc_fixed::pool::FixedSystem;
 use basealloc_sys::{
+  current_system,
+  math::align_up,
   prim::{
+    PageRange,
     PrimError,
     page_align,
+    page_size,
+    va_size,
+  },
+  system::{
+    SysError,
+    SysOption,
+    System,
   },
-  system::SysOption,
 };
 
 #[derive(Debug)]
@@ -52,20 +77,161 @@ pub enum ArenaError {
   LookupError(LookupError),
   ExtentError(ExtentError),
   PrimError(PrimError),
+  SysError(SysError),
+  /// A large request above `max_large()`; nothing was mapped.
+  TooLarge {
+    size: usize,
+    max: usize,
+  },
 }
 
 pub type ArenaResult<T> = Result<T, ArenaError>;
 
-#[derive(Getters, MutGetters, CloneGetters)]
+const REMOTE_CAP: usize = 256;
+
+/// Id shared by arenas from `Arena::new_fixed`; outside the arena table.
+pub const FIXED_ARENA: ArenaId = ArenaId(usize::MAX - 1);
+const FIXED_CHUNK_PAGES: usize = 4;
+
+// Half the address space; anything bigger can't be mapped anyway.
+static MAX_LARGE: AtomicUsize = AtomicUsize::new(1 << (va_size() - 1));
+
+// What `Arena::new` picks when the caller doesn't.
+static EAGER_BY_DEFAULT: AtomicBool = AtomicBool::new(false);
+
+pub(crate) const FOOTPRINT: usize = size_of::<AtomicUsize>() + size_of::<AtomicBool>();
+
+/// Caps the size of a single large allocation; bigger ones fail with `TooLarge`.
+pub fn set_max_large(bytes: usize) {
+  MAX_LARGE.store(bytes, Ordering::Relaxed);
+}
+
+pub fn max_large() -> usize {
+  MAX_LARGE.load(Ordering::Relaxed)
+}
+
+// `TooLarge` past `max_large`, checked before anything is mapped.
+fn check_large(size: usize) -> ArenaResult<()> {
+  let max = max_large();
+  if size > max {
+    return Err(ArenaError::TooLarge { size, max });
+  }
+  Ok(())
+}
+
+/// Live and committed bytes of an arena, or several summed with `+=`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ArenaUsage {
+  /// Live small slots at their class size, plus large extents whole.
+  pub live: usize,
+  /// Committed slab memory plus large extents.
+  pub committed: usize,
+}
+
+impl ArenaUsage {
+  /// Share of committed memory holding no live data, `1 - live / committed`; `0.0` when
+  /// nothing is committed.
+  pub fn fragmentation(&self) -> f32 {
+    if self.committed == 0 {
+      return 0.0;
+    }
+    1.0 - self.live as f32 / self.committed as f32
+  }
+}
+
+impl AddAssign for ArenaUsage {
+  fn add_assign(&mut self, other: Self) {
+    self.live += other.live;
+    self.committed += other.committed;
+  }
+}
+
+// Bytes `extent` has to grow by for the block at `ptr` in it to hold `new_size`; zero
+// when it already does.
+fn extra_for(extent: &Extent, ptr: NonNull<u8>, new_size: usize) -> ArenaResult<usize> {
+  let offset = ptr.as_ptr() as usize - extent.as_ref().as_ptr() as usize;
+  let needed = offset
+    .checked_add(new_size)
+    .ok_or(ArenaError::PrimError(PrimError::Overflow))?;
+  let pages = page_align(needed).map_err(ArenaError::PrimError)?;
+  Ok(pages.saturating_sub(extent.size()))
+}
+
+/// Bytes mapped for a large `layout`: its pages, plus room to slide the block up to an
+/// alignment above the page size. Mappings are only page-aligned.
+pub fn large_map_size(layout: Layout) -> ArenaResult<usize> {
+  let pages = page_align(layout.size()).map_err(ArenaError::PrimError)?;
+  let slack = layout.align().saturating_sub(page_size());
+  pages
+    .checked_add(slack)
+    .ok_or(ArenaError::PrimError(PrimError::Overflow))
+}
+
+/// When an arena's slabs get their memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CommitPolicy {
+  /// Reserve a new slab and commit it on first use; pages fault in as slots are touched.
+  #[default]
+  Lazy,
+  /// Commit a new slab and fault in all of its pages at once, trading RSS for no page
+  /// faults on the allocation path.
+  Eager,
+}
+
+/// Where an arena's slabs get their address space.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SlabMode {
+  /// Every slab is a mapping of its own.
+  #[default]
+  Separate,
+  /// Each bin reserves regions of `SLAB_GROUP_SIZE` bytes and carves its slabs from them,
+  /// so its slabs share a handful of mappings instead of one each. A destroyed slab only
+  /// gives its pages back; the region is unmapped with the last slab carved from it.
+  SubSlab,
+}
+
+/// Sets the policy `Arena::new` gives arenas created from now on.
+pub fn set_default_commit_policy(policy: CommitPolicy) {
+  EAGER_BY_DEFAULT.store(policy == CommitPolicy::Eager, Ordering::Relaxed);
+}
+
+pub fn default_commit_policy() -> CommitPolicy {
+  match EAGER_BY_DEFAULT.load(Ordering::Relaxed) {
+    true => CommitPolicy::Eager,
+    false => CommitPolicy::Lazy,
+  }
+}
+
+// Lock order: drain, then bin, then bump, then the extent trees.
+#[derive(Getters, CloneGetters, CopyGetters)]
 pub struct Arena {
   #[getset(get_clone = "pub")]
   index: ArenaId,
-  bins: [Bin; NSCLASSES],
-  bump: Bump,
-  #[getset(get = "pub", get_mut = "pub")]
+  bins: [Mutex<Bin>; NSCLASSES],
+  #[getset(get = "pub")]
   etree: ExtentTree,
+  // `None` follows the current global backend.
+  system: Option<&'static dyn System>,
+  #[getset(get_copy = "pub")]
+  commit_policy: CommitPolicy,
+  // Everything allocated in `bump` after the arena itself.
+  mark: BumpMark,
+  // Pointers freed by other threads, handed back on the next allocation.
+  remote: MpscRing<NonNull<u8>, REMOTE_CAP>,
+  drain: Mutex<()>,
+  // Allocations served so far; a rough busyness hint for picking arenas.
+  load: AtomicUsize,
+  // Bytes of the live large extents.
+  large: AtomicUsize,
+  // Whether bins carve new slabs from shared regions; see `SlabMode`.
+  sub_slabs: AtomicBool,
+  // SAFETY: Must stay the last field; the arena itself lives in this bump.
+  bump: Mutex<Bump>,
 }
 
+unsafe impl Send for Arena {}
+unsafe impl Sync for Arena {}
+
 impl Arena {
   /// Creates a new arena.
   ///
@@ -74,98 +240,363 @@ impl Arena {
   /// The caller must ensure that the returned arena is properly managed and
   /// dropped before any referenced memory becomes invalid.
   pub unsafe fn new(index: ArenaId, chunk_size: usize) -> ArenaResult<NonNull<Self>> {
-    let mut bump = Bump::new(chunk_size);
-    let this_uninit = bump.create::<Self>().map_err(ArenaError::BumpError)? as *mut Self;
+    unsafe { Self::new_with_policy(index, chunk_size, default_commit_policy()) }
+  }
+
+  /// Like `new`, but with `policy` instead of `default_commit_policy()`.
+  ///
+  /// # Safety
+  ///
+  /// Same as `new`.
+  pub unsafe fn new_with_policy(
+    index: ArenaId,
+    chunk_size: usize,
+    policy: CommitPolicy,
+  ) -> ArenaResult<NonNull<Self>> {
+    let bump = Bump::new(chunk_size);
+    let etree = ExtentTree::new(chunk_size);
+    unsafe { Self::build(index, bump, etree, None, policy) }
+  }
+
+  /// Creates an arena whose memory all comes from `buf`; it never maps anything.
+  ///
+  /// Freed memory isn't handed back to the buffer, so once it is used up every
+  /// allocation fails with `OutOfMemory`. Its pages are still indexed in the global
+  /// arena map, under an id no arena table slot uses.
+  pub fn new_fixed(buf: &'static mut [u8]) -> ArenaResult<NonNull<Self>> {
+    let system: &'static dyn System = FixedSystem::carve(buf).map_err(ArenaError::SysError)?;
+    let chunk_size = FIXED_CHUNK_PAGES * page_size();
+    let bump = Bump::new_in(chunk_size, system);
+    let etree = ExtentTree::new_in(chunk_size, system);
+    unsafe { Self::build(FIXED_ARENA, bump, etree, Some(system), CommitPolicy::Lazy) }
+  }
 
-    unsafe { core::ptr::addr_of_mut!((*this_uninit).index).write(index) };
-    unsafe { core::ptr::addr_of_mut!((*this_uninit).bump).write(bump) };
+  // The arena lives at the start of its own bump and starts out uninitialized: every
+  // field is written exactly once, in place, and nothing is read until all of them are.
+  // Bump memory isn't zeroed, so no field may be left to a zero pattern.
+  pub(crate) unsafe fn build(
+    index: ArenaId,
+    mut bump: Bump,
+    etree: ExtentTree,
+    system: Option<&'static dyn System>,
+    commit_policy: CommitPolicy,
+  ) -> ArenaResult<NonNull<Self>> {
+    let slot = bump.create::<Self>().map_err(ArenaError::BumpError)?;
+    let this = unsafe { (*slot).as_mut_ptr() };
 
-    let bins = core::array::from_fn(|i| {
-      let class = ScIdx(i);
-      Bin::new(class)
-    });
-    unsafe { core::ptr::addr_of_mut!((*this_uninit).bins).write(bins) };
+    unsafe { addr_of_mut!((*this).index).write(index) };
+    unsafe { addr_of_mut!((*this).system).write(system) };
+    unsafe { addr_of_mut!((*this).commit_policy).write(commit_policy) };
+    unsafe { addr_of_mut!((*this).mark).write(bump.mark()) };
+    unsafe { addr_of_mut!((*this).remote).write(MpscRing::new()) };
+    unsafe { addr_of_mut!((*this).drain).write(Mutex::new(())) };
+    unsafe { addr_of_mut!((*this).load).write(AtomicUsize::new(0)) };
+    unsafe { addr_of_mut!((*this).large).write(AtomicUsize::new(0)) };
+    unsafe { addr_of_mut!((*this).sub_slabs).write(AtomicBool::new(false)) };
+    unsafe { addr_of_mut!((*this).etree).write(etree) };
+    unsafe { Self::init_bins(this) };
+    unsafe { addr_of_mut!((*this).bump).write(Mutex::new(bump)) };
 
-    let etree = ExtentTree::new(chunk_size);
-    unsafe { core::ptr::addr_of_mut!((*this_uninit).etree).write(etree) };
+    // SAFETY: every field was written above; `slot` came from the bump, so isn't null.
+    Ok(unsafe { NonNull::new_unchecked(this) })
+  }
+
+  // Writes each bin in place rather than moving a whole array of them through the stack.
+  unsafe fn init_bins(this: *mut Self) {
+    let bins = unsafe { addr_of_mut!((*this).bins) } as *mut Mutex<Bin>;
+    for i in 0..NSCLASSES {
+      unsafe { bins.add(i).write(Mutex::new(Bin::new(ScIdx(i)))) };
+    }
+  }
 
-    Ok(unsafe { NonNull::new_unchecked(this_uninit) })
+  /// The backend this arena maps slabs and large extents through.
+  pub fn system(&self) -> &'static dyn System {
+    self.system.unwrap_or_else(current_system)
   }
 
-  pub fn allocate(&mut self, sc: ScIdx) -> ArenaResult<NonNull<u8>> {
-    let self_nn = unsafe { NonNull::new_unchecked(self as *mut Arena) };
-    let bin = &mut self.bins[sc.0];
-    bin
-      .allocate(&mut self.bump, self_nn)
+  pub fn slab_mode(&self) -> SlabMode {
+    match self.sub_slabs.load(Ordering::Relaxed) {
+      true => SlabMode::SubSlab,
+      false => SlabMode::Separate,
+    }
+  }
+
+  /// Switches how slabs created from now on are mapped; live slabs stay where they are.
+  pub fn set_slab_mode(&self, mode: SlabMode) {
+    self
+      .sub_slabs
+      .store(mode == SlabMode::SubSlab, Ordering::Relaxed);
+  }
+
+  /// How many allocations this arena has served since it was created.
+  pub fn load(&self) -> usize {
+    self.load.load(Ordering::Relaxed)
+  }
+
+  pub fn allocate(&self, sc: ScIdx) -> ArenaResult<NonNull<u8>> {
+    self.load.fetch_add(1, Ordering::Relaxed);
+    self.drain_remote();
+    let self_nn = NonNull::from(self);
+    self.bins[sc.0]
+      .lock()
+      .allocate(&self.bump, self_nn)
+      .map_err(ArenaError::BinError)
+  }
+
+  /// Like `allocate`, but the slot reads as zero, memsetting only when it has to.
+  pub fn allocate_zeroed(&self, sc: ScIdx) -> ArenaResult<NonNull<u8>> {
+    self.load.fetch_add(1, Ordering::Relaxed);
+    self.drain_remote();
+    let self_nn = NonNull::from(self);
+    self.bins[sc.0]
+      .lock()
+      .allocate_zeroed(&self.bump, self_nn)
       .map_err(ArenaError::BinError)
   }
 
-  pub fn allocate_large(&mut self, layout: Layout) -> ArenaResult<NonNull<u8>> {
+  /// Maps a block for `layout` on its own, aligned as it asks.
+  ///
+  /// Mappings are page-aligned; a larger alignment is met by mapping `large_map_size`
+  /// bytes and returning the first aligned address inside, so the pointer may sit past
+  /// the extent's base. Lookups by that pointer still find the extent.
+  pub fn allocate_large(&self, layout: Layout) -> ArenaResult<NonNull<u8>> {
+    check_large(layout.size())?;
+    self.load.fetch_add(1, Ordering::Relaxed);
+    self.drain_remote();
+    self
+      .place_large(layout)
+      .inspect_err(|_| _ = self.load.fetch_sub(1, Ordering::Relaxed))
+  }
+
+  // Maps and registers the extent, releasing it again if registering fails.
+  fn place_large(&self, layout: Layout) -> ArenaResult<NonNull<u8>> {
+    let (extent, ptr) = self.map_large(layout)?;
+    if let Err(err) = self.register_large(extent) {
+      let size = unsafe { extent.as_ref() }.size();
+      self.large.fetch_sub(size, Ordering::Relaxed);
+      unsafe { core::ptr::drop_in_place(extent.as_ptr()) };
+      return Err(err);
+    }
+    Ok(ptr)
+  }
+
+  // The extent, stored in the bump, and the first address in it aligned for `layout`.
+  fn map_large(&self, layout: Layout) -> ArenaResult<(NonNull<Extent>, NonNull<u8>)> {
     let extent_store = self
       .bump
+      .lock()
       .create::<Extent>()
       .map_err(ArenaError::BumpError)? as *mut Extent;
 
-    let pga_size = page_align(layout.size()).map_err(ArenaError::PrimError)?;
-
-    let extent = Extent::new(pga_size, SysOption::Commit).map_err(ArenaError::ExtentError)?;
-    let ptr = extent.as_ref().as_ptr() as *mut u8;
+    let map_size = large_map_size(layout)?;
+    let extent = Extent::new_in(map_size, SysOption::Commit, self.system())
+      .map_err(ArenaError::ExtentError)?;
+    let base = extent.as_ref().as_ptr() as usize;
+    let ptr = align_up(base, layout.align()).ok_or(ArenaError::PrimError(PrimError::Overflow))?;
+    self.large.fetch_add(map_size, Ordering::Relaxed);
     unsafe {
       core::ptr::write(extent_store, extent);
     }
 
     let extent_nn = unsafe { NonNull::new_unchecked(extent_store) };
-    let info = OwnerInfo::new_extent(extent_nn);
-    self
-      .etree_mut()
-      .register(extent_nn, info)
-      .map_err(ArenaError::LookupError)?;
-
-    ARENA_MAP
-      .associate(extent_nn, self.index())
-      .map_err(ArenaError::LookupError)?;
+    Ok((extent_nn, unsafe { NonNull::new_unchecked(ptr as *mut u8) }))
+  }
 
-    Ok(unsafe { NonNull::new_unchecked(ptr) })
+  // Files `extent` in both lookup trees, or in neither.
+  fn register_large(&self, extent: NonNull<Extent>) -> ArenaResult<()> {
+    if let Err(err) = ARENA_MAP.associate(extent, self.index()) {
+      _ = ARENA_MAP.detach(extent);
+      return Err(ArenaError::LookupError(err));
+    }
+    let info = OwnerInfo::new_extent(extent);
+    if let Err(err) = self.etree().register(extent, info) {
+      _ = self.etree().unregister(extent);
+      _ = ARENA_MAP.detach(extent);
+      return Err(ArenaError::LookupError(err));
+    }
+    Ok(())
   }
 
-  pub fn deallocate_large(&mut self, extent: NonNull<Extent>) -> ArenaResult<()> {
+  /// Unmaps a large allocation. Threads may free distinct extents into one arena at
+  /// once: both lookup trees serialize their writers, and only the thread that
+  /// unregistered an extent goes on to drop it.
+  pub fn deallocate_large(&self, extent: NonNull<Extent>) -> ArenaResult<()> {
     self
-      .etree_mut()
+      .etree()
       .unregister(extent)
       .map_err(ArenaError::LookupError)?;
     ARENA_MAP.detach(extent).map_err(ArenaError::LookupError)?;
 
+    let size = unsafe { extent.as_ref() }.size();
+    self.large.fetch_sub(size, Ordering::Relaxed);
     unsafe {
       core::ptr::drop_in_place(extent.as_ptr());
     }
     Ok(())
   }
 
-  pub fn deallocate(&mut self, ptr: NonNull<u8>) -> ArenaResult<()> {
+  /// Grows the large block at `ptr` to `new_size` bytes without moving it, mapping the
+  /// pages right after its extent when it has to. Fails, with the block untouched,
+  /// when those pages are taken.
+  pub fn grow_large(
+    &self,
+    mut extent: NonNull<Extent>,
+    ptr: NonNull<u8>,
+    new_size: usize,
+  ) -> ArenaResult<()> {
+    check_large(new_size)?;
+    let extent_ref = unsafe { extent.as_mut() };
+    let extra = extra_for(extent_ref, ptr, new_size)?;
+    if extra == 0 {
+      return Ok(());
+    }
+
+    let end = extent_ref.as_ref().as_ptr() as usize + extent_ref.size();
+    let pages = PageRange::new(end, extra).map_err(ArenaError::PrimError)?;
+    extent_ref.grow(extra).map_err(ArenaError::ExtentError)?;
+    self.large.fetch_add(extra, Ordering::Relaxed);
+    self.register_grown(extent, pages)
+  }
+
+  // Files `pages`, which `extent` just grew by, under this arena in both lookup trees.
+  fn register_grown(&self, extent: NonNull<Extent>, pages: PageRange) -> ArenaResult<()> {
+    ARENA_MAP
+      .associate_range(pages, self.index())
+      .map_err(ArenaError::LookupError)?;
+    self
+      .etree()
+      .register_range(pages, OwnerInfo::new_extent(extent))
+      .map_err(ArenaError::LookupError)
+  }
+
+  pub fn deallocate(&self, ptr: NonNull<u8>) -> ArenaResult<()> {
     let info = self
       .etree()
       .lookup(ptr.as_ptr() as usize)
-      .ok_or(ArenaError::LookupError(LookupError::NotFound))?
-      .clone();
+      .ok_or(ArenaError::LookupError(LookupError::NotFound))?;
 
     match info {
-      OwnerInfo::Slab { slab, size_class } => {
-        let bin = &mut self.bins[size_class.0];
-        bin.deallocate(ptr, slab).map_err(ArenaError::BinError)
-      }
+      OwnerInfo::Slab { slab, size_class } => self.bins[size_class.0]
+        .lock()
+        .deallocate(ptr, slab)
+        .map_err(ArenaError::BinError),
       OwnerInfo::Extent { extent } => self.deallocate_large(extent),
     }
   }
 
+  /// Frees `ptr` from a thread that doesn't own this arena without touching its bins.
+  ///
+  /// Falls back to a locked free when the queue is full.
+  pub fn free_remote(&self, ptr: NonNull<u8>) -> ArenaResult<()> {
+    match self.remote.push(ptr) {
+      Ok(()) => Ok(()),
+      Err(RingError::Full(ptr)) => self.deallocate(ptr),
+    }
+  }
+
+  fn drain_remote(&self) {
+    if self.remote.is_empty() {
+      return;
+    }
+    // Someone else is already draining; they'll pick these up.
+    let Some(_guard) = self.drain.try_lock() else {
+      return;
+    };
+
+    // SAFETY: `drain` makes this the only consumer.
+    while let Some(ptr) = unsafe { self.remote.pop() } {
+      let _ = self.deallocate(ptr);
+    }
+  }
+
+  /// Checks every bin, and that each mapping in `etree` is filed under this arena.
+  ///
+  /// Only reliable while no other thread uses the arena.
+  pub fn validate(&self) -> Result<(), &'static str> {
+    for bin in self.bins.iter() {
+      bin.lock().validate()?;
+    }
+
+    let index = self.index();
+    let stray = self
+      .etree
+      .tree()
+      .find(|info| ARENA_MAP.lookup(info.base()) != Some(index));
+    match stray {
+      Some(_) => Err("arena map disagrees with an arena's extent tree"),
+      None => Ok(()),
+    }
+  }
+
+  /// Live against committed bytes over every bin and large extent; see `ArenaUsage`.
+  ///
+  /// Slots parked in thread caches count as live.
+  pub fn usage(&self) -> ArenaUsage {
+    let large = self.large.load(Ordering::Relaxed);
+    let mut usage = ArenaUsage {
+      live: large,
+      committed: large,
+    };
+    for bin in self.bins.iter() {
+      let bin = bin.lock();
+      usage += ArenaUsage {
+        live: bin.live_bytes(),
+        committed: bin.committed_bytes(),
+      };
+    }
+    usage
+  }
+
+  /// Drains remote frees, then reclaims the free pages of active slabs bin by bin until
+  /// about `budget` pages went back; see `Bin::reclaim_pages`. Returns the pages reclaimed.
+  pub fn reclaim_pages(&self, budget: usize) -> ArenaResult<usize> {
+    self.drain_remote();
+    let mut reclaimed = 0;
+    for bin in self.bins.iter() {
+      if reclaimed >= budget {
+        break;
+      }
+      reclaimed += bin
+        .lock()
+        .reclaim_pages(budget - reclaimed)
+        .map_err(ArenaError::BinError)?;
+    }
+    Ok(reclaimed)
+  }
+
   pub fn owns(&self, ptr: NonNull<u8>) -> bool {
     self.etree().lookup(ptr.as_ptr() as usize).is_some()
   }
+
+  /// Frees every allocation at once while keeping the metadata chunks for reuse.
+  ///
+  /// # Safety
+  ///
+  /// No pointer handed out by this arena may be used or freed afterwards, including
+  /// slots parked in a thread cache.
+  pub unsafe fn reset(&mut self) -> ArenaResult<()> {
+    while unsafe { self.remote.pop() }.is_some() {}
+
+    // Dropping a bin drops its slabs, which unregister their extents.
+    for (i, bin) in self.bins.iter_mut().enumerate() {
+      *bin.get_mut() = Bin::new(ScIdx(i));
+    }
+
+    while let Some(extent) = self.etree.any_extent() {
+      self.deallocate_large(extent)?;
+    }
+
+    unsafe { self.bump.get_mut().rewind(self.mark) };
+    Ok(())
+  }
 }
 
 #[cfg(test)]
 mod tests {
   use core::ptr::drop_in_place;
 
+  use basealloc_sys::prim::page_size;
+
   use crate::CHUNK_SIZE;
 
   use super::*;
@@ -175,4 +606,394 @@ mod tests {
     let arena = unsafe { Arena::new(ArenaId(0), CHUNK_SIZE).expect("Failed to create arena") };
     unsafe { drop_in_place(arena.as_ptr()) };
   }
+
+  // Resident pages of the slab the first slot of `sc` lands in, before and after writing it.
+  fn slab_residency(policy: CommitPolicy, id: usize, sc: ScIdx) -> (usize, usize) {
+    let arena_nn = unsafe { Arena::new_with_policy(ArenaId(id), CHUNK_SIZE, policy).unwrap() };
+    let arena = unsafe { arena_nn.as_ref() };
+    let ptr = arena.allocate(sc).expect("alloc");
+    let Some(OwnerInfo::Slab { slab, .. }) = arena.etree().lookup(ptr.as_ptr() as usize) else {
+      panic!("not a slab pointer");
+    };
+    let backing = unsafe { slab.as_ref() }.extent().as_ref();
+    let resident = || unsafe { arena.system().resident_pages(backing) }.unwrap();
+
+    let before = resident();
+    unsafe { ptr.as_ptr().write_bytes(0xAB, 1) };
+    let after = resident();
+    arena.deallocate(ptr).expect("dealloc");
+    unsafe { drop_in_place(arena_nn.as_ptr()) };
+    (before, after)
+  }
+
+  #[test]
+  fn commit_policy_decides_when_pages_fault_in() {
+    let sc = ScIdx(40);
+    let pages = crate::classes::pages_for(sc).0 / page_size();
+    assert!(pages > 1);
+
+    assert_eq!(slab_residency(CommitPolicy::Eager, 22, sc), (pages, pages));
+    // Poison fills every fresh slab, touching all of it.
+    #[cfg(not(feature = "poison"))]
+    {
+      let (before, after) = slab_residency(CommitPolicy::Lazy, 23, sc);
+      assert!(before <= 1 && after == 1, "lazy {} {}", before, after);
+    }
+  }
+
+  #[test]
+  fn oversized_large_fails_before_mapping() {
+    use basealloc_sys::{
+      GLOBAL_SYSTEM,
+      counting::CountingSystem,
+    };
+
+    static COUNTING: CountingSystem = CountingSystem::new(GLOBAL_SYSTEM);
+    let bump = Bump::new(CHUNK_SIZE);
+    let etree = ExtentTree::new(CHUNK_SIZE);
+    let arena_nn = unsafe {
+      Arena::build(
+        ArenaId(16),
+        bump,
+        etree,
+        Some(&COUNTING),
+        CommitPolicy::Lazy,
+      )
+    }
+    .unwrap();
+    let arena = unsafe { arena_nn.as_ref() };
+
+    let huge = Layout::from_size_align(1 << 62, 8).unwrap();
+    let result = arena.allocate_large(huge);
+    assert!(matches!(
+      result,
+      Err(ArenaError::TooLarge { size, max }) if size == 1 << 62 && max == max_large()
+    ));
+    assert_eq!(COUNTING.allocs(), 0);
+
+    let ok = Layout::from_size_align(page_size(), 8).unwrap();
+    assert!(arena.allocate_large(ok).is_ok());
+    assert_eq!(COUNTING.allocs(), 1);
+    unsafe { drop_in_place(arena_nn.as_ptr()) };
+  }
+
+  #[test]
+  fn failed_large_register_is_rolled_back() {
+    use basealloc_sys::{
+      GLOBAL_SYSTEM,
+      system::{
+        SysResult,
+        UnsupportedSystem,
+      },
+    };
+
+    struct LastMap {
+      mapped: AtomicUsize,
+      unmapped: AtomicUsize,
+    }
+
+    unsafe impl System for LastMap {
+      unsafe fn alloc<'mem>(&self, size: usize, options: SysOption) -> SysResult<&'mem mut [u8]> {
+        let slice = unsafe { GLOBAL_SYSTEM.alloc(size, options) }?;
+        self
+          .mapped
+          .store(slice.as_ptr() as usize, Ordering::Relaxed);
+        Ok(slice)
+      }
+
+      unsafe fn modify(&self, slice: &[u8], options: SysOption) -> SysResult<()> {
+        unsafe { GLOBAL_SYSTEM.modify(slice, options) }
+      }
+
+      unsafe fn dealloc(&self, slice: &[u8]) -> SysResult<()> {
+        self
+          .unmapped
+          .store(slice.as_ptr() as usize, Ordering::Relaxed);
+        unsafe { GLOBAL_SYSTEM.dealloc(slice) }
+      }
+    }
+
+    static LAST: LastMap = LastMap {
+      mapped: AtomicUsize::new(0),
+      unmapped: AtomicUsize::new(0),
+    };
+    // The extent maps fine, but the tree can't grow a node to file it in.
+    let etree = ExtentTree::new_in(CHUNK_SIZE, &UnsupportedSystem {});
+    let bump = Bump::new(CHUNK_SIZE);
+    let id = ArenaId(32);
+    let arena_nn =
+      unsafe { Arena::build(id, bump, etree, Some(&LAST), CommitPolicy::Lazy) }.unwrap();
+    let arena = unsafe { arena_nn.as_ref() };
+
+    let layout = Layout::from_size_align(4 * page_size(), 8).unwrap();
+    let result = arena.allocate_large(layout);
+    assert!(matches!(result, Err(ArenaError::LookupError(_))));
+
+    let base = LAST.mapped.load(Ordering::Relaxed);
+    assert_ne!(base, 0);
+    assert_eq!(LAST.unmapped.load(Ordering::Relaxed), base);
+    assert_ne!(ARENA_MAP.lookup(base), Some(id));
+    assert!(arena.etree().lookup(base).is_none());
+    assert_eq!(arena.load(), 0);
+    assert_eq!(arena.large.load(Ordering::Relaxed), 0);
+    unsafe { drop_in_place(arena_nn.as_ptr()) };
+  }
+
+  #[test]
+  fn reset_reuses_chunks() {
+    let layout = Layout::from_size_align(page_size(), 8).unwrap();
+    let mut arena_nn = unsafe { Arena::new(ArenaId(10), page_size()).expect("arena") };
+    let arena = unsafe { arena_nn.as_mut() };
+
+    // Enough slab and extent headers to spill past the arena's own chunk.
+    let fill = |arena: &Arena| {
+      let small: Vec<_> = (0..NSCLASSES)
+        .map(|i| arena.allocate(ScIdx(i)).expect("alloc"))
+        .collect();
+      let large: Vec<_> = (0..128)
+        .map(|_| arena.allocate_large(layout).expect("large"))
+        .collect();
+      (small, large)
+    };
+
+    let (small, large) = fill(arena);
+    let chunks = arena.bump.lock().chunk_count();
+    assert!(chunks > 1);
+
+    unsafe { arena.reset() }.expect("reset");
+    assert!(!arena.owns(small[0]) && !arena.owns(large[0]));
+    assert_eq!(ARENA_MAP.lookup(large[0].as_ptr() as usize), None);
+
+    let (small, large) = fill(arena);
+    assert!(arena.owns(small[0]) && arena.owns(large[0]));
+    assert_eq!(arena.bump.lock().chunk_count(), chunks);
+
+    unsafe { drop_in_place(arena_nn.as_ptr()) };
+  }
+
+  #[test]
+  fn validate_catches_an_unmapped_extent() {
+    let arena_nn = unsafe { Arena::new(ArenaId(17), CHUNK_SIZE).expect("arena") };
+    let arena = unsafe { arena_nn.as_ref() };
+    let small = arena.allocate(ScIdx(9)).expect("alloc");
+    let large = arena.allocate_large(Layout::from_size_align(3 * page_size(), 8).unwrap());
+    let large = large.expect("large");
+    assert_eq!(arena.validate(), Ok(()));
+
+    let Some(OwnerInfo::Extent { extent }) = arena.etree().lookup(large.as_ptr() as usize) else {
+      panic!("large allocation isn't an extent");
+    };
+    ARENA_MAP.detach(extent).expect("detach");
+    assert_eq!(
+      arena.validate(),
+      Err("arena map disagrees with an arena's extent tree")
+    );
+
+    ARENA_MAP
+      .associate(extent, arena.index())
+      .expect("associate");
+    arena.deallocate(large).expect("dealloc large");
+    arena.deallocate(small).expect("dealloc");
+    unsafe { drop_in_place(arena_nn.as_ptr()) };
+  }
+
+  #[test]
+  fn remote_free_is_reclaimed_by_owner() {
+    let arena_nn = unsafe { Arena::new(ArenaId(11), CHUNK_SIZE).expect("arena") };
+    let arena = unsafe { arena_nn.as_ref() };
+    let sc = ScIdx(12);
+
+    let freed = arena.allocate(sc).expect("alloc");
+    let kept = arena.allocate(sc).expect("alloc");
+
+    let addr = freed.as_ptr() as usize;
+    std::thread::scope(|scope| {
+      scope.spawn(|| {
+        let ptr = NonNull::new(addr as *mut u8).unwrap();
+        arena.free_remote(ptr).expect("remote free");
+      });
+    });
+    assert!(!arena.remote.is_empty());
+
+    let again = arena.allocate(sc).expect("alloc");
+    assert_eq!(again, freed);
+    assert!(arena.remote.is_empty());
+
+    arena.deallocate(again).expect("dealloc");
+    arena.deallocate(kept).expect("dealloc");
+    unsafe { drop_in_place(arena_nn.as_ptr()) };
+  }
+
+  #[test]
+  fn fixed_arena_survives_random_traffic() {
+    const BUF: usize = 1 << 20;
+    let buf: &'static mut [u8] = Box::leak(vec![0u8; BUF].into_boxed_slice());
+    let range = buf.as_ptr() as usize..buf.as_ptr() as usize + BUF;
+    let arena_nn = Arena::new_fixed(buf).expect("fixed arena");
+    let arena = unsafe { arena_nn.as_ref() };
+
+    let mut state = 0x9E37_79B9_7F4A_7C15u64;
+    let mut next = move || {
+      state ^= state << 13;
+      state ^= state >> 7;
+      state ^= state << 17;
+      state as usize
+    };
+
+    let mut live: Vec<(NonNull<u8>, u8)> = Vec::new();
+    for step in 0..20_000 {
+      if live.is_empty() || next() % 3 != 0 {
+        let tag = step as u8;
+        let ptr = match next() % 64 {
+          0 => arena.allocate_large(Layout::from_size_align(next() % 20_000 + 1, 8).unwrap()),
+          _ => arena.allocate(ScIdx(next() % 24)),
+        };
+        // Running dry is expected; it just has to fail cleanly.
+        let Ok(ptr) = ptr else { continue };
+        assert!(range.contains(&(ptr.as_ptr() as usize)));
+        unsafe { ptr.as_ptr().write(tag) };
+        live.push((ptr, tag));
+      } else {
+        let (ptr, tag) = live.swap_remove(next() % live.len());
+        assert_eq!(unsafe { ptr.as_ptr().read() }, tag);
+        arena.deallocate(ptr).expect("dealloc");
+      }
+    }
+
+    assert!(
+      arena
+        .allocate_large(Layout::from_size_align(BUF, 8).unwrap())
+        .is_err()
+    );
+    for (ptr, _) in live {
+      arena.deallocate(ptr).expect("dealloc");
+    }
+    unsafe { drop_in_place(arena_nn.as_ptr()) };
+  }
+
+  #[test]
+  fn concurrent_allocate_same_class() {
+    const THREADS: usize = 8;
+    const PER_THREAD: usize = 600;
+
+    let arena_nn = unsafe { Arena::new(ArenaId(8), CHUNK_SIZE).expect("arena") };
+    let arena = unsafe { arena_nn.as_ref() };
+
+    std::thread::scope(|scope| {
+      for tag in 0..THREADS as u8 {
+        scope.spawn(move || {
+          for sc in [ScIdx(2), ScIdx(40)] {
+            let size = crate::classes::class_at(sc).0;
+            let slots: Vec<_> = (0..PER_THREAD)
+              .map(|_| arena.allocate(sc).expect("alloc"))
+              .inspect(|p| unsafe { p.as_ptr().write_bytes(tag, size) })
+              .collect();
+            for p in slots {
+              let bytes = unsafe { core::slice::from_raw_parts(p.as_ptr(), size) };
+              assert!(
+                bytes.iter().all(|b| *b == tag),
+                "slot shared between threads"
+              );
+              arena.deallocate(p).expect("dealloc");
+            }
+          }
+        });
+      }
+    });
+
+    unsafe { drop_in_place(arena_nn.as_ptr()) };
+  }
+
+  #[test]
+  fn concurrent_large_frees_into_one_arena() {
+    const THREADS: usize = 8;
+    const PER_THREAD: usize = 16;
+    let layout = Layout::from_size_align(3 * page_size(), 8).unwrap();
+
+    let arena_nn = unsafe { Arena::new(ArenaId(9), CHUNK_SIZE).expect("arena") };
+    let arena = unsafe { arena_nn.as_ref() };
+    let batches: Vec<Vec<usize>> = (0..THREADS)
+      .map(|tag| {
+        (0..PER_THREAD)
+          .map(|_| arena.allocate_large(layout).expect("alloc"))
+          .inspect(|p| unsafe { p.as_ptr().write_bytes(tag as u8, layout.size()) })
+          .map(|p| p.as_ptr() as usize)
+          .collect()
+      })
+      .collect();
+
+    std::thread::scope(|scope| {
+      for (tag, batch) in batches.iter().enumerate() {
+        scope.spawn(move || {
+          for addr in batch {
+            let bytes = unsafe { core::slice::from_raw_parts(*addr as *const u8, layout.size()) };
+            assert!(bytes.iter().all(|b| *b == tag as u8), "extent corrupted");
+            let ptr = NonNull::new(*addr as *mut u8).unwrap();
+            arena.deallocate(ptr).expect("dealloc");
+          }
+        });
+      }
+    });
+
+    for addr in batches.iter().flatten() {
+      assert!(arena.etree().lookup(*addr).is_none());
+    }
+    assert!(arena.etree().any_extent().is_none());
+    unsafe { drop_in_place(arena_nn.as_ptr()) };
+  }
+
+  #[test]
+  fn fragmentation_falls_after_compaction() {
+    let arena_nn = unsafe { Arena::new(ArenaId(24), CHUNK_SIZE).expect("arena") };
+    let arena = unsafe { arena_nn.as_ref() };
+    let sc = ScIdx(36);
+    let regions = crate::classes::pages_for(sc).0 / crate::classes::class_at(sc).0;
+    assert_eq!(arena.usage(), ArenaUsage::default());
+
+    // Four full slabs, then every other slot of each freed.
+    let slots: Vec<_> = (0..4 * regions)
+      .map(|_| arena.allocate(sc).expect("alloc"))
+      .collect();
+    let mut live = Vec::new();
+    for (i, p) in slots.into_iter().enumerate() {
+      match i % 2 {
+        0 => live.push(p),
+        _ => arena.deallocate(p).expect("dealloc"),
+      }
+    }
+    let sparse = arena.usage().fragmentation();
+    assert!(sparse > 0.4, "sparse {}", sparse);
+
+    let moved = core::cell::RefCell::new(live);
+    let relocate = |old, new| {
+      let mut live = moved.borrow_mut();
+      *live.iter_mut().find(|p| **p == old).expect("tracked") = new;
+    };
+    // Slots went straight to the arena, never through a thread cache.
+    unsafe { arena.bins[sc.0].lock().compact(relocate) }.expect("compact");
+    let compact = arena.usage().fragmentation();
+    assert!(compact < 0.1, "compact {}", compact);
+
+    for p in moved.into_inner() {
+      arena.deallocate(p).expect("dealloc");
+    }
+    unsafe { drop_in_place(arena_nn.as_ptr()) };
+  }
+
+  #[test]
+  fn every_bin_starts_with_its_class() {
+    let arena_nn = unsafe { Arena::new(ArenaId(25), CHUNK_SIZE).expect("arena") };
+    let arena = unsafe { arena_nn.as_ref() };
+
+    for (i, bin) in arena.bins.iter().enumerate() {
+      let bin = bin.lock();
+      assert_eq!(bin.class(), crate::classes::class_at(ScIdx(i)));
+      assert_eq!(bin.pages(), crate::classes::pages_for(ScIdx(i)));
+      assert!(bin.is_empty());
+      assert_eq!(bin.validate(), Ok(()));
+    }
+    assert_eq!(arena.index(), ArenaId(25));
+    assert_eq!(arena.load(), 0);
+    unsafe { drop_in_place(arena_nn.as_ptr()) };
+  }
 }
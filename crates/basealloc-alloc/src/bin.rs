@@ -1,6 +1,11 @@
 use core::ptr::NonNull;
 
-use basealloc_extent::ExtentError;
+use basealloc_extent::{
+  Extent,
+  ExtentError,
+  SharedCell,
+  SharedExtent,
+};
 use basealloc_fixed::bump::{
   Bump,
   BumpError,
@@ -8,10 +13,17 @@ use basealloc_fixed::bump::{
 use basealloc_list::{
   HasLink,
   List,
+  ListError,
+  ListIter,
 };
+use basealloc_sys::system::SysOption;
+use spin::Mutex;
 
 use crate::{
-  arena::Arena,
+  arena::{
+    Arena,
+    SlabMode,
+  },
   classes::{
     ScIdx,
     SizeClass,
@@ -22,6 +34,7 @@ use crate::{
   slab::{
     Slab,
     SlabError,
+    SlabResult,
   },
 };
 
@@ -52,35 +65,155 @@ impl From<ExtentError> for BinError {
 
 pub type BinResult<T> = Result<T, BinError>;
 
+// How a slot is taken from the chosen slab: `Slab::allocate` or `Slab::allocate_zeroed`.
+type TakeSlot = fn(&mut Slab) -> SlabResult<NonNull<u8>>;
+
+fn list_fault(err: ListError) -> &'static str {
+  match err {
+    ListError::Cycle => "bin slab list has a cycle",
+    ListError::BrokenLink => "bin slab list has a broken back link",
+  }
+}
+
+/// Empty slabs a bin parks for reuse before it starts destroying them.
+pub const KEEP_FREE_SLABS: usize = 4;
+
+/// Bytes a bin reserves at once for the slabs it carves under `SlabMode::SubSlab`; a
+/// region always holds at least one slab.
+pub const SLAB_GROUP_SIZE: usize = 1 << 20;
+
+// A `SharedCell` in the arena's bump, on the list of those the bin ever used.
+struct GroupCell {
+  cell: SharedCell,
+  next: Option<NonNull<GroupCell>>,
+}
+
+/// Buckets of `Bin::occupancy_histogram`: ten of 10% each, then one for full slabs.
+pub const OCCUPANCY_BUCKETS: usize = 11;
+
 pub struct Bin {
   // SAFETY: User must ensure bin is dropped before bump.
   class: SizeClass,
   pages: SlabPages,
   free_head: Option<NonNull<Slab>>,
+  free_count: usize,
+  keep_free: usize,
   active_head: Option<NonNull<Slab>>,
   active_tail: Option<NonNull<Slab>>,
+  // Slots handed out across all slabs, so emptiness needs no walk.
+  live_count: usize,
+  // The shared region slabs are carved from, and how many of its bytes are taken.
+  group: Option<SharedExtent>,
+  carved: usize,
+  // Cells of past and present regions; one without handles is free for the next region.
+  cells: Option<NonNull<GroupCell>>,
+  // Headers of destroyed slabs, without pages; new slabs reuse them before the bump.
+  husks: Option<NonNull<Slab>>,
 }
 
+// Slabs are only touched with the owning bin's lock held.
+unsafe impl Send for Bin {}
+
 impl Bin {
   pub fn new(idx: ScIdx) -> Self {
     Self {
       class: class_at(idx),
       pages: pages_for(idx),
       free_head: None,
+      free_count: 0,
+      keep_free: KEEP_FREE_SLABS,
       active_head: None,
       active_tail: None,
+      group: None,
+      carved: 0,
+      cells: None,
+      husks: None,
+      live_count: 0,
+    }
+  }
+
+  pub fn class(&self) -> SizeClass {
+    self.class
+  }
+
+  /// Bytes of each slab the bin creates.
+  pub fn pages(&self) -> SlabPages {
+    self.pages
+  }
+
+  /// Slots handed out and not yet freed, over every slab of the bin.
+  pub fn live_count(&self) -> usize {
+    self.live_count
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.live_count == 0
+  }
+
+  /// Bytes of the live slots, at their class size.
+  pub fn live_bytes(&self) -> usize {
+    self.live_count * self.class.0
+  }
+
+  /// Bytes of the active slabs that are committed; parked slabs were given back.
+  pub fn committed_bytes(&self) -> usize {
+    ListIter::new(self.active_head)
+      .map(|slab| slab.extent())
+      .filter(|extent| extent.is_activated())
+      .map(|extent| extent.size())
+      .sum()
+  }
+
+  /// Active slabs by fill: bucket `i` counts those with at least `10 * i` but under
+  /// `10 * (i + 1)` percent of their slots live, so only full slabs land in the last.
+  ///
+  /// Weight in the low buckets means compacting the bin would free whole slabs.
+  pub fn occupancy_histogram(&self) -> [usize; OCCUPANCY_BUCKETS] {
+    let mut buckets = [0; OCCUPANCY_BUCKETS];
+    for slab in ListIter::new(self.active_head) {
+      let bucket = slab.live_count() * 10 / slab.capacity().max(1);
+      buckets[bucket.min(OCCUPANCY_BUCKETS - 1)] += 1;
     }
+    buckets
   }
 
-  fn alloc_fast(&mut self) -> Option<NonNull<u8>> {
+  /// Caps how many empty slabs stay parked; later ones are unmapped on retirement.
+  pub fn set_keep_free(&mut self, keep: usize) {
+    self.keep_free = keep;
+  }
+
+  #[cfg(not(feature = "best-fit"))]
+  fn alloc_fast(&mut self, take: TakeSlot) -> Option<NonNull<u8>> {
     let active_ptr = self.active_head?;
     let active_slab = unsafe { active_ptr.as_ptr().as_mut().unwrap() };
-    active_slab.allocate().ok()
+    take(active_slab).ok()
+  }
+
+  #[cfg(feature = "best-fit")]
+  fn alloc_fast(&mut self, take: TakeSlot) -> Option<NonNull<u8>> {
+    let mut best = self.best_fit()?;
+    take(unsafe { best.as_mut() }).ok()
+  }
+
+  /// The fullest active slab that still has a free slot.
+  ///
+  /// Walks the whole active list, trading allocation speed for fewer partial slabs.
+  #[cfg_attr(not(feature = "best-fit"), allow(dead_code))]
+  fn best_fit(&self) -> Option<NonNull<Slab>> {
+    self.best_fit_except(None)
+  }
+
+  fn best_fit_except(&self, skip: Option<NonNull<Slab>>) -> Option<NonNull<Slab>> {
+    ListIter::new(self.active_head)
+      .filter(|slab| !slab.is_full() && Some(NonNull::from(&**slab)) != skip)
+      .max_by_key(|slab| slab.live_count())
+      .map(|slab| NonNull::from(&*slab))
   }
 
   fn pop_free(&mut self) -> Option<NonNull<Slab>> {
     let free_ptr = self.free_head.take()?;
     let free_slab = unsafe { free_ptr.as_ptr().as_mut().unwrap() };
+    self.free_count -= 1;
 
     self.free_head = if let Some(next) = free_slab.link().next() {
       List::remove(free_slab);
@@ -101,8 +234,109 @@ impl Bin {
     Some(free_ptr)
   }
 
-  fn push_new(&mut self, bump: &mut Bump, arena: NonNull<Arena>) -> BinResult<NonNull<Slab>> {
-    let new_slab = Slab::new(bump, self.class, self.pages.0, arena)?;
+  // The next slab's extent, a view of the current shared region or a fresh one, with a
+  // handle that keeps the region mapped while the slab lives.
+  fn carve(&mut self, bump: &Mutex<Bump>, arena: &Arena) -> BinResult<(Extent, SharedExtent)> {
+    let size = self.pages.0;
+    let group = match self.group.take() {
+      Some(group) if group.size() - self.carved >= size => group,
+      _ => {
+        self.carved = 0;
+        self.new_group(bump, arena)?
+      }
+    };
+    let view = unsafe { group.view(self.carved..self.carved + size) };
+    let handle = group.clone();
+    self.group = Some(group);
+    let view = view?;
+    self.carved += size;
+    Ok((view, handle))
+  }
+
+  fn new_group(&mut self, bump: &Mutex<Bump>, arena: &Arena) -> BinResult<SharedExtent> {
+    let size = self.pages.0;
+    let len = (SLAB_GROUP_SIZE / size).max(1) * size;
+    let extent = Extent::new_in(len, SysOption::Reserve, arena.system())?;
+    Ok(SharedExtent::new(extent, self.free_cell(bump)?)?)
+  }
+
+  fn free_cell(&mut self, bump: &Mutex<Bump>) -> BinResult<&'static SharedCell> {
+    let mut next = self.cells;
+    while let Some(group_cell) = next {
+      // SAFETY: cells live in the arena's bump, which outlives the bin.
+      let group_cell = unsafe { group_cell.as_ref() };
+      if group_cell.cell.refs() == 0 {
+        return Ok(&group_cell.cell);
+      }
+      next = group_cell.next;
+    }
+
+    let group_cell = bump.lock().create_with(GroupCell {
+      cell: SharedCell::new(),
+      next: self.cells,
+    })?;
+    self.cells = Some(group_cell);
+    Ok(unsafe { &group_cell.as_ref().cell })
+  }
+
+  // The pages go back, with the shared region once no slab uses it; the header waits
+  // for the next new slab.
+  fn bury(&mut self, slab: NonNull<Slab>, slab_ref: &mut Slab) {
+    unsafe { Slab::unmap(slab) };
+    if self.group.as_ref().is_some_and(|group| group.refs() == 1) {
+      self.group = None;
+    }
+    if let Some(mut husk) = self.husks {
+      List::insert_before(slab_ref, unsafe { husk.as_mut() });
+    }
+    self.husks = Some(slab);
+  }
+
+  fn pop_husk(&mut self) -> Option<NonNull<Slab>> {
+    let husk = self.husks.take()?;
+    let husk_ref = unsafe { husk.as_ptr().as_mut().unwrap() };
+    self.husks = husk_ref.link().next();
+    List::remove(husk_ref);
+    Some(husk)
+  }
+
+  // A slab over `extent`, in a husk if there is one.
+  fn slab_over(
+    &mut self,
+    bump: &Mutex<Bump>,
+    extent: Extent,
+    group: Option<SharedExtent>,
+    arena: NonNull<Arena>,
+  ) -> BinResult<NonNull<Slab>> {
+    let mut slab = match self.pop_husk() {
+      Some(husk) => Self::refill(husk, extent)?,
+      None => Slab::new_in(&mut bump.lock(), self.class, extent, arena)?,
+    };
+    unsafe { slab.as_mut() }.hold_group(group);
+    Ok(slab)
+  }
+
+  fn refill(husk: NonNull<Slab>, extent: Extent) -> BinResult<NonNull<Slab>> {
+    if let Err(err) = unsafe { Slab::refill(husk, extent) } {
+      // It holds `extent` again, so it tears down like any slab.
+      unsafe { core::ptr::drop_in_place(husk.as_ptr()) };
+      return Err(err.into());
+    }
+    Ok(husk)
+  }
+
+  fn push_new(&mut self, bump: &Mutex<Bump>, arena: NonNull<Arena>) -> BinResult<NonNull<Slab>> {
+    let arena_ref = unsafe { arena.as_ref() };
+    let (extent, group) = match arena_ref.slab_mode() {
+      SlabMode::Separate => {
+        let extent = Extent::new_in(self.pages.0, SysOption::Reserve, arena_ref.system())?;
+        (extent, None)
+      }
+      SlabMode::SubSlab => self
+        .carve(bump, arena_ref)
+        .map(|(view, handle)| (view, Some(handle)))?,
+    };
+    let new_slab = self.slab_over(bump, extent, group, arena)?;
     let slab_mut = unsafe { new_slab.as_ptr().as_mut().unwrap() };
 
     if let Some(active_head_ptr) = self.active_head {
@@ -118,44 +352,138 @@ impl Bin {
   }
 
   fn retire_slab(&mut self, slab: NonNull<Slab>, slab_ref: &mut Slab) -> BinResult<()> {
+    // `remove` clears the links, so read the neighbours first.
+    let (prev, next) = (slab_ref.link().prev(), slab_ref.link().next());
     List::remove(slab_ref);
 
     if Some(slab) == self.active_head {
-      self.active_head = slab_ref.link().next();
+      self.active_head = next;
     }
     if Some(slab) == self.active_tail {
-      self.active_tail = slab_ref.link().prev();
+      self.active_tail = prev;
+    }
+
+    if self.free_count >= self.keep_free {
+      self.bury(slab, slab_ref);
+      return Ok(());
     }
 
     slab_ref.extent_mut().deactivate()?;
+    self.park_free(slab, slab_ref);
+    Ok(())
+  }
 
+  fn park_free(&mut self, slab: NonNull<Slab>, slab_ref: &mut Slab) {
     if let Some(mut free_head_ptr) = self.free_head {
       let free_head_slab = unsafe { free_head_ptr.as_mut() };
       List::insert_before(slab_ref, free_head_slab);
     }
     self.free_head = Some(slab);
+    self.free_count += 1;
+  }
 
-    Ok(())
+  pub fn allocate(&mut self, bump: &Mutex<Bump>, arena: NonNull<Arena>) -> BinResult<NonNull<u8>> {
+    self.allocate_by(bump, arena, Slab::allocate)
   }
 
-  pub fn allocate(&mut self, bump: &mut Bump, arena: NonNull<Arena>) -> BinResult<NonNull<u8>> {
-    if let Some(ptr) = self.alloc_fast() {
+  /// Like `allocate`, but the slot reads as zero; see `Slab::allocate_zeroed`.
+  pub fn allocate_zeroed(
+    &mut self,
+    bump: &Mutex<Bump>,
+    arena: NonNull<Arena>,
+  ) -> BinResult<NonNull<u8>> {
+    self.allocate_by(bump, arena, Slab::allocate_zeroed)
+  }
+
+  fn allocate_by(
+    &mut self,
+    bump: &Mutex<Bump>,
+    arena: NonNull<Arena>,
+    take: TakeSlot,
+  ) -> BinResult<NonNull<u8>> {
+    let ptr = self.take_slot(bump, arena, take)?;
+    self.live_count += 1;
+    Ok(ptr)
+  }
+
+  fn take_slot(
+    &mut self,
+    bump: &Mutex<Bump>,
+    arena: NonNull<Arena>,
+    take: TakeSlot,
+  ) -> BinResult<NonNull<u8>> {
+    if let Some(ptr) = self.alloc_fast(take) {
       return Ok(ptr);
     }
 
     if let Some(slab) = self.pop_free() {
       let slab_mut = unsafe { slab.as_ptr().as_mut().unwrap() };
-      return Ok(slab_mut.allocate()?);
+      return Ok(take(slab_mut)?);
     }
 
     let new_slab = self.push_new(bump, arena)?;
     let slab_mut = unsafe { new_slab.as_ptr().as_mut().unwrap() };
-    Ok(slab_mut.allocate()?)
+    Ok(take(slab_mut)?)
+  }
+
+  /// Hands out `n` adjacent slots from one slab; see `Slab::allocate_run`.
+  ///
+  /// Only the active slab is tried: when fragmentation leaves it without such a run, the
+  /// run goes to a parked or fresh slab instead.
+  pub fn allocate_run(
+    &mut self,
+    bump: &Mutex<Bump>,
+    arena: NonNull<Arena>,
+    n: usize,
+  ) -> BinResult<NonNull<u8>> {
+    let ptr = self.take_run(bump, arena, n)?;
+    self.live_count += n;
+    Ok(ptr)
+  }
+
+  fn take_run(
+    &mut self,
+    bump: &Mutex<Bump>,
+    arena: NonNull<Arena>,
+    n: usize,
+  ) -> BinResult<NonNull<u8>> {
+    if let Some(mut active) = self.active_head {
+      match unsafe { active.as_mut() }.allocate_run(n) {
+        Err(SlabError::OutOfMemory) => {}
+        taken => return Ok(taken?),
+      }
+    }
+
+    let slab = match self.pop_free() {
+      Some(slab) => slab,
+      None => self.push_new(bump, arena)?,
+    };
+    let slab_mut = unsafe { slab.as_ptr().as_mut().unwrap() };
+    Ok(slab_mut.allocate_run(n)?)
+  }
+
+  /// Frees a block `allocate_run(n)` handed out from `slab`.
+  pub fn deallocate_run(
+    &mut self,
+    ptr: NonNull<u8>,
+    mut slab: NonNull<Slab>,
+    n: usize,
+  ) -> BinResult<()> {
+    let slab_ref = unsafe { slab.as_mut() };
+    slab_ref.deallocate_run(ptr, n)?;
+    self.live_count -= n;
+
+    if slab_ref.is_empty() {
+      self.retire_slab(slab, slab_ref)?;
+    }
+
+    Ok(())
   }
 
   pub fn deallocate(&mut self, ptr: NonNull<u8>, mut slab: NonNull<Slab>) -> BinResult<()> {
     let slab_ref = unsafe { slab.as_mut() };
     slab_ref.deallocate(ptr)?;
+    self.live_count -= 1;
 
     if slab_ref.is_empty() {
       self.retire_slab(slab, slab_ref)?;
@@ -163,6 +491,111 @@ impl Bin {
 
     Ok(())
   }
+
+  /// Empties sparse slabs into denser ones; returns how many slabs it emptied.
+  ///
+  /// The allocator can't rewrite the caller's pointers, so every moved object is copied
+  /// into a slot of a fuller slab and reported as `relocate(old, new)` before its old slot
+  /// is freed; the caller must switch to `new` there. Emptied slabs retire as on `deallocate`.
+  ///
+  /// # Safety
+  ///
+  /// No slot of this bin may be parked in a thread cache or in the arena's remote-free
+  /// queue. Both count as live, so they would be moved too, and the stale address handed
+  /// out or freed later; flush every cache (`TCache::flush_all`) and drain the queue first.
+  pub unsafe fn compact(
+    &mut self,
+    relocate: impl Fn(NonNull<u8>, NonNull<u8>),
+  ) -> BinResult<usize> {
+    let mut emptied = 0;
+    while let Some(source) = self.evacuee() {
+      self.evacuate(source, &relocate)?;
+      emptied += 1;
+    }
+    Ok(emptied)
+  }
+
+  /// Hands the free pages of active slabs back to the system, slab by slab, until at
+  /// least `budget` pages went back or every slab was visited. Returns the pages reclaimed.
+  pub fn reclaim_pages(&mut self, budget: usize) -> BinResult<usize> {
+    let mut reclaimed = 0;
+    for slab in ListIter::new(self.active_head) {
+      if reclaimed >= budget {
+        break;
+      }
+      reclaimed += slab.reclaim_empty_pages()?;
+    }
+    Ok(reclaimed)
+  }
+
+  // The sparsest active slab, as long as the other ones have room for all it holds.
+  fn evacuee(&self) -> Option<NonNull<Slab>> {
+    let sparsest = ListIter::new(self.active_head).min_by_key(|slab| slab.live_count())?;
+    let sparsest = NonNull::from(&*sparsest);
+    let spare: usize = ListIter::new(self.active_head)
+      .filter(|slab| NonNull::from(&**slab) != sparsest)
+      .map(|slab| slab.spare())
+      .sum();
+    let live = unsafe { sparsest.as_ref() }.live_count();
+    (spare >= live).then_some(sparsest)
+  }
+
+  fn evacuate(
+    &mut self,
+    mut source: NonNull<Slab>,
+    relocate: &impl Fn(NonNull<u8>, NonNull<u8>),
+  ) -> BinResult<()> {
+    let source_ref = unsafe { source.as_mut() };
+    loop {
+      let Some(old) = source_ref.live_slots().next() else {
+        break;
+      };
+      let mut target = self
+        .best_fit_except(Some(source))
+        .ok_or(SlabError::OutOfMemory)?;
+      let new = unsafe { target.as_mut() }.allocate()?;
+      unsafe { core::ptr::copy_nonoverlapping(old.as_ptr(), new.as_ptr(), self.class.0) };
+      relocate(old, new);
+      source_ref.deallocate(old)?;
+    }
+    self.retire_slab(source, source_ref)
+  }
+}
+
+#[cfg(test)]
+impl Bin {
+  /// Allocates from the active slab until it is full; the next allocation needs another.
+  ///
+  /// Returns the slots it took, none if there is no active slab yet.
+  pub fn fill_current_slab(&mut self) -> Vec<NonNull<u8>> {
+    let Some(mut active) = self.active_head else {
+      return Vec::new();
+    };
+    let slab = unsafe { active.as_mut() };
+    let slots: Vec<_> = core::iter::from_fn(|| slab.allocate().ok()).collect();
+    self.live_count += slots.len();
+    slots
+  }
+}
+
+impl Bin {
+  /// Checks both slab lists, the counts kept about them and every slab on them.
+  pub fn validate(&self) -> Result<(), &'static str> {
+    List::validate(self.active_head).map_err(list_fault)?;
+    let free = List::validate(self.free_head).map_err(list_fault)?;
+    if free != self.free_count {
+      return Err("bin free slab count doesn't match its free list");
+    }
+
+    let last = ListIter::new(self.active_head).last();
+    if last.map(|slab| NonNull::from(&*slab)) != self.active_tail {
+      return Err("bin active tail isn't the last active slab");
+    }
+
+    ListIter::new(self.active_head)
+      .chain(ListIter::new(self.free_head))
+      .try_for_each(|slab| slab.validate())
+  }
 }
 
 impl Drop for Bin {
@@ -180,3 +613,460 @@ impl Drop for Bin {
     }
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use core::ptr::drop_in_place;
+
+  use basealloc_sys::{
+    GLOBAL_SYSTEM,
+    system::{
+      SysOption,
+      SysResult,
+      System,
+    },
+  };
+
+  use super::*;
+  use crate::{
+    CHUNK_SIZE,
+    arena::{
+      ArenaId,
+      CommitPolicy,
+    },
+    lookup::{
+      ExtentTree,
+      OwnerInfo,
+    },
+  };
+
+  // An arena that maps its slabs through `system`, leaving the global backend alone.
+  fn arena_over(id: usize, system: &'static dyn System) -> NonNull<Arena> {
+    let (bump, etree) = (Bump::new(CHUNK_SIZE), ExtentTree::new(CHUNK_SIZE));
+    unsafe { Arena::build(ArenaId(id), bump, etree, Some(system), CommitPolicy::Lazy) }
+      .expect("arena")
+  }
+
+  fn slab_of(arena: &Arena, ptr: NonNull<u8>) -> NonNull<Slab> {
+    match arena.etree().lookup(ptr.as_ptr() as usize) {
+      Some(OwnerInfo::Slab { slab, .. }) => slab,
+      _ => panic!("not a slab pointer"),
+    }
+  }
+
+  struct UnmapRecorder {
+    unmapped: Mutex<Vec<usize>>,
+  }
+
+  unsafe impl System for UnmapRecorder {
+    unsafe fn alloc<'mem>(&self, size: usize, options: SysOption) -> SysResult<&'mem mut [u8]> {
+      unsafe { GLOBAL_SYSTEM.alloc(size, options) }
+    }
+
+    unsafe fn modify(&self, slice: &[u8], options: SysOption) -> SysResult<()> {
+      unsafe { GLOBAL_SYSTEM.modify(slice, options) }
+    }
+
+    unsafe fn dealloc(&self, slice: &[u8]) -> SysResult<()> {
+      self.unmapped.lock().push(slice.as_ptr() as usize);
+      unsafe { GLOBAL_SYSTEM.dealloc(slice) }
+    }
+  }
+
+  static RECORDER: UnmapRecorder = UnmapRecorder {
+    unmapped: Mutex::new(Vec::new()),
+  };
+  static RECORDER_SYSTEM: &dyn System = &RECORDER;
+
+  struct MapRecorder {
+    mapped: Mutex<Vec<(usize, usize)>>,
+  }
+
+  unsafe impl System for MapRecorder {
+    unsafe fn alloc<'mem>(&self, size: usize, options: SysOption) -> SysResult<&'mem mut [u8]> {
+      let slice = unsafe { GLOBAL_SYSTEM.alloc(size, options) }?;
+      self.mapped.lock().push((slice.as_ptr() as usize, size));
+      Ok(slice)
+    }
+
+    unsafe fn modify(&self, slice: &[u8], options: SysOption) -> SysResult<()> {
+      unsafe { GLOBAL_SYSTEM.modify(slice, options) }
+    }
+
+    unsafe fn dealloc(&self, slice: &[u8]) -> SysResult<()> {
+      unsafe { GLOBAL_SYSTEM.dealloc(slice) }
+    }
+  }
+
+  static MAPS: MapRecorder = MapRecorder {
+    mapped: Mutex::new(Vec::new()),
+  };
+  static MAPS_SYSTEM: &dyn System = &MAPS;
+
+  #[test]
+  fn retire_destroys_slabs_past_keep() {
+    let arena_nn = arena_over(13, RECORDER_SYSTEM);
+    let arena = unsafe { arena_nn.as_ref() };
+    let bump = Mutex::new(Bump::new(CHUNK_SIZE));
+    let sc = ScIdx(37);
+    let regions = pages_for(sc).0 / class_at(sc).0;
+    let mut bin = Bin::new(sc);
+    bin.set_keep_free(1);
+
+    let slots: Vec<_> = (0..3 * regions)
+      .map(|_| bin.allocate(&bump, arena_nn).expect("alloc"))
+      .collect();
+
+    let bases: Vec<usize> = (0..3)
+      .map(|i| unsafe { slab_of(arena, slots[i * regions]).as_ref() })
+      .map(|slab| slab.extent().as_ref().as_ptr() as usize)
+      .collect();
+    for p in slots {
+      bin.deallocate(p, slab_of(arena, p)).expect("dealloc");
+    }
+
+    let unmapped = RECORDER.unmapped.lock();
+    assert_eq!(bin.free_count, 1);
+    assert!(!unmapped.contains(&bases[0]));
+    assert!(unmapped.contains(&bases[1]) && unmapped.contains(&bases[2]));
+    drop(unmapped);
+
+    drop(bin);
+    unsafe { drop_in_place(arena_nn.as_ptr()) };
+  }
+
+  #[test]
+  fn destroyed_slab_headers_are_reused() {
+    let arena_nn = unsafe { Arena::new(ArenaId(30), CHUNK_SIZE).expect("arena") };
+    let arena = unsafe { arena_nn.as_ref() };
+    let bump = Mutex::new(Bump::new(CHUNK_SIZE));
+    let sc = ScIdx(38);
+    let regions = pages_for(sc).0 / class_at(sc).0;
+    let mut bin = Bin::new(sc);
+    bin.set_keep_free(0);
+
+    // Every round maps a fresh slab and destroys it again, under the same header.
+    let mut header = None;
+    for _ in 0..8 {
+      let slots: Vec<_> = (0..regions)
+        .map(|_| bin.allocate(&bump, arena_nn).expect("alloc"))
+        .collect();
+      let (first, slab) = (slots[0], slab_of(arena, slots[0]));
+      assert!(header.is_none_or(|header| header == slab));
+      header = Some(slab);
+      for p in slots {
+        bin.deallocate(p, slab).expect("dealloc");
+      }
+      assert!(!arena.owns(first));
+    }
+    assert_eq!(bin.husks, header);
+    assert_eq!(bin.free_count, 0);
+
+    drop(bin);
+    unsafe { drop_in_place(arena_nn.as_ptr()) };
+  }
+
+  #[test]
+  fn best_fit_picks_fullest_slab_with_space() {
+    let arena_nn = unsafe { Arena::new(ArenaId(12), CHUNK_SIZE).expect("arena") };
+    let arena = unsafe { arena_nn.as_ref() };
+    let bump = Mutex::new(Bump::new(CHUNK_SIZE));
+    let sc = ScIdx(36);
+    let regions = pages_for(sc).0 / class_at(sc).0;
+    let mut bin = Bin::new(sc);
+
+    let mut slots: Vec<_> = (0..2 * regions + 1)
+      .map(|_| bin.allocate(&bump, arena_nn).expect("alloc"))
+      .collect();
+    let (first, second) = (slab_of(arena, slots[0]), slab_of(arena, slots[regions]));
+    assert!(bin.best_fit() != Some(first) && bin.best_fit() != Some(second));
+
+    let mut freed: Vec<_> = slots.drain(regions..regions + 3).collect();
+    freed.push(slots.remove(0));
+    for p in freed {
+      bin.deallocate(p, slab_of(arena, p)).expect("dealloc");
+    }
+    assert_eq!(bin.best_fit(), Some(first));
+
+    for p in slots {
+      bin.deallocate(p, slab_of(arena, p)).expect("dealloc");
+    }
+    drop(bin);
+    unsafe { drop_in_place(arena_nn.as_ptr()) };
+  }
+
+  // Fills three slabs of `sc`, keeps a quarter of each alive, and compacts them into one
+  // while a table indexed by object id follows the moves.
+  fn compact_into_one(sc: ScIdx, id: usize) {
+    let arena_nn = unsafe { Arena::new(ArenaId(id), CHUNK_SIZE).expect("arena") };
+    let arena = unsafe { arena_nn.as_ref() };
+    let bump = Mutex::new(Bump::new(CHUNK_SIZE));
+    let regions = pages_for(sc).0 / class_at(sc).0;
+    let mut bin = Bin::new(sc);
+
+    let slots: Vec<_> = (0..3 * regions)
+      .map(|_| bin.allocate(&bump, arena_nn).expect("alloc"))
+      .collect();
+    let mut table = Vec::new();
+    for (i, p) in slots.into_iter().enumerate() {
+      if i % 4 == 0 {
+        unsafe { p.cast::<usize>().write(table.len()) };
+        table.push(p);
+      } else {
+        bin.deallocate(p, slab_of(arena, p)).expect("dealloc");
+      }
+    }
+
+    let table = core::cell::RefCell::new(table);
+    // Nothing here goes through a thread cache or the remote queue.
+    let emptied = unsafe {
+      bin.compact(|old, new| {
+        let mut table = table.borrow_mut();
+        let entry = table
+          .iter_mut()
+          .find(|p| **p == old)
+          .expect("unknown object");
+        *entry = new;
+      })
+    }
+    .expect("compact");
+
+    let table = table.into_inner();
+    assert_eq!(emptied, 2);
+    assert_eq!(bin.free_count, 2);
+    assert_eq!(ListIter::new(bin.active_head).count(), 1);
+    let kept = slab_of(arena, table[0]);
+    for (id, p) in table.iter().enumerate() {
+      assert_eq!(unsafe { p.cast::<usize>().read() }, id);
+      assert_eq!(slab_of(arena, *p), kept);
+      bin.deallocate(*p, kept).expect("dealloc");
+    }
+    assert_eq!(bin.validate(), Ok(()));
+
+    drop(bin);
+    unsafe { drop_in_place(arena_nn.as_ptr()) };
+  }
+
+  #[test]
+  fn compact_relocates_into_the_fewest_slabs() {
+    compact_into_one(ScIdx(1), 18);
+    compact_into_one(ScIdx(36), 19);
+  }
+
+  #[test]
+  fn live_count_tracks_handed_out_slots() {
+    let arena_nn = unsafe { Arena::new(ArenaId(20), CHUNK_SIZE).expect("arena") };
+    let arena = unsafe { arena_nn.as_ref() };
+    let bump = Mutex::new(Bump::new(CHUNK_SIZE));
+    let sc = ScIdx(8);
+    let regions = pages_for(sc).0 / class_at(sc).0;
+    let mut bin = Bin::new(sc);
+    assert!(bin.is_empty());
+
+    let slots: Vec<_> = (0..regions + 3)
+      .map(|_| bin.allocate(&bump, arena_nn).expect("alloc"))
+      .collect();
+    assert_eq!(bin.live_count(), regions + 3);
+
+    for (freed, p) in slots.iter().enumerate() {
+      assert!(!bin.is_empty());
+      bin.deallocate(*p, slab_of(arena, *p)).expect("dealloc");
+      assert_eq!(bin.live_count(), regions + 2 - freed);
+    }
+    assert!(bin.is_empty());
+
+    drop(bin);
+    unsafe { drop_in_place(arena_nn.as_ptr()) };
+  }
+
+  #[test]
+  fn run_skips_a_fragmented_slab() {
+    let arena_nn = unsafe { Arena::new(ArenaId(21), CHUNK_SIZE).expect("arena") };
+    let arena = unsafe { arena_nn.as_ref() };
+    let bump = Mutex::new(Bump::new(CHUNK_SIZE));
+    let sc = ScIdx(36);
+    let regions = pages_for(sc).0 / class_at(sc).0;
+    let mut bin = Bin::new(sc);
+
+    // Every other slot stays live, so half the slab is free but no two slots are adjacent.
+    let slots: Vec<_> = (0..regions)
+      .map(|_| bin.allocate(&bump, arena_nn).expect("alloc"))
+      .collect();
+    let fragmented = slab_of(arena, slots[0]);
+    for p in slots.iter().skip(1).step_by(2) {
+      bin.deallocate(*p, fragmented).expect("dealloc");
+    }
+
+    let run = bin.allocate_run(&bump, arena_nn, 2).expect("run");
+    let fresh = slab_of(arena, run);
+    assert_ne!(fresh, fragmented);
+    assert_eq!(unsafe { fresh.as_ref() }.live_count(), 2);
+    assert_eq!(bin.live_count(), regions.div_ceil(2) + 2);
+
+    bin.deallocate_run(run, fresh, 2).expect("dealloc run");
+    for p in slots.iter().step_by(2) {
+      bin.deallocate(*p, fragmented).expect("dealloc");
+    }
+    assert!(bin.is_empty());
+    assert_eq!(bin.validate(), Ok(()));
+
+    drop(bin);
+    unsafe { drop_in_place(arena_nn.as_ptr()) };
+  }
+
+  #[test]
+  fn occupancy_histogram_buckets_by_fill() {
+    let arena_nn = unsafe { Arena::new(ArenaId(27), CHUNK_SIZE).expect("arena") };
+    let arena = unsafe { arena_nn.as_ref() };
+    let bump = Mutex::new(Bump::new(CHUNK_SIZE));
+    let mut bin = Bin::new(ScIdx(2));
+    assert_eq!(bin.occupancy_histogram(), [0; OCCUPANCY_BUCKETS]);
+
+    // A full slab, a half full one and one holding a single slot.
+    let mut slots = Vec::new();
+    for _ in 0..2 {
+      slots.push(bin.allocate(&bump, arena_nn).expect("alloc"));
+      slots.extend(bin.fill_current_slab());
+    }
+    let lone = bin.allocate(&bump, arena_nn).expect("alloc");
+    let half = slab_of(arena, slots[slots.len() - 1]);
+    let capacity = unsafe { half.as_ref() }.capacity();
+    assert!(capacity >= 10 && capacity.is_multiple_of(2));
+    for p in slots.split_off(slots.len() - capacity / 2) {
+      bin.deallocate(p, half).expect("dealloc");
+    }
+
+    let mut expected = [0; OCCUPANCY_BUCKETS];
+    expected[0] = 1;
+    expected[5] = 1;
+    expected[OCCUPANCY_BUCKETS - 1] = 1;
+    assert_eq!(bin.occupancy_histogram(), expected);
+
+    slots.push(lone);
+    for p in slots {
+      bin.deallocate(p, slab_of(arena, p)).expect("dealloc");
+    }
+    assert_eq!(bin.occupancy_histogram(), [0; OCCUPANCY_BUCKETS]);
+    drop(bin);
+    unsafe { drop_in_place(arena_nn.as_ptr()) };
+  }
+
+  #[test]
+  fn second_slab_only_once_the_first_is_full() {
+    let arena_nn = unsafe { Arena::new(ArenaId(26), CHUNK_SIZE).expect("arena") };
+    let arena = unsafe { arena_nn.as_ref() };
+    let bump = Mutex::new(Bump::new(CHUNK_SIZE));
+    let mut bin = Bin::new(ScIdx(2));
+    assert!(bin.fill_current_slab().is_empty());
+
+    let first_ptr = bin.allocate(&bump, arena_nn).expect("alloc");
+    let first = slab_of(arena, first_ptr);
+    let mut slots = bin.fill_current_slab();
+    slots.push(first_ptr);
+    let capacity = unsafe { first.as_ref() }.capacity();
+    assert_eq!(slots.len(), capacity);
+    assert!(slots.iter().all(|p| slab_of(arena, *p) == first));
+    assert_eq!(ListIter::new(bin.active_head).count(), 1);
+
+    let spill = bin.allocate(&bump, arena_nn).expect("alloc");
+    assert_ne!(slab_of(arena, spill), first);
+    assert_eq!(ListIter::new(bin.active_head).count(), 2);
+    assert_eq!(bin.live_count(), capacity + 1);
+
+    slots.push(spill);
+    for p in slots {
+      bin.deallocate(p, slab_of(arena, p)).expect("dealloc");
+    }
+    assert_eq!(bin.free_count, 2);
+    drop(bin);
+    unsafe { drop_in_place(arena_nn.as_ptr()) };
+  }
+
+  #[test]
+  fn sub_slab_group_unmaps_with_its_last_slab() {
+    use basealloc_sys::counting::CountingSystem;
+
+    static COUNTING: CountingSystem = CountingSystem::new(GLOBAL_SYSTEM);
+    let arena_nn = arena_over(33, &COUNTING);
+    let arena = unsafe { arena_nn.as_ref() };
+    arena.set_slab_mode(SlabMode::SubSlab);
+    let bump = Mutex::new(Bump::new(CHUNK_SIZE));
+    let sc = ScIdx(36);
+    assert!(SLAB_GROUP_SIZE / pages_for(sc).0 >= 2);
+    let mut bin = Bin::new(sc);
+    bin.set_keep_free(0);
+
+    let mut fill = || {
+      let mut slots = vec![bin.allocate(&bump, arena_nn).expect("alloc")];
+      slots.extend(bin.fill_current_slab());
+      slots
+    };
+    let (first, second) = (fill(), fill());
+    assert_ne!(slab_of(arena, first[0]), slab_of(arena, second[0]));
+    assert_eq!(COUNTING.allocs(), 1);
+
+    for p in first {
+      bin.deallocate(p, slab_of(arena, p)).expect("dealloc");
+    }
+    assert_eq!(COUNTING.deallocs(), 0);
+    for p in second {
+      bin.deallocate(p, slab_of(arena, p)).expect("dealloc");
+    }
+    assert_eq!(COUNTING.deallocs(), 1);
+
+    drop(bin);
+    unsafe { drop_in_place(arena_nn.as_ptr()) };
+  }
+
+  #[test]
+  fn sub_slabs_share_a_few_mappings() {
+    const SLABS: usize = 24;
+    let arena_nn = arena_over(28, MAPS_SYSTEM);
+    let arena = unsafe { arena_nn.as_ref() };
+    arena.set_slab_mode(SlabMode::SubSlab);
+    let bump = Mutex::new(Bump::new(CHUNK_SIZE));
+    let sc = ScIdx(36);
+    let size = pages_for(sc).0;
+    let per_group = SLAB_GROUP_SIZE / size;
+    assert!(per_group >= 4);
+    let mut bin = Bin::new(sc);
+
+    // Only this arena's mappings are recorded; the latest one holding a slab is its own.
+    let mut slots = Vec::new();
+    for _ in 0..SLABS {
+      slots.push(bin.allocate(&bump, arena_nn).expect("alloc"));
+      slots.extend(bin.fill_current_slab());
+    }
+
+    let mut slabs: Vec<_> = slots.iter().map(|p| slab_of(arena, *p)).collect();
+    slabs.dedup();
+    let mapped = MAPS.mapped.lock();
+    let mut regions: Vec<_> = slabs
+      .iter()
+      .map(|slab| unsafe { slab.as_ref() }.extent().as_ref().as_ptr() as usize)
+      .map(|base| {
+        mapped
+          .iter()
+          .rposition(|(start, len)| (*start..start + len).contains(&base))
+      })
+      .collect();
+    drop(mapped);
+    regions.sort_unstable();
+    regions.dedup();
+    assert_eq!(slabs.len(), SLABS);
+    assert_eq!(regions.len(), SLABS.div_ceil(per_group));
+    assert!(regions.iter().all(Option::is_some));
+
+    for (tag, p) in slots.iter().enumerate() {
+      unsafe { p.cast::<usize>().write_unaligned(tag) };
+    }
+    for (tag, p) in slots.iter().enumerate() {
+      assert_eq!(unsafe { p.cast::<usize>().read_unaligned() }, tag);
+      bin.deallocate(*p, slab_of(arena, *p)).expect("dealloc");
+    }
+    assert!(bin.is_empty());
+    assert_eq!(bin.validate(), Ok(()));
+    drop(bin);
+    unsafe { drop_in_place(arena_nn.as_ptr()) };
+  }
+}
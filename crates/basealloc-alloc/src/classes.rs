@@ -1,3 +1,5 @@
+use core::alloc::Layout;
+
 use basealloc_sync::lazy::LazyLock;
 use basealloc_sys::{
   prelude::*,
@@ -7,14 +9,18 @@ use basealloc_sys::{
   },
 };
 
-use crate::{
-  WORD,
-  WORD_BITS,
-  WORD_TRAILING,
-};
+use crate::WORD_BITS;
 
-pub const QUANTUM: usize = WORD;
-pub const NGROUPSEX: usize = 4;
+#[cfg(all(feature = "fine-classes", feature = "coarse-classes"))]
+compile_error!("`fine-classes` and `coarse-classes` are mutually exclusive");
+
+pub const QUANTUM: usize = min_align();
+#[cfg(feature = "fine-classes")]
+pub const NGROUPSEX: usize = 3;
+#[cfg(feature = "coarse-classes")]
+pub const NGROUPSEX: usize = 1;
+#[cfg(not(any(feature = "fine-classes", feature = "coarse-classes")))]
+pub const NGROUPSEX: usize = 2;
 pub const NGROUPS: usize = 1 << NGROUPSEX;
 
 pub const NTINY: usize = NGROUPS * QUANTUM;
@@ -27,12 +33,30 @@ pub const NREGULAR: usize = (MAX_REGULAR - FIRST_REGULAR) * NGROUPS;
 pub const NSCLASSES: usize = NTINY + NREGULAR;
 pub const SCLASS_CUTOFF: usize = 1 << MAX_REGULAR;
 
-const LOOKUP_SHIFT: usize = WORD_TRAILING + 1;
-
+const LOOKUP_SHIFT: usize = QUANTUM.trailing_zeros() as usize;
+// Every tiny size; its class is one shift away, without a table load.
+pub const FAST_CUTOFF: usize = NTINY * QUANTUM;
+
+#[cfg(all(feature = "small-tcache", feature = "large-tcache"))]
+compile_error!("`small-tcache` and `large-tcache` are mutually exclusive");
+
+// Per-class tcache bounds. Picked at compile time, so there is no ordering
+// constraint against the lazily built `CACHE_SIZES` table.
+#[cfg(feature = "small-tcache")]
+const CACHE_MIN: usize = 2;
+#[cfg(feature = "small-tcache")]
+const CACHE_MAX: usize = 32;
+#[cfg(feature = "large-tcache")]
+const CACHE_MIN: usize = 16;
+#[cfg(feature = "large-tcache")]
+const CACHE_MAX: usize = 1024;
+#[cfg(not(any(feature = "small-tcache", feature = "large-tcache")))]
 const CACHE_MIN: usize = 8;
+#[cfg(not(any(feature = "small-tcache", feature = "large-tcache")))]
 const CACHE_MAX: usize = 200;
 
-// Size class structure:
+// Size class structure (numbers for the default NGROUPSEX = 2; `fine-classes`
+// selects 3 and `coarse-classes` selects 1, scaling NTINY and NREGULAR with it):
 // - QUANTUM (16): minimum allocation unit
 // - Tiny classes [0..NTINY): linear spacing by QUANTUM up to TINY_CUTOFF (1024)
 //   NTINY = 64 classes = NGROUPS * QUANTUM
@@ -55,9 +79,36 @@ pub struct SlabPages(pub usize);
 pub struct CacheSlots(pub usize);
 
 const CLASSES: [SizeClass; NSCLASSES] = generate_classes();
-const TINY_LOOKUP: [u8; TINY_CUTOFF >> LOOKUP_SHIFT] = generate_tiny_lookup();
-static PAGES: LazyLock<[SlabPages; NSCLASSES]> = LazyLock::new(|| generate_pages());
-static CACHE_SIZES: LazyLock<[CacheSlots; NSCLASSES]> = LazyLock::new(|| generate_cache_sizes());
+static PAGES: LazyLock<[SlabPages; NSCLASSES]> = LazyLock::new(generate_pages);
+static CACHE_SIZES: LazyLock<[CacheSlots; NSCLASSES]> =
+  LazyLock::new(generate_cache_sizes::<CACHE_MIN, CACHE_MAX>);
+static TABLE: LazyLock<[(usize, usize); NSCLASSES]> =
+  LazyLock::new(|| core::array::from_fn(|i| (CLASSES[i].0, PAGES[i].0)));
+
+/// The lazily built tables; `CLASSES` is a const and lands in rodata.
+pub(crate) const FOOTPRINT: usize = size_of::<LazyLock<[SlabPages; NSCLASSES]>>()
+  + size_of::<LazyLock<[CacheSlots; NSCLASSES]>>()
+  + size_of::<LazyLock<[(usize, usize); NSCLASSES]>>();
+
+/// Builds the lazily computed class tables now instead of on first use.
+pub fn init_tables() {
+  LazyLock::force(&PAGES);
+  LazyLock::force(&CACHE_SIZES);
+  LazyLock::force(&TABLE);
+}
+
+/// `(class_size, slab_size)` for every class of this build, smallest first.
+pub fn class_table() -> &'static [(usize, usize)] {
+  &*TABLE
+}
+
+/// Writes `class_table()` as CSV with a `class_size,slab_size` header, for offline tools.
+pub fn write_class_table(out: &mut impl core::fmt::Write) -> core::fmt::Result {
+  writeln!(out, "class_size,slab_size")?;
+  class_table()
+    .iter()
+    .try_for_each(|(size, slab)| writeln!(out, "{},{}", size, slab))
+}
 
 const fn log2c(mut x: usize) -> usize {
   let mut log = 0;
@@ -78,6 +129,10 @@ fn gcd(mut a: usize, mut b: usize) -> usize {
   a
 }
 
+// Each slab pays for a header and a bitmap; below this many objects that overhead
+// stops being small next to the objects themselves.
+const MIN_SLAB_OBJECTS: usize = 8;
+
 fn generate_pages() -> [SlabPages; NSCLASSES] {
   let mut pages = [const { SlabPages(0) }; NSCLASSES];
   let ps = page_size();
@@ -85,8 +140,11 @@ fn generate_pages() -> [SlabPages; NSCLASSES] {
   for (i, class) in CLASSES.iter().enumerate() {
     let SizeClass(size, _) = *class;
     let g = gcd(ps, size);
-    let num_pages = size / g;
-    pages[i] = SlabPages(num_pages * ps);
+    // The smallest slab without tail waste; large classes repeat it to reach the minimum.
+    let slab = (size / g) * ps;
+    let objects = slab / size;
+    let repeat = MIN_SLAB_OBJECTS.div_ceil(objects);
+    pages[i] = SlabPages(slab * repeat);
   }
   pages
 }
@@ -117,70 +175,125 @@ const fn generate_classes() -> [SizeClass; NSCLASSES] {
   classes
 }
 
-const fn generate_tiny_lookup() -> [u8; TINY_CUTOFF >> LOOKUP_SHIFT] {
-  let mut table = [0u8; TINY_CUTOFF >> LOOKUP_SHIFT];
-  let mut i = 0;
-  while i < table.len() {
-    let size = (i + 1) << LOOKUP_SHIFT;
-    let idx = size.div_ceil(QUANTUM) - 1;
-    table[i] = idx as u8;
-    i += 1;
-  }
-  table
-}
-
-fn generate_cache_sizes() -> [CacheSlots; NSCLASSES] {
+fn generate_cache_sizes<const MIN: usize, const MAX: usize>() -> [CacheSlots; NSCLASSES] {
+  const { assert!(MIN <= MAX) };
   let mut caches = [CacheSlots(0); NSCLASSES];
 
   let mut i = 0;
   while i < NSCLASSES {
     let SizeClass(size, _) = CLASSES[i];
     let scale = SCLASS_CUTOFF / size;
-    let nslots = scale.clamp(CACHE_MIN, CACHE_MAX);
+    let nslots = scale.clamp(MIN, MAX);
 
-    caches[i] = CacheSlots(nslots * size);
+    caches[i] = CacheSlots(nslots);
     i += 1;
   }
   caches
 }
 
 pub fn total_cache_size() -> usize {
-  CACHE_SIZES.iter().map(|CacheSlots(sz)| *sz).sum()
+  let slots: usize = CACHE_SIZES.iter().map(|CacheSlots(n)| *n).sum();
+  slots * core::mem::size_of::<*mut u8>()
 }
 
 pub fn cache_for(class: ScIdx) -> CacheSlots {
   CACHE_SIZES[class.0]
 }
 
+// Group `g` covers `(2^g, 2^(g+1)]`, so the group comes from `size - 1`; exact
+// powers of two then land on the last class of the group below.
 #[inline]
 fn class_for_regular(size: usize) -> ScIdx {
-  let log = (usize::BITS - size.leading_zeros()) as usize - 1;
+  let above = size - 1;
+  let log = (usize::BITS - above.leading_zeros()) as usize - 1;
   let group_idx = log - FIRST_REGULAR;
-  let base = 1 << log;
-  let delta = base >> NGROUPSEX;
-  let offset = if unlikely(size > base) {
-    (size - base - 1) / delta
-  } else {
-    0
-  };
+  let delta = (1 << log) >> NGROUPSEX;
+  let offset = (above - (1 << log)) / delta;
 
   ScIdx(NTINY + group_idx * NGROUPS + offset)
 }
 
+/// Tiny classes are spaced linearly by `QUANTUM`, so no table load is needed.
+///
+/// `size` must be in `1..=FAST_CUTOFF`.
+#[inline(always)]
+pub const fn class_for_tiny_fast(size: usize) -> ScIdx {
+  debug_assert!(size > 0 && size <= FAST_CUTOFF);
+  ScIdx((size - 1) >> LOOKUP_SHIFT)
+}
+
 #[inline(always)]
 pub fn class_for(size: usize) -> Option<ScIdx> {
   if unlikely(size == 0 || size >= SCLASS_CUTOFF) {
     return None;
   }
 
-  if likely(size <= TINY_CUTOFF) {
-    let idx = TINY_LOOKUP[(size - 1) >> LOOKUP_SHIFT] as usize;
-    return Some(ScIdx(idx));
+  if likely(size <= FAST_CUTOFF) {
+    return Some(class_for_tiny_fast(size));
   }
 
   Some(class_for_regular(size))
 }
 
+/// `class_for` and `class_at` in one call: the class `size` lands in, with its size.
+///
+/// Tiny classes are computed outright; regular ones cost one table load on top of
+/// `class_for`'s arithmetic.
+#[inline(always)]
+pub fn class_lookup(size: usize) -> Option<SizeClass> {
+  if likely(size > 0 && size <= FAST_CUTOFF) {
+    let idx = class_for_tiny_fast(size);
+    return Some(SizeClass((idx.0 + 1) * QUANTUM, idx));
+  }
+  class_for(size).map(class_at)
+}
+
+/// Bytes a small object of `size` takes of its slot: with `redzone` the guard bytes
+/// after it, with `debug-cookies` the header before it.
+#[inline(always)]
+pub fn padded_size(size: usize) -> Option<usize> {
+  #[cfg(feature = "redzone")]
+  let size = size.checked_add(crate::REDZONE)?;
+  #[cfg(feature = "debug-cookies")]
+  let size = size.checked_add(crate::cookie::COOKIE)?;
+  Some(size)
+}
+
+/// The largest alignment a class can serve: a page, or with `debug-cookies` the header,
+/// which moves objects that far off the aligned slot start.
+#[inline(always)]
+pub fn max_class_align() -> usize {
+  #[cfg(feature = "debug-cookies")]
+  return crate::cookie::COOKIE;
+  #[cfg(not(feature = "debug-cookies"))]
+  page_size()
+}
+
+/// The smallest class that fits `layout` and keeps every slot aligned to it.
+///
+/// Slabs start on a page, so a class whose size is a multiple of the alignment
+/// aligns all its slots; alignments above `max_class_align()` never fit a class. The
+/// class also leaves room for the padding of `padded_size`.
+pub fn class_for_layout(layout: Layout) -> Option<ScIdx> {
+  let size = padded_size(layout.size())?;
+  if likely(layout.align() <= QUANTUM) {
+    return class_for(size);
+  }
+  if layout.align() > max_class_align() {
+    return None;
+  }
+
+  let first = class_lookup(size)?;
+  if first.0.is_multiple_of(layout.align()) {
+    return Some(first.1);
+  }
+  (first.1.0 + 1..NSCLASSES)
+    .map(|idx| class_at(ScIdx(idx)))
+    .take_while(|class| class.0 < SCLASS_CUTOFF)
+    .find(|class| class.0.is_multiple_of(layout.align()))
+    .map(|class| class.1)
+}
+
 #[inline(always)]
 pub fn pages_for(class: ScIdx) -> SlabPages {
   PAGES[class.0]
@@ -191,10 +304,54 @@ pub fn class_at(idx: ScIdx) -> SizeClass {
   CLASSES[idx.0]
 }
 
+/// Decides which size class a request lands in.
+///
+/// Policy classes are backed by the built-in bins: each one is served by the smallest
+/// built-in class that fits it (`bin_for`), so a policy can only coarsen the spacing.
+/// Powers of two and every `QUANTUM` multiple up to `TINY_CUTOFF` map exactly.
+pub trait SizeClassPolicy {
+  fn class_for(&self, size: usize) -> Option<ScIdx>;
+  fn class_at(&self, idx: ScIdx) -> SizeClass;
+  fn num_classes(&self) -> usize;
+
+  /// The built-in class whose bin serves policy class `idx`.
+  #[inline]
+  fn bin_for(&self, idx: ScIdx) -> Option<ScIdx> {
+    class_for(self.class_at(idx).0)
+  }
+}
+
+/// The built-in jemalloc-style spacing; what `GlobalAlloc` uses.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DefaultPolicy;
+
+impl SizeClassPolicy for DefaultPolicy {
+  #[inline(always)]
+  fn class_for(&self, size: usize) -> Option<ScIdx> {
+    class_for(size)
+  }
+
+  #[inline(always)]
+  fn class_at(&self, idx: ScIdx) -> SizeClass {
+    class_at(idx)
+  }
+
+  #[inline(always)]
+  fn num_classes(&self) -> usize {
+    NSCLASSES
+  }
+
+  #[inline(always)]
+  fn bin_for(&self, idx: ScIdx) -> Option<ScIdx> {
+    Some(idx)
+  }
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
 
+  #[cfg(not(any(feature = "fine-classes", feature = "coarse-classes")))]
   #[test]
   fn constants_are_valid() {
     assert_eq!(QUANTUM, 16);
@@ -203,7 +360,112 @@ mod tests {
     assert_eq!(TINY_CUTOFF, 1024);
     assert_eq!(FIRST_REGULAR, 10);
     assert_eq!(SCLASS_CUTOFF, 2097152);
-    assert!(NSCLASSES > 0 && NSCLASSES < 256);
+    const { assert!(NSCLASSES > 0 && NSCLASSES < 256) };
+  }
+
+  #[cfg(feature = "fine-classes")]
+  #[test]
+  fn constants_are_valid() {
+    assert_eq!(NGROUPS, 8);
+    assert_eq!(NTINY, 128);
+    assert_eq!(TINY_CUTOFF, 2048);
+    assert_eq!(FIRST_REGULAR, 11);
+    assert_eq!(NSCLASSES, 208);
+    const { assert!(NSCLASSES < 256) };
+  }
+
+  #[cfg(feature = "coarse-classes")]
+  #[test]
+  fn constants_are_valid() {
+    assert_eq!(NGROUPS, 2);
+    assert_eq!(NTINY, 32);
+    assert_eq!(TINY_CUTOFF, 512);
+    assert_eq!(FIRST_REGULAR, 9);
+    assert_eq!(NSCLASSES, 56);
+  }
+
+  #[test]
+  fn regular_groups_end_on_powers_of_two() {
+    for chunk in CLASSES[NTINY..].chunks(NGROUPS) {
+      let SizeClass(last, _) = chunk[NGROUPS - 1];
+      assert!(last.is_power_of_two(), "group ends at {}", last);
+    }
+  }
+
+  #[test]
+  fn class_for_maps_class_sizes_exactly() {
+    for SizeClass(size, idx) in CLASSES.iter().take_while(|c| c.0 < SCLASS_CUTOFF) {
+      assert_eq!(class_for(*size), Some(*idx), "size {}", size);
+    }
+  }
+
+  #[test]
+  fn class_for_layout_respects_alignment() {
+    let pick = |size, align| class_for_layout(Layout::from_size_align(size, align).unwrap());
+
+    assert_eq!(pick(48, 8), class_for(padded_size(48).unwrap()));
+    for (size, align) in [(48, 32), (100, 64), (3000, 1024), (5000, 4096)] {
+      if align > max_class_align() {
+        assert_eq!(pick(size, align), None);
+        continue;
+      }
+      let SizeClass(class_size, _) = class_at(pick(size, align).unwrap());
+      assert!(class_size >= size && class_size.is_multiple_of(align));
+    }
+    assert_eq!(pick(64, 2 * page_size()), None);
+  }
+
+  #[test]
+  fn default_policy_is_the_class_table() {
+    let policy = DefaultPolicy;
+    assert_eq!(policy.num_classes(), NSCLASSES);
+    for size in [1, QUANTUM + 1, TINY_CUTOFF, TINY_CUTOFF + 1, SCLASS_CUTOFF] {
+      assert_eq!(policy.class_for(size), class_for(size));
+    }
+    for idx in (0..NSCLASSES).map(ScIdx) {
+      assert_eq!(policy.class_at(idx), class_at(idx));
+      assert_eq!(policy.bin_for(idx), Some(idx));
+    }
+  }
+
+  #[test]
+  fn slabs_hold_a_minimum_of_objects() {
+    let ps = page_size();
+    for SizeClass(size, idx) in CLASSES {
+      let SlabPages(slab) = pages_for(idx);
+      assert!(slab.is_multiple_of(ps) && slab.is_multiple_of(size));
+      assert!(
+        slab / size >= MIN_SLAB_OBJECTS,
+        "class {} holds {}",
+        size,
+        slab / size
+      );
+    }
+  }
+
+  #[test]
+  fn footprint_matches_statics() {
+    assert_eq!(
+      FOOTPRINT,
+      size_of_val(&PAGES) + size_of_val(&CACHE_SIZES) + size_of_val(&TABLE)
+    );
+  }
+
+  #[test]
+  fn class_table_csv_lists_every_class() {
+    let mut csv = String::new();
+    write_class_table(&mut csv).expect("write");
+
+    let mut lines = csv.lines();
+    assert_eq!(lines.next(), Some("class_size,slab_size"));
+    let rows: Vec<(usize, usize)> = lines
+      .map(|line| line.split_once(',').expect("two columns"))
+      .map(|(size, slab)| (size.parse().unwrap(), slab.parse().unwrap()))
+      .collect();
+    assert_eq!(rows.len(), NSCLASSES);
+    assert!(rows.windows(2).all(|pair| pair[0].0 < pair[1].0));
+    assert_eq!(rows[NSCLASSES - 1].0, SCLASS_CUTOFF);
+    assert_eq!(rows[3], (class_at(ScIdx(3)).0, pages_for(ScIdx(3)).0));
   }
 
   #[test]
@@ -224,9 +486,18 @@ mod tests {
 
   #[test]
   fn tiny_classes_correct() {
-    for i in 0..NTINY {
-      let SizeClass(size, _) = CLASSES[i];
-      assert_eq!(size, (i + 1) * QUANTUM);
+    for (i, SizeClass(size, _)) in CLASSES.iter().take(NTINY).enumerate() {
+      assert_eq!(*size, (i + 1) * QUANTUM);
+    }
+  }
+
+  #[test]
+  fn tiny_fast_picks_the_smallest_fitting_class() {
+    assert_eq!(FAST_CUTOFF, TINY_CUTOFF);
+    for size in 1..=FAST_CUTOFF {
+      let ScIdx(idx) = class_for_tiny_fast(size);
+      assert!(CLASSES[idx].0 >= size, "size {}", size);
+      assert!(idx == 0 || CLASSES[idx - 1].0 < size, "size {}", size);
     }
   }
 
@@ -246,6 +517,45 @@ mod tests {
     assert_eq!(idx, 1);
   }
 
+  #[test]
+  fn class_lookup_agrees_with_class_at() {
+    let sizes = (0..=TINY_CUTOFF + QUANTUM).chain((TINY_CUTOFF..SCLASS_CUTOFF + 64).step_by(97));
+    for size in sizes.chain([SCLASS_CUTOFF - 1, SCLASS_CUTOFF]) {
+      assert_eq!(
+        class_lookup(size),
+        class_for(size).map(class_at),
+        "size {}",
+        size
+      );
+    }
+  }
+
+  #[test]
+  fn class_for_is_exact_around_the_cutoff() {
+    for size in SCLASS_CUTOFF - 64..=SCLASS_CUTOFF + 64 {
+      let Some(ScIdx(idx)) = class_for(size) else {
+        assert!(size >= SCLASS_CUTOFF, "size {} went large", size);
+        continue;
+      };
+      assert!(size < SCLASS_CUTOFF, "size {} got class {}", size, idx);
+      assert!(idx < NSCLASSES, "size {} got class {}", size, idx);
+      assert!(CLASSES[idx].0 >= size, "size {} got class {}", size, idx);
+      assert!(
+        idx == 0 || CLASSES[idx - 1].0 < size,
+        "size {} skipped a class",
+        size
+      );
+    }
+  }
+
+  #[test]
+  fn cutoff_minus_one_takes_the_last_class() {
+    // The last class is exactly the cutoff, yet only smaller requests may use it.
+    assert_eq!(CLASSES[NSCLASSES - 1].0, SCLASS_CUTOFF);
+    assert_eq!(class_for(SCLASS_CUTOFF - 1), Some(ScIdx(NSCLASSES - 1)));
+    assert_eq!(class_for(SCLASS_CUTOFF), None);
+  }
+
   #[test]
   fn class_for_all_sizes_valid() {
     for idx in 0..NSCLASSES {
@@ -288,12 +598,23 @@ mod tests {
 
   #[test]
   fn regular_classes_exponential() {
-    for i in NTINY + 1..NSCLASSES {
-      let SizeClass(size, _) = CLASSES[i];
-      assert!(size > TINY_CUTOFF);
+    for SizeClass(size, _) in CLASSES.iter().skip(NTINY + 1) {
+      assert!(*size > TINY_CUTOFF);
     }
   }
 
+  #[test]
+  fn cache_sizes_respect_bounds() {
+    for class in 0..NSCLASSES {
+      let CacheSlots(slots) = cache_for(ScIdx(class));
+      assert!((CACHE_MIN..=CACHE_MAX).contains(&slots));
+    }
+
+    let tiny = generate_cache_sizes::<1, 4>();
+    assert!(tiny.iter().all(|CacheSlots(n)| (1..=4).contains(n)));
+    assert_eq!(tiny[0], CacheSlots(4));
+  }
+
   #[test]
   fn page_sizes_are_multiples() {
     let ps = page_size();
@@ -0,0 +1,108 @@
+//! Headers in front of small objects, so a free can tell one of ours from a
+//! corrupted or foreign pointer. Only built with `debug-cookies`.
+
+use core::ptr::NonNull;
+
+use basealloc_sys::hook::{
+  Fault,
+  report,
+};
+
+use crate::classes::ScIdx;
+
+/// Bytes in front of every small object; also the largest alignment a class can serve,
+/// since objects start this far into their slot.
+pub const COOKIE: usize = 16;
+
+const MAGIC: u32 = 0xBA5E_C00C;
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct Cookie {
+  magic: u32,
+  class: u32,
+  size: usize,
+}
+
+const _: () = assert!(size_of::<Cookie>() <= COOKIE);
+
+/// Writes the cookie of a `size`-byte object of `class` at the start of `slot` and
+/// returns the object, `COOKIE` bytes in.
+pub fn seal(slot: NonNull<u8>, class: ScIdx, size: usize) -> NonNull<u8> {
+  let cookie = Cookie {
+    magic: MAGIC,
+    class: class.0 as u32,
+    size,
+  };
+  unsafe { slot.cast::<Cookie>().write_unaligned(cookie) };
+  unsafe { slot.add(COOKIE) }
+}
+
+/// Checks the cookie in front of `ptr` and returns the slot it starts.
+///
+/// Reports `CookieMismatch` if the magic was overwritten or the object wasn't sealed for
+/// `class`, or for `size` when given.
+pub fn open(ptr: NonNull<u8>, class: ScIdx, size: Option<usize>) -> NonNull<u8> {
+  let slot = unsafe { ptr.sub(COOKIE) };
+  let cookie = unsafe { slot.cast::<Cookie>().read_unaligned() };
+  let intact = cookie.magic == MAGIC && cookie.class as usize == class.0;
+  if !intact || size.is_some_and(|size| size != cookie.size) {
+    report(Fault::CookieMismatch(ptr.as_ptr() as usize));
+  }
+  slot
+}
+
+/// Records that the object at `ptr` is `size` bytes now, after it was resized in place.
+pub fn resize(ptr: NonNull<u8>, size: usize) {
+  let slot = unsafe { ptr.sub(COOKIE) };
+  let mut cookie = unsafe { slot.cast::<Cookie>().read_unaligned() };
+  cookie.size = size;
+  unsafe { slot.cast::<Cookie>().write_unaligned(cookie) };
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::testing::{
+    saw,
+    watch_faults,
+  };
+
+  #[test]
+  fn overwritten_cookie_is_reported() {
+    watch_faults();
+    let mut slots = [[0u8; 64]; 2];
+    let [clean, smashed] = slots
+      .each_mut()
+      .map(|slot| NonNull::from(slot).cast::<u8>());
+
+    let clean = seal(clean, ScIdx(3), 40);
+    assert_eq!(open(clean, ScIdx(3), Some(40)), unsafe {
+      clean.sub(COOKIE)
+    });
+    assert!(!saw(Fault::CookieMismatch(clean.as_ptr() as usize)));
+
+    let smashed = seal(smashed, ScIdx(3), 40);
+    unsafe { smashed.sub(COOKIE).write(0x41) };
+    open(smashed, ScIdx(3), Some(40));
+    assert!(saw(Fault::CookieMismatch(smashed.as_ptr() as usize)));
+  }
+
+  #[test]
+  fn size_and_class_must_match() {
+    watch_faults();
+    let mut slots = [[0u8; 64]; 2];
+    let [resized, wrong] = slots
+      .each_mut()
+      .map(|slot| NonNull::from(slot).cast::<u8>());
+
+    let resized = seal(resized, ScIdx(5), 40);
+    resize(resized, 48);
+    open(resized, ScIdx(5), Some(48));
+    assert!(!saw(Fault::CookieMismatch(resized.as_ptr() as usize)));
+
+    let wrong = seal(wrong, ScIdx(5), 40);
+    open(wrong, ScIdx(6), None);
+    assert!(saw(Fault::CookieMismatch(wrong.as_ptr() as usize)));
+  }
+}
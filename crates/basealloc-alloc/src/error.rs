@@ -0,0 +1,150 @@
+use basealloc_bitmap::BitmapError;
+use basealloc_extent::ExtentError;
+use basealloc_fixed::bump::BumpError;
+use basealloc_rtree::RTreeError;
+use basealloc_sys::{
+  prim::PrimError,
+  system::SysError,
+};
+
+use crate::{
+  arena::ArenaError,
+  bin::BinError,
+  lookup::LookupError,
+  slab::SlabError,
+  tcache::TCacheError,
+};
+
+/// Any error of the allocator crates, so code spanning several layers can `?` them all.
+///
+/// Each variant wraps the granular error unchanged; match on it, or get it back with
+/// `try_into`, to tell causes apart.
+#[derive(Debug)]
+pub enum Error {
+  Sys(SysError),
+  Prim(PrimError),
+  Bitmap(BitmapError),
+  Extent(ExtentError),
+  Bump(BumpError),
+  RTree(RTreeError),
+  Lookup(LookupError),
+  Slab(SlabError),
+  Bin(BinError),
+  Arena(ArenaError),
+  TCache(TCacheError),
+}
+
+pub type AllocResult<T> = Result<T, Error>;
+
+// `From` into `Error` and `TryFrom` back out of it, for every wrapped error.
+macro_rules! wrap {
+  ($($variant:ident($sub:ty)),* $(,)?) => {$(
+    impl From<$sub> for Error {
+      fn from(err: $sub) -> Self {
+        Error::$variant(err)
+      }
+    }
+
+    impl TryFrom<Error> for $sub {
+      type Error = Error;
+
+      fn try_from(err: Error) -> Result<Self, Error> {
+        match err {
+          Error::$variant(inner) => Ok(inner),
+          other => Err(other),
+        }
+      }
+    }
+  )*};
+}
+
+wrap!(
+  Sys(SysError),
+  Prim(PrimError),
+  Bitmap(BitmapError),
+  Extent(ExtentError),
+  Bump(BumpError),
+  RTree(RTreeError),
+  Lookup(LookupError),
+  Slab(SlabError),
+  Bin(BinError),
+  Arena(ArenaError),
+  TCache(TCacheError),
+);
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn through<E>(err: E) -> AllocResult<E>
+  where
+    E: TryFrom<Error, Error = Error>,
+    Error: From<E>,
+  {
+    Error::from(err).try_into()
+  }
+
+  #[test]
+  fn sub_errors_round_trip() {
+    assert!(matches!(
+      through(SysError::OutOfMemory),
+      Ok(SysError::OutOfMemory)
+    ));
+    assert!(matches!(
+      through(PrimError::Overflow),
+      Ok(PrimError::Overflow)
+    ));
+    assert!(matches!(
+      through(BitmapError::OutOfBounds { index: 9, size: 8 }),
+      Ok(BitmapError::OutOfBounds { index: 9, size: 8 })
+    ));
+    assert!(matches!(
+      through(ExtentError::OutOfBounds),
+      Ok(ExtentError::OutOfBounds)
+    ));
+    assert!(matches!(
+      through(RTreeError::AlreadyPresent),
+      Ok(RTreeError::AlreadyPresent)
+    ));
+    assert!(matches!(
+      through(LookupError::NotFound),
+      Ok(LookupError::NotFound)
+    ));
+    assert!(matches!(
+      through(SlabError::OutOfMemory),
+      Ok(SlabError::OutOfMemory)
+    ));
+    assert!(matches!(
+      through(BinError::SlabError(SlabError::InvalidPointer)),
+      Ok(BinError::SlabError(SlabError::InvalidPointer))
+    ));
+    assert!(matches!(
+      through(ArenaError::TooLarge { size: 2, max: 1 }),
+      Ok(ArenaError::TooLarge { size: 2, max: 1 })
+    ));
+    assert!(matches!(
+      through(TCacheError::LookupError(LookupError::InvalidArena)),
+      Ok(TCacheError::LookupError(LookupError::InvalidArena))
+    ));
+  }
+
+  #[test]
+  fn wrong_variant_hands_the_error_back() {
+    let err = Error::from(LookupError::NotFound);
+    let back: Result<SlabError, Error> = err.try_into();
+    assert!(matches!(back, Err(Error::Lookup(LookupError::NotFound))));
+  }
+
+  fn layered() -> AllocResult<()> {
+    Err(SlabError::OutOfMemory)?;
+    Ok(())
+  }
+
+  #[test]
+  fn question_mark_lifts_sub_errors() {
+    assert!(matches!(
+      layered(),
+      Err(Error::Slab(SlabError::OutOfMemory))
+    ));
+  }
+}
@@ -1,15 +1,26 @@
 #![cfg_attr(not(test), no_std)]
-use basealloc_bitmap::BitmapWord;
+use basealloc_bitmap::Bitmap;
 use basealloc_sys::prim::word_width;
 
 pub mod arena;
 pub mod bin;
 pub mod classes;
+#[cfg(feature = "debug-cookies")]
+pub mod cookie;
+pub mod error;
 pub mod lookup;
+#[cfg(feature = "sampling")]
+pub mod sampling;
 pub mod slab;
 pub mod static_;
+pub mod stats;
 pub mod tcache;
 
+pub use error::{
+  AllocResult,
+  Error,
+};
+
 const WORD: usize = word_width();
 const BITS_PER_BYTE: usize = 8;
 
@@ -17,10 +28,77 @@ const WORD_BITS: usize = WORD * BITS_PER_BYTE;
 const WORD_TRAILING: usize = WORD.trailing_zeros() as usize;
 
 const MAX_ARENAS: usize = 256;
-pub const ARENA_BMS: usize = core::mem::size_of::<BitmapWord>() * MAX_ARENAS;
+/// Words backing the arena bitmap; one bit per arena.
+pub const ARENA_BMS: usize = Bitmap::words(MAX_ARENAS);
+const _: () =
+  assert!(ARENA_BMS * WORD_BITS >= MAX_ARENAS && ARENA_BMS * WORD_BITS < MAX_ARENAS + WORD_BITS);
 
 pub const CHUNK_SHIFT: usize = 16 + WORD_TRAILING;
 pub const CHUNK_SIZE: usize = 1 << CHUNK_SHIFT; // ONLY USED FOR THE BUMP ALLOCATOR!!!!
 
+#[cfg(feature = "poison")]
+const POISON: u8 = 0xDE;
+
+/// Guard bytes at the end of every small slot, checked on free.
+#[cfg(feature = "redzone")]
+pub const REDZONE: usize = 8;
+#[cfg(feature = "redzone")]
+const REDZONE_BYTE: u8 = 0xFD;
+
 const BITS_PER_LEVEL: usize = 9;
 pub const FANOUT: usize = 1 << BITS_PER_LEVEL;
+
+#[cfg(feature = "sampling")]
+const SAMPLING_FOOTPRINT: usize = sampling::FOOTPRINT;
+#[cfg(not(feature = "sampling"))]
+const SAMPLING_FOOTPRINT: usize = 0;
+
+/// Bytes of static storage this crate reserves, for budgeting `.bss` at compile time.
+///
+/// Covers the arena table and its bitmap, the arena map, the lazily built class
+/// tables, the stats counters and the thread-local keys. Memory those point to is
+/// mapped at runtime and not included.
+pub const fn static_footprint() -> usize {
+  static_::FOOTPRINT
+    + stats::FOOTPRINT
+    + classes::FOOTPRINT
+    + arena::FOOTPRINT
+    + tcache::FOOTPRINT
+    + SAMPLING_FOOTPRINT
+}
+
+/// Initializes every global in dependency order: class tables, then the arena
+/// table, then the thread-local keys. Idempotent.
+///
+/// Returns `false` if thread-local storage is unavailable, in which case all threads
+/// share the fallback arena.
+pub fn init() -> bool {
+  classes::init_tables();
+  let arenas = static_::init_statics();
+  let tcache = tcache::register_tcache();
+  arenas && tcache
+}
+
+#[cfg(test)]
+pub(crate) mod testing {
+  use basealloc_sys::hook::{
+    Fault,
+    set_error_hook,
+  };
+  use spin::Mutex;
+
+  static SEEN: Mutex<Vec<Fault>> = Mutex::new(Vec::new());
+
+  fn record(fault: Fault) {
+    SEEN.lock().push(fault);
+  }
+
+  /// Installs a hook recording every fault; shared by all tests in this crate.
+  pub fn watch_faults() {
+    set_error_hook(Some(record));
+  }
+
+  pub fn saw(fault: Fault) -> bool {
+    SEEN.lock().contains(&fault)
+  }
+}
@@ -1,19 +1,18 @@
-use core::{
-  cell::UnsafeCell,
-  ptr::NonNull,
-};
+use core::ptr::NonNull;
 
 use basealloc_extent::Extent;
 use basealloc_rtree::{
   RTree,
   RTreeError,
 };
+
 use basealloc_sys::{
-  prelude::page_align_down,
   prim::{
+    PageRange,
     PrimError,
     page_size,
   },
+  system::System,
 };
 
 use crate::{
@@ -42,6 +41,15 @@ impl OwnerInfo {
   pub fn new_extent(extent: NonNull<Extent>) -> Self {
     Self::Extent { extent }
   }
+
+  /// Start of the mapping this entry stands for.
+  pub fn base(&self) -> usize {
+    let extent = match self {
+      Self::Slab { slab, .. } => unsafe { slab.as_ref() }.extent(),
+      Self::Extent { extent } => unsafe { extent.as_ref() },
+    };
+    extent.as_ref().as_ptr() as usize
+  }
 }
 
 unsafe impl Send for OwnerInfo {}
@@ -68,103 +76,69 @@ impl From<PrimError> for LookupError {
   }
 }
 
+// Keyed by page number rather than address, so consecutive pages share a leaf
+// instead of each landing in its own.
+#[inline(always)]
+fn page_key(addr: usize) -> usize {
+  addr / page_size()
+}
+
+// The pages an extent covers; empty for a zero-length one.
+fn pages_of(extent: NonNull<Extent>) -> Result<PageRange, LookupError> {
+  let slice = unsafe { extent.as_ref() }.as_ref();
+  PageRange::new(slice.as_ptr() as usize, slice.len()).map_err(|err| match err {
+    PrimError::Overflow => LookupError::RangeOverflow,
+    err => LookupError::Align(err),
+  })
+}
+
 pub struct ArenaMap {
-  tree: UnsafeCell<RTree<ArenaId, FANOUT>>,
+  // Lookups run lock-free; the tree serializes its own writers.
+  tree: RTree<ArenaId, FANOUT>,
 }
 
 impl ArenaMap {
   pub const fn new(chunk_size: usize) -> Self {
     Self {
-      tree: UnsafeCell::new(RTree::new(chunk_size)),
+      tree: RTree::new(chunk_size),
     }
   }
 
-  pub unsafe fn tree(&self) -> &RTree<ArenaId, FANOUT> {
-    unsafe { &*self.tree.get() }
-  }
-
-  #[allow(clippy::mut_from_ref)]
-  pub unsafe fn tree_mut(&self) -> &mut RTree<ArenaId, FANOUT> {
-    unsafe { &mut *self.tree.get() }
-  }
-
-  fn page_range(&self, extent: NonNull<Extent>) -> Result<Option<(usize, usize)>, LookupError> {
-    let extent_ref = unsafe { extent.as_ref() };
-    let slice = extent_ref.as_ref();
-    let base = slice.as_ptr() as usize;
-    let len = slice.len();
-
-    if len == 0 {
-      return Ok(None);
-    }
-
-    let start = page_align_down(base)?;
-    let end_addr = base.checked_add(len).ok_or(LookupError::RangeOverflow)?;
-    let last_page = page_align_down(end_addr.saturating_sub(1))?;
-
-    Ok(Some((start, last_page)))
-  }
-
-  fn range_execute<F>(
-    &self,
-    start: usize,
-    stop: usize,
-    step: usize,
-    f: F,
-  ) -> Result<(), LookupError>
-  where
-    F: FnMut(usize) -> Result<(), LookupError>,
-  {
-    let mut current = start;
-    let mut func = f;
-    while current <= stop {
-      func(current)?;
-      current = current
-        .checked_add(step)
-        .ok_or(LookupError::RangeOverflow)?;
-    }
-    Ok(())
+  pub fn tree(&self) -> &RTree<ArenaId, FANOUT> {
+    &self.tree
   }
 
   pub fn associate(&self, extent: NonNull<Extent>, id: ArenaId) -> Result<(), LookupError> {
-    let Some((start, last_page)) = self.page_range(extent)? else {
-      return Ok(());
-    };
-
-    let page_sz = page_size();
-    self.range_execute(start, last_page, page_sz, |addr| {
-      let tree = unsafe { self.tree_mut() };
-      Ok(tree.insert(addr, id)?)
-    })?;
+    self.associate_range(pages_of(extent)?, id)
+  }
 
-    Ok(())
+  /// Maps every page of `pages` to `id`, e.g. the ones an extent just grew by.
+  pub fn associate_range(&self, pages: PageRange, id: ArenaId) -> Result<(), LookupError> {
+    pages
+      .into_iter()
+      .try_for_each(|addr| Ok(self.tree.insert(page_key(addr), id)?))
   }
 
   pub fn detach(&self, extent: NonNull<Extent>) -> Result<(), LookupError> {
-    let Some((start, last_page)) = self.page_range(extent)? else {
+    let pages = pages_of(extent)?;
+    let Some(last_page) = pages.last_page() else {
       return Ok(());
     };
+    self.detach_range(pages.start, last_page)
+  }
 
-    let page_sz = page_size();
-    let mut removed_any = false;
-    self.range_execute(start, last_page, page_sz, |addr| {
-      let tree = unsafe { self.tree_mut() };
-      if tree.remove(addr).is_some() {
-        removed_any = true;
-      }
-      Ok(())
-    })?;
-
-    if removed_any {
-      Ok(())
-    } else {
-      Err(LookupError::NotFound)
+  /// Drops the pages from `start` through `last_page` in one batched tree pass.
+  ///
+  /// `NotFound` if none of them was mapped.
+  pub fn detach_range(&self, start: usize, last_page: usize) -> Result<(), LookupError> {
+    match self.tree.remove_range(page_key(start), page_key(last_page)) {
+      0 => Err(LookupError::NotFound),
+      _ => Ok(()),
     }
   }
 
   pub fn lookup(&self, addr: usize) -> Option<ArenaId> {
-    let aligned_addr = page_align_down(addr).ok()?;
-    unsafe { self.tree() }.lookup(aligned_addr).copied()
+    self.tree.lookup(page_key(addr)).copied()
   }
 }
 
@@ -172,102 +146,66 @@ unsafe impl Send for ArenaMap {}
 unsafe impl Sync for ArenaMap {}
 
 pub struct ExtentTree {
-  tree: UnsafeCell<RTree<OwnerInfo, FANOUT>>,
+  // Lookups run lock-free; the tree serializes its own writers.
+  tree: RTree<OwnerInfo, FANOUT>,
 }
 
 impl ExtentTree {
   pub const fn new(chunk_size: usize) -> Self {
     Self {
-      tree: UnsafeCell::new(RTree::new(chunk_size)),
+      tree: RTree::new(chunk_size),
     }
   }
 
-  pub unsafe fn tree(&self) -> &RTree<OwnerInfo, FANOUT> {
-    unsafe { &*self.tree.get() }
-  }
-
-  #[allow(clippy::mut_from_ref)]
-  pub unsafe fn tree_mut(&self) -> &mut RTree<OwnerInfo, FANOUT> {
-    unsafe { &mut *self.tree.get() }
-  }
-
-  fn page_range(extent: NonNull<Extent>) -> Result<Option<(usize, usize)>, LookupError> {
-    let extent_ref = unsafe { extent.as_ref() };
-    let slice = extent_ref.as_ref();
-    let base = slice.as_ptr() as usize;
-    let len = slice.len();
-
-    if len == 0 {
-      return Ok(None);
+  pub const fn new_in(chunk_size: usize, system: &'static dyn System) -> Self {
+    Self {
+      tree: RTree::new_in(chunk_size, system),
     }
-
-    let start = page_align_down(base)?;
-    let end_addr = base.checked_add(len).ok_or(LookupError::RangeOverflow)?;
-    let last_page = page_align_down(end_addr.saturating_sub(1))?;
-
-    Ok(Some((start, last_page)))
   }
 
-  fn range_execute<F>(
-    &self,
-    start: usize,
-    stop: usize,
-    step: usize,
-    f: F,
-  ) -> Result<(), LookupError>
-  where
-    F: FnMut(usize) -> Result<(), LookupError>,
-  {
-    let mut current = start;
-    let mut func = f;
-    while current <= stop {
-      func(current)?;
-      current = current
-        .checked_add(step)
-        .ok_or(LookupError::RangeOverflow)?;
-    }
-    Ok(())
+  pub fn tree(&self) -> &RTree<OwnerInfo, FANOUT> {
+    &self.tree
   }
 
   pub fn register(&self, extent: NonNull<Extent>, info: OwnerInfo) -> Result<(), LookupError> {
-    let Some((start, last_page)) = Self::page_range(extent)? else {
-      return Ok(());
-    };
-
-    let page_sz = page_size();
-    self.range_execute(start, last_page, page_sz, |addr| {
-      let tree = unsafe { self.tree_mut() };
-      Ok(tree.insert(addr, info.clone())?)
-    })?;
+    self.register_range(pages_of(extent)?, info)
+  }
 
-    Ok(())
+  /// Points every page of `pages` at `info`, e.g. the ones an extent just grew by.
+  pub fn register_range(&self, pages: PageRange, info: OwnerInfo) -> Result<(), LookupError> {
+    pages
+      .into_iter()
+      .try_for_each(|addr| Ok(self.tree.insert(page_key(addr), info)?))
   }
 
   pub fn unregister(&self, extent: NonNull<Extent>) -> Result<(), LookupError> {
-    let Some((start, last_page)) = Self::page_range(extent)? else {
+    let pages = pages_of(extent)?;
+    let Some(last_page) = pages.last_page() else {
       return Ok(());
     };
 
-    let page_sz = page_size();
-    let mut removed_any = false;
-    self.range_execute(start, last_page, page_sz, |addr| {
-      let tree = unsafe { self.tree_mut() };
-      if tree.remove(addr).is_some() {
-        removed_any = true;
-      }
-      Ok(())
-    })?;
-
-    if removed_any {
-      Ok(())
-    } else {
-      Err(LookupError::NotFound)
+    match self
+      .tree
+      .remove_range(page_key(pages.start), page_key(last_page))
+    {
+      0 => Err(LookupError::NotFound),
+      _ => Ok(()),
     }
   }
 
-  pub fn lookup(&self, addr: usize) -> Option<&OwnerInfo> {
-    let aligned_addr = page_align_down(addr).ok()?;
-    unsafe { self.tree() }.lookup(aligned_addr)
+  pub fn lookup(&self, addr: usize) -> Option<OwnerInfo> {
+    self.tree.lookup(page_key(addr)).copied()
+  }
+
+  /// Returns any large extent still registered here.
+  pub fn any_extent(&self) -> Option<NonNull<Extent>> {
+    let info = self
+      .tree
+      .find(|info| matches!(info, OwnerInfo::Extent { .. }))?;
+    match info {
+      OwnerInfo::Extent { extent } => Some(*extent),
+      OwnerInfo::Slab { .. } => None,
+    }
   }
 }
 
@@ -0,0 +1,98 @@
+use core::sync::atomic::{
+  AtomicU64,
+  AtomicUsize,
+  Ordering,
+};
+
+use crate::{
+  MAX_ARENAS,
+  classes::NSCLASSES,
+};
+
+/// One bucket per size class plus a last one for large allocations.
+pub const BUCKETS: usize = NSCLASSES + 1;
+
+const DEFAULT_RATE: usize = 64;
+
+// Threads map onto arenas, so sharding by arena index keeps writers apart without
+// per-thread registration; a shard shared by a few threads only costs some contention.
+struct Shard {
+  tick: AtomicUsize,
+  counts: [AtomicU64; BUCKETS],
+}
+
+static SHARDS: [Shard; MAX_ARENAS] = [const {
+  Shard {
+    tick: AtomicUsize::new(0),
+    counts: [const { AtomicU64::new(0) }; BUCKETS],
+  }
+}; MAX_ARENAS];
+
+static RATE: AtomicUsize = AtomicUsize::new(DEFAULT_RATE);
+
+pub(crate) const FOOTPRINT: usize = size_of::<[Shard; MAX_ARENAS]>() + size_of::<AtomicUsize>();
+
+/// Samples every `rate`th allocation per shard; `0` is treated as `1`.
+pub fn set_sample_rate(rate: usize) {
+  RATE.store(rate.max(1), Ordering::Relaxed);
+}
+
+pub fn sample_rate() -> usize {
+  RATE.load(Ordering::Relaxed)
+}
+
+/// Counts one allocation in `bucket` from the thread owning arena `shard`.
+///
+/// A sample adds the current rate, so each bucket estimates the allocations it saw.
+#[inline]
+pub fn record(shard: usize, bucket: usize) {
+  let shard = &SHARDS[shard % MAX_ARENAS];
+  let rate = sample_rate();
+  if !shard
+    .tick
+    .fetch_add(1, Ordering::Relaxed)
+    .is_multiple_of(rate)
+  {
+    return;
+  }
+  if let Some(count) = shard.counts.get(bucket) {
+    count.fetch_add(rate as u64, Ordering::Relaxed);
+  }
+}
+
+/// Estimated allocations per bucket, summed over all shards.
+pub fn histogram() -> [u64; BUCKETS] {
+  core::array::from_fn(|bucket| {
+    SHARDS
+      .iter()
+      .map(|shard| shard.counts[bucket].load(Ordering::Relaxed))
+      .sum()
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn histogram_tracks_a_known_mix() {
+    const RATE: usize = 7;
+    set_sample_rate(RATE);
+    let before = histogram();
+
+    // Three small for every large; the rate is coprime to the pattern so samples
+    // land on every position of it.
+    for i in 0..4000 {
+      let bucket = if i % 4 == 3 { NSCLASSES } else { 2 };
+      record(i % 3, bucket);
+    }
+
+    let after = histogram();
+    let small = after[2] - before[2];
+    let large = after[NSCLASSES] - before[NSCLASSES];
+    let slack = (3 * RATE) as u64;
+    assert!(small.abs_diff(3000) <= 3000 / 10 + slack, "small {}", small);
+    assert!(large.abs_diff(1000) <= 1000 / 10 + slack, "large {}", large);
+    assert_eq!(after[1], before[1]);
+  }
+}
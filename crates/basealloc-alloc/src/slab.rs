@@ -14,6 +14,7 @@ use basealloc_bitmap::{
 use basealloc_extent::{
   Extent,
   ExtentError,
+  SharedExtent,
 };
 use basealloc_fixed::bump::{
   Bump,
@@ -23,14 +24,36 @@ use basealloc_list::{
   HasLink,
   Link,
 };
-use basealloc_sys::system::SysOption;
+use basealloc_sys::{
+  math::align_up,
+  prim::{
+    min_align,
+    page_size,
+  },
+  system::SysOption,
+};
 use getset::{
   Getters,
   MutGetters,
 };
 
+use basealloc_sys::hook::{
+  Fault,
+  report,
+};
+
+#[cfg(feature = "poison")]
+use crate::POISON;
+#[cfg(feature = "redzone")]
+use crate::{
+  REDZONE,
+  REDZONE_BYTE,
+};
 use crate::{
-  arena::Arena,
+  arena::{
+    Arena,
+    CommitPolicy,
+  },
   classes::{
     ScIdx,
     SizeClass,
@@ -40,6 +63,7 @@ use crate::{
     OwnerInfo,
   },
   static_::ARENA_MAP,
+  stats,
 };
 
 #[derive(Debug)]
@@ -51,18 +75,165 @@ pub enum SlabError {
   LookupError(LookupError),
   OutOfMemory,
   InvalidPointer,
+  /// The slab size isn't a non-zero multiple of the class size.
+  InvalidSize {
+    size: usize,
+    class: usize,
+  },
+  /// Stack classes can't hand out runs of adjacent slots.
+  RunsUnsupported,
+  /// The slot was already free; only detected with `debug-checks`.
+  DoubleFree,
 }
 
 pub type SlabResult<T> = Result<T, SlabError>;
 
+// Classes below this index (16..=64 bytes) keep their free slots on an intrusive stack.
+const NSTACK: usize = 4;
+
+type FreeLink = Option<NonNull<u8>>;
+
+enum FreeSlots {
+  Bitmap {
+    bitmap: Bitmap,
+    last: usize,
+  },
+  // Free slots hold the link to the next one; `carved` slots past the stack were never handed out.
+  Stack {
+    head: FreeLink,
+    carved: usize,
+    used: usize,
+    regions: usize,
+  },
+}
+
+impl FreeSlots {
+  fn pop(&mut self, base: *mut u8, size: usize) -> Option<NonNull<u8>> {
+    match self {
+      FreeSlots::Bitmap { bitmap, last } => {
+        let slot = bitmap.find_fc(Some(*last))?;
+        bitmap.set(slot).ok()?;
+        *last = slot % bitmap.bits();
+        NonNull::new(unsafe { base.add(slot * size) })
+      }
+      FreeSlots::Stack {
+        head,
+        carved,
+        used,
+        regions,
+      } => {
+        let ptr = match *head {
+          Some(top) => {
+            *head = unsafe { top.cast::<FreeLink>().read() };
+            top
+          }
+          None if *carved < *regions => {
+            *carved += 1;
+            NonNull::new(unsafe { base.add((*carved - 1) * size) })?
+          }
+          None => return None,
+        };
+        *used += 1;
+        Some(ptr)
+      }
+    }
+  }
+
+  fn push(&mut self, ptr: NonNull<u8>, index: usize) -> SlabResult<()> {
+    match self {
+      FreeSlots::Bitmap { bitmap, last } => {
+        bitmap.clear(index).map_err(SlabError::BitmapError)?;
+        *last = index % bitmap.bits();
+      }
+      FreeSlots::Stack { head, used, .. } => {
+        // The cheap half of `check_double_free`: freeing the last-freed slot again.
+        if *head == Some(ptr) || *used == 0 {
+          report(Fault::DoubleFree(ptr.as_ptr() as usize));
+          return Err(SlabError::DoubleFree);
+        }
+        unsafe { ptr.cast::<FreeLink>().write(*head) };
+        *head = Some(ptr);
+        *used -= 1;
+      }
+    }
+    Ok(())
+  }
+
+  /// Forgets the stack; only valid once every slot is free and the pages were dropped.
+  fn reset(&mut self) {
+    if let FreeSlots::Stack { head, carved, .. } = self {
+      *head = None;
+      *carved = 0;
+    }
+  }
+
+  fn used(&self) -> usize {
+    match self {
+      FreeSlots::Bitmap { bitmap, .. } => bitmap.used(),
+      FreeSlots::Stack { used, .. } => *used,
+    }
+  }
+
+  fn capacity(&self) -> usize {
+    match self {
+      FreeSlots::Bitmap { bitmap, .. } => bitmap.bits(),
+      FreeSlots::Stack { regions, .. } => *regions,
+    }
+  }
+
+  fn clear(&mut self) {
+    match self {
+      FreeSlots::Bitmap { bitmap, .. } => bitmap.clear_all(),
+      FreeSlots::Stack { used, .. } => *used = 0,
+    }
+    self.reset();
+  }
+
+  fn is_empty(&self) -> bool {
+    self.used() == 0
+  }
+
+  /// Whether slot `index` is handed out. Stack classes walk their free stack for it.
+  fn is_live(&self, base: *mut u8, size: usize, index: usize) -> bool {
+    match self {
+      FreeSlots::Bitmap { bitmap, .. } => matches!(bitmap.get(index), Ok(true)),
+      FreeSlots::Stack { carved, .. } if index >= *carved => false,
+      FreeSlots::Stack { head, .. } => {
+        let slot = NonNull::new(unsafe { base.add(index * size) });
+        let mut free = *head;
+        while let Some(top) = free {
+          if Some(top) == slot {
+            return false;
+          }
+          free = unsafe { top.cast::<FreeLink>().read() };
+        }
+        true
+      }
+    }
+  }
+
+  #[cfg(feature = "poison")]
+  fn link_bytes(&self) -> usize {
+    match self {
+      FreeSlots::Bitmap { .. } => 0,
+      FreeSlots::Stack { .. } => core::mem::size_of::<FreeLink>(),
+    }
+  }
+}
+
 #[derive(Getters, MutGetters)]
 pub struct Slab {
   class: SizeClass,
+  // Distance between slots: the class size padded to `min_align()`.
+  stride: usize,
   #[getset(get = "pub", get_mut = "pub")]
   extent: Extent,
+  // The region `extent` is a view of, if several slabs share one; dropped after it.
+  group: Option<SharedExtent>,
   link: Link<Self>,
-  bitmap: Bitmap,
-  last: usize,
+  slots: FreeSlots,
+  // One bit per page still zero from the kernel; cleared once a slot on it is handed out.
+  zeroed: Bitmap,
   arena: NonNull<Arena>,
 }
 
@@ -74,13 +245,21 @@ impl Slab {
       .map_err(SlabError::LayoutError)?;
 
     let bm_raw = bump.allocate(bm_layout).map_err(SlabError::BumpError)?;
-    let bm_slice = unsafe {
-      core::slice::from_raw_parts_mut(bm_raw.as_ptr() as *mut BitmapWord, Bitmap::words(regions))
-    };
+    Bitmap::from_bytes(bm_raw, regions).map_err(SlabError::BitmapError)
+  }
 
-    let bitmap = Bitmap::zero(bm_slice, regions).map_err(SlabError::BitmapError)?;
+  fn new_slots(bump: &mut Bump, class: SizeClass, regions: usize) -> SlabResult<FreeSlots> {
+    if class.1.0 < NSTACK {
+      return Ok(FreeSlots::Stack {
+        head: None,
+        carved: 0,
+        used: 0,
+        regions,
+      });
+    }
 
-    Ok(bitmap)
+    let bitmap = Self::new_bitmap(bump, regions)?;
+    Ok(FreeSlots::Bitmap { bitmap, last: 0 })
   }
 
   pub fn new(
@@ -89,20 +268,45 @@ impl Slab {
     size: usize,
     arena: NonNull<Arena>,
   ) -> SlabResult<NonNull<Slab>> {
-    let slab = bump.create::<Slab>().map_err(SlabError::BumpError)? as *mut Slab;
+    Self::check_size(class, size)?;
+    let system = unsafe { arena.as_ref() }.system();
+    let extent =
+      Extent::new_in(size, SysOption::Reserve, system).map_err(SlabError::ExtentError)?;
+    Self::new_in(bump, class, extent, arena)
+  }
 
-    let extent = Extent::new(size, SysOption::Reserve).map_err(SlabError::ExtentError)?;
+  fn check_size(class: SizeClass, size: usize) -> SlabResult<usize> {
+    let stride = align_up(class.0, min_align()).unwrap_or(0);
+    if stride == 0 || size < stride || !size.is_multiple_of(stride) {
+      return Err(SlabError::InvalidSize {
+        size,
+        class: class.0,
+      });
+    }
+    Ok(stride)
+  }
 
-    let region_size = class.0;
-    let regions = size / region_size;
-    let bitmap = Self::new_bitmap(bump, regions)?;
+  /// Like `new`, but carves the slots from `extent`, e.g. a piece of a region the arena
+  /// reserved for several slabs. Its size is the slab size.
+  pub fn new_in(
+    bump: &mut Bump,
+    class: SizeClass,
+    extent: Extent,
+    arena: NonNull<Arena>,
+  ) -> SlabResult<NonNull<Slab>> {
+    let size = extent.size();
+    let stride = Self::check_size(class, size)?;
+    let slab = bump.create::<Slab>().map_err(SlabError::BumpError)? as *mut Slab;
+    let (slots, zeroed) = Self::new_tracking(bump, class, size, stride)?;
 
     let tmp = Self {
       class,
+      stride,
       extent,
+      group: None,
       link: Link::default(),
-      bitmap,
-      last: 0,
+      slots,
+      zeroed,
       arena,
     };
 
@@ -110,10 +314,68 @@ impl Slab {
       core::ptr::write(slab, tmp);
     }
 
-    let _slab_ref = unsafe { &mut *slab };
-    Self::register(unsafe { NonNull::new_unchecked(slab) }, arena, class.1)?;
+    let slab_nn = unsafe { NonNull::new_unchecked(slab) };
+    Self::attach(slab_nn)?;
+    Ok(slab_nn)
+  }
+
+  // Free-slot tracking for the `stride`-spaced slots of `size` bytes, and a zero bit per
+  // page, sized for the slab's own range whatever region it was carved from.
+  fn new_tracking(
+    bump: &mut Bump,
+    class: SizeClass,
+    size: usize,
+    stride: usize,
+  ) -> SlabResult<(FreeSlots, Bitmap)> {
+    let slots = Self::new_slots(bump, class, size / stride)?;
+    let zeroed = Self::new_bitmap(bump, size.div_ceil(page_size()))?;
+    Ok((slots, zeroed))
+  }
+
+  // Makes the slab's extent findable and, for eager arenas, faults it in.
+  fn attach(mut slab: NonNull<Slab>) -> SlabResult<()> {
+    let slab_ref = unsafe { slab.as_mut() };
+    Self::register(slab, slab_ref.arena, slab_ref.class.1)?;
+    if unsafe { slab_ref.arena.as_ref() }.commit_policy() == CommitPolicy::Eager {
+      slab_ref.prefault()?;
+    }
+    Ok(())
+  }
 
-    Ok(unsafe { NonNull::new_unchecked(slab) })
+  /// Unregisters and unmaps the slab's pages but keeps the header and its bitmaps, so a
+  /// later `refill` can reuse them instead of taking more of the arena's bump.
+  ///
+  /// # Safety
+  ///
+  /// The slab must be empty and off every list. Until `refill`, nothing may touch it:
+  /// its extent is gone, so it must not be dropped either.
+  pub unsafe fn unmap(mut slab: NonNull<Slab>) {
+    let slab_ref = unsafe { slab.as_mut() };
+    slab_ref.slots.clear();
+    slab_ref.zeroed.clear_all();
+    slab_ref.unregister();
+    unsafe { core::ptr::drop_in_place(&mut slab_ref.extent) };
+    slab_ref.group = None;
+  }
+
+  /// Gives a slab from `unmap` the slots of `extent`, which must be as large as the
+  /// one it lost.
+  ///
+  /// # Safety
+  ///
+  /// `slab` must come from `unmap` and not have been refilled since. If this fails the
+  /// slab holds `extent` again and is dropped like any other.
+  pub unsafe fn refill(mut slab: NonNull<Slab>, extent: Extent) -> SlabResult<()> {
+    let slab_ref = unsafe { slab.as_mut() };
+    unsafe { core::ptr::write(&mut slab_ref.extent, extent) };
+    slab_ref.link = Link::default();
+    Self::attach(slab)
+  }
+
+  /// Keeps `group`, the region the slab's extent is a view of, mapped for as long as the
+  /// slab holds its pages.
+  pub fn hold_group(&mut self, group: Option<SharedExtent>) {
+    self.group = group;
   }
 
   fn register(slab_ptr: NonNull<Slab>, arena: NonNull<Arena>, class_idx: ScIdx) -> SlabResult<()> {
@@ -121,27 +383,25 @@ impl Slab {
     let extent_nn = unsafe { NonNull::new_unchecked(&slab_ref.extent as *const _ as *mut _) };
     let info = OwnerInfo::new_slab(slab_ptr, class_idx);
 
+    // The arena map is filled first and emptied last, so every `etree` entry is in it.
     let arena_ref = unsafe { arena.as_ref() };
+    ARENA_MAP
+      .associate(extent_nn, arena_ref.index())
+      .map_err(SlabError::LookupError)?;
+
     arena_ref
       .etree()
       .register(extent_nn, info)
       .map_err(SlabError::LookupError)?;
-
-    ARENA_MAP
-      .associate(extent_nn, arena_ref.index())
-      .map_err(SlabError::LookupError)?;
     Ok(())
   }
 
-  fn update_last(&mut self, found: usize) {
-    self.last = found % self.bitmap.bits();
-  }
+  fn unregister(&self) {
+    let extent_nn = unsafe { NonNull::new_unchecked(&self.extent as *const _ as *mut _) };
+    let arena_ref = unsafe { self.arena.as_ref() };
+    let _ = arena_ref.etree().unregister(extent_nn);
 
-  fn ptr_at(&mut self, index: usize) -> NonNull<u8> {
-    let offset = index * self.class.0;
-    let eslice = self.extent.as_mut();
-    let ptr = unsafe { eslice.as_mut_ptr().add(offset) };
-    NonNull::new(ptr).unwrap()
+    let _ = ARENA_MAP.detach(extent_nn);
   }
 
   fn has_ptr(&self, ptr: NonNull<u8>) -> bool {
@@ -158,23 +418,211 @@ impl Slab {
 
     let base_ptr = self.extent.as_ref().as_ptr() as *mut u8;
     let offset = unsafe { ptr.as_ptr().offset_from(base_ptr) as usize };
-    Some(offset / self.class.0)
+    Some(offset / self.stride)
+  }
+
+  /// Alignment every slot meets: the largest power of two dividing the stride, up to a page.
+  pub fn guaranteed_align(&self) -> usize {
+    (1 << self.stride.trailing_zeros()).min(page_size())
+  }
+
+  #[cfg(any(feature = "poison", feature = "redzone"))]
+  fn slot_mut(&mut self, index: usize) -> &mut [u8] {
+    let offset = index * self.stride;
+    &mut self.extent.as_mut()[offset..offset + self.class.0]
+  }
+
+  // Pushing a free slot again would hand it out twice; stack classes walk their stack.
+  #[cfg(feature = "debug-checks")]
+  fn check_double_free(&self, ptr: NonNull<u8>, index: usize) -> SlabResult<()> {
+    let base = self.extent.as_ref().as_ptr() as *mut u8;
+    if self.slots.is_live(base, self.stride, index) {
+      return Ok(());
+    }
+    report(Fault::DoubleFree(ptr.as_ptr() as usize));
+    Err(SlabError::DoubleFree)
+  }
+
+  #[cfg(feature = "poison")]
+  fn check_poison(&mut self, ptr: NonNull<u8>) {
+    self.check_poison_at(self.index_for(ptr).unwrap());
+  }
+
+  #[cfg(feature = "poison")]
+  fn check_poison_at(&mut self, index: usize) {
+    let skip = self.slots.link_bytes();
+    let slot = self.slot_mut(index);
+    if slot[skip..].iter().any(|b| *b != POISON) {
+      report(Fault::WriteAfterFree(slot.as_ptr() as usize));
+    }
+  }
+
+  #[cfg(feature = "redzone")]
+  fn redzone_mut(&mut self, ptr: NonNull<u8>) -> &mut [u8] {
+    self.redzone_at(self.index_for(ptr).unwrap())
+  }
+
+  #[cfg(feature = "redzone")]
+  fn redzone_at(&mut self, index: usize) -> &mut [u8] {
+    let slot = self.slot_mut(index);
+    let start = slot.len() - REDZONE;
+    &mut slot[start..]
+  }
+
+  #[cfg(feature = "redzone")]
+  fn check_redzone(&mut self, ptr: NonNull<u8>) {
+    if self.redzone_mut(ptr).iter().any(|b| *b != REDZONE_BYTE) {
+      report(Fault::RedzoneOverwrite(ptr.as_ptr() as usize));
+    }
+  }
+
+  fn activate(&mut self) -> SlabResult<()> {
+    self.extent.activate().map_err(SlabError::ExtentError)?;
+    self.slots.reset();
+    // Fresh pages are zero; poison them so every free slot carries the pattern.
+    #[cfg(feature = "poison")]
+    self.extent.as_mut().fill(POISON);
+    #[cfg(not(feature = "poison"))]
+    self.zeroed.set_all();
+    Ok(())
+  }
+
+  /// Commits the slab and faults in every page now, for `CommitPolicy::Eager` arenas.
+  fn prefault(&mut self) -> SlabResult<()> {
+    self.activate()?;
+    let region = self.extent.as_mut();
+    for offset in (0..region.len()).step_by(page_size()) {
+      // Rewrite what's there: the pages keep reading as zero, or as poison.
+      let byte = unsafe { region.as_mut_ptr().add(offset) };
+      unsafe { byte.write_volatile(byte.read_volatile()) };
+    }
+    Ok(())
+  }
+
+  /// Whether every page under the `len` bytes at `ptr` was still kernel-zeroed; marks
+  /// them written.
+  fn claim_pages(&self, ptr: NonNull<u8>, len: usize) -> bool {
+    if self.zeroed.is_clear() {
+      return false;
+    }
+
+    let ps = page_size();
+    let start = ptr.as_ptr() as usize - self.extent.as_ref().as_ptr() as usize;
+    let pages = start / ps..(start + len).div_ceil(ps);
+    pages.fold(true, |fresh, page| {
+      let was_zero = matches!(self.zeroed.get(page), Ok(true));
+      if was_zero {
+        let _ = self.zeroed.clear(page);
+      }
+      fresh && was_zero
+    })
+  }
+
+  // Hands out a slot along with whether it still reads as zero.
+  fn take_slot(&mut self) -> SlabResult<(NonNull<u8>, bool)> {
+    if !self.extent.is_activated() {
+      self.activate()?;
+    }
+
+    let base = self.extent.as_mut().as_mut_ptr();
+    let ptr = self
+      .slots
+      .pop(base, self.stride)
+      .ok_or(SlabError::OutOfMemory)?;
+    let fresh = self.claim_pages(ptr, self.class.0);
+    #[cfg(feature = "poison")]
+    self.check_poison(ptr);
+    #[cfg(feature = "redzone")]
+    self.redzone_mut(ptr).fill(REDZONE_BYTE);
+    stats::add_live(self.class.1, 1);
+    Ok((ptr, fresh))
   }
 
   pub fn allocate(&mut self) -> SlabResult<NonNull<u8>> {
+    self.take_slot().map(|(ptr, _)| ptr)
+  }
+
+  /// Like `allocate`, but the slot reads as zero.
+  ///
+  /// Slots on pages the kernel zeroed (fresh, deactivated or reclaimed) that nothing was
+  /// handed out from since skip the memset.
+  pub fn allocate_zeroed(&mut self) -> SlabResult<NonNull<u8>> {
+    let (ptr, fresh) = self.take_slot()?;
+    if !fresh {
+      unsafe { ptr.as_ptr().write_bytes(0, self.user_len()) };
+      stats::add_zero_fill();
+    }
+    Ok(ptr)
+  }
+
+  /// Hands out `n` adjacent slots as one block of `n` strides; free it with
+  /// `deallocate_run`.
+  ///
+  /// `OutOfMemory` if no such run is free, however many single slots are.
+  pub fn allocate_run(&mut self, n: usize) -> SlabResult<NonNull<u8>> {
     if !self.extent.is_activated() {
-      self.extent.activate().map_err(SlabError::ExtentError)?;
+      self.activate()?;
     }
 
-    let slot = self.bitmap.find_fc(Some(self.last));
-    if slot.is_none() {
-      return Err(SlabError::OutOfMemory);
+    let first = self.claim_run(n)?;
+    let base = self.extent.as_mut().as_mut_ptr();
+    let ptr =
+      NonNull::new(unsafe { base.add(first * self.stride) }).ok_or(SlabError::OutOfMemory)?;
+    self.claim_pages(ptr, n * self.stride);
+    #[cfg(feature = "poison")]
+    (first..first + n).for_each(|index| self.check_poison_at(index));
+    // Only the end of the run is guarded; the slots inside it are the caller's.
+    #[cfg(feature = "redzone")]
+    self.redzone_at(first + n - 1).fill(REDZONE_BYTE);
+    stats::add_live(self.class.1, n);
+    Ok(ptr)
+  }
+
+  fn claim_run(&mut self, n: usize) -> SlabResult<usize> {
+    let FreeSlots::Bitmap { bitmap, .. } = &self.slots else {
+      return Err(SlabError::RunsUnsupported);
+    };
+    let first = bitmap.find_fc_run(n).ok_or(SlabError::OutOfMemory)?;
+    (first..first + n)
+      .try_for_each(|index| bitmap.set(index))
+      .map_err(SlabError::BitmapError)?;
+    Ok(first)
+  }
+
+  /// Frees a block `allocate_run(n)` handed out at `ptr`.
+  pub fn deallocate_run(&mut self, ptr: NonNull<u8>, n: usize) -> SlabResult<()> {
+    let first = self.index_for(ptr).ok_or(SlabError::InvalidPointer)?;
+    if n == 0 || first + n > self.slots.capacity() {
+      return Err(SlabError::InvalidPointer);
+    }
+
+    #[cfg(feature = "redzone")]
+    if self
+      .redzone_at(first + n - 1)
+      .iter()
+      .any(|b| *b != REDZONE_BYTE)
+    {
+      report(Fault::RedzoneOverwrite(ptr.as_ptr() as usize));
+    }
+    for index in first..first + n {
+      #[cfg(feature = "poison")]
+      self.slot_mut(index).fill(POISON);
+      let slot = unsafe { ptr.add((index - first) * self.stride) };
+      self.slots.push(slot, index)?;
     }
+    stats::sub_live(self.class.1, n);
+    Ok(())
+  }
 
-    let slot = slot.unwrap();
-    self.bitmap.set(slot).map_err(SlabError::BitmapError)?;
-    self.update_last(slot);
-    Ok(self.ptr_at(slot))
+  // Bytes of a slot the caller may use; the redzone sits past them.
+  #[cfg(not(feature = "redzone"))]
+  fn user_len(&self) -> usize {
+    self.class.0
+  }
+
+  #[cfg(feature = "redzone")]
+  fn user_len(&self) -> usize {
+    self.class.0 - REDZONE
   }
 
   pub fn deallocate(&mut self, ptr: NonNull<u8>) -> SlabResult<()> {
@@ -183,13 +631,107 @@ impl Slab {
     }
 
     let index = self.index_for(ptr).unwrap();
-    self.bitmap.clear(index).map_err(SlabError::BitmapError)?;
-    self.update_last(index);
+    #[cfg(feature = "debug-checks")]
+    self.check_double_free(ptr, index)?;
+    #[cfg(feature = "redzone")]
+    self.check_redzone(ptr);
+    #[cfg(feature = "poison")]
+    self.slot_mut(index).fill(POISON);
+    self.slots.push(ptr, index)?;
+    stats::sub_live(self.class.1, 1);
     Ok(())
   }
 
   pub fn is_empty(&self) -> bool {
-    self.bitmap.is_clear()
+    self.slots.is_empty()
+  }
+
+  /// Whether no live slot overlaps `page`.
+  fn page_is_free(&self, bitmap: &Bitmap, page: usize) -> bool {
+    let ps = page_size();
+    let first = page * ps / self.stride;
+    let end = ((page + 1) * ps).div_ceil(self.stride);
+    // `find_fs` wraps, so a hit below `first` also means the page is clear.
+    bitmap
+      .find_fs(Some(first))
+      .is_none_or(|set| set < first || set >= end)
+  }
+
+  /// Hands back the pages of a partly used slab that hold no live slot; returns how many.
+  ///
+  /// The slab stays active and the pages refault as zeros when a slot on them is reused.
+  /// Stack classes keep free-list links inside free slots and poison builds need the
+  /// pattern intact, so both keep their pages.
+  pub fn reclaim_empty_pages(&mut self) -> SlabResult<usize> {
+    let FreeSlots::Bitmap { bitmap, .. } = &self.slots else {
+      return Ok(0);
+    };
+    if cfg!(feature = "poison") || !self.extent.is_activated() {
+      return Ok(0);
+    }
+
+    let ps = page_size();
+    let mut reclaimed = 0;
+    for page in 0..self.extent.size() / ps {
+      if !self.page_is_free(bitmap, page) {
+        continue;
+      }
+      let range = page * ps..(page + 1) * ps;
+      self
+        .extent
+        .reclaim_range(range)
+        .map_err(SlabError::ExtentError)?;
+      if !matches!(self.zeroed.get(page), Ok(true)) {
+        let _ = self.zeroed.set(page);
+      }
+      reclaimed += 1;
+    }
+    Ok(reclaimed)
+  }
+
+  /// Slots currently handed out.
+  pub fn live_count(&self) -> usize {
+    self.slots.used()
+  }
+
+  /// Slots the slab holds in total.
+  pub fn capacity(&self) -> usize {
+    self.slots.capacity()
+  }
+
+  pub fn is_full(&self) -> bool {
+    self.live_count() == self.capacity()
+  }
+
+  /// Free slots left before the slab is full.
+  pub fn spare(&self) -> usize {
+    self.capacity() - self.live_count()
+  }
+
+  /// The slots currently handed out, lowest address first.
+  pub fn live_slots(&self) -> impl Iterator<Item = NonNull<u8>> + '_ {
+    let base = self.extent.as_ref().as_ptr() as *mut u8;
+    (0..self.slots.capacity())
+      .filter(move |index| self.slots.is_live(base, self.stride, *index))
+      .filter_map(move |index| NonNull::new(unsafe { base.add(index * self.stride) }))
+  }
+
+  /// Checks the free-slot bookkeeping against itself.
+  pub fn validate(&self) -> Result<(), &'static str> {
+    match &self.slots {
+      FreeSlots::Bitmap { bitmap, .. } if bitmap.used() != bitmap.count_set() => {
+        Err("slab bitmap's used count doesn't match its set bits")
+      }
+      FreeSlots::Stack {
+        carved,
+        used,
+        regions,
+        ..
+      } if used > carved || carved > regions => {
+        Err("slab stack hands out more slots than it carved")
+      }
+      _ => Ok(()),
+    }
   }
 }
 
@@ -205,11 +747,11 @@ impl HasLink for Slab {
 
 impl Drop for Slab {
   fn drop(&mut self) {
-    let extent_nn = unsafe { NonNull::new_unchecked(&self.extent as *const _ as *mut _) };
-    let _ = ARENA_MAP.detach(extent_nn);
-
-    let arena_ref = unsafe { self.arena.as_ref() };
-    let _ = arena_ref.etree().unregister(extent_nn);
+    // Slots still out at teardown die with the extent; settle them in the stats.
+    stats::sub_live(self.class.1, self.slots.used());
+    self.slots.clear();
+    self.zeroed.clear_all();
+    self.unregister();
   }
 }
 
@@ -271,4 +813,358 @@ mod tests {
     let p = slab.allocate().expect("alloc after free");
     assert!(slab.has_ptr(p));
   }
+
+  #[test]
+  fn validate_catches_a_desynced_bitmap() {
+    let mut bump = Bump::new(CHUNK_SIZE);
+    let class_idx = ScIdx(NSTACK);
+    let SlabPages(slab_size) = pages_for(class_idx);
+    let arena = unsafe { Arena::new(ArenaId(5), CHUNK_SIZE).expect("arena") };
+    let mut slab_ptr = Slab::new(&mut bump, class_at(class_idx), slab_size, arena).unwrap();
+    let slab = unsafe { slab_ptr.as_mut() };
+
+    let p = slab.allocate().expect("alloc");
+    assert_eq!(slab.validate(), Ok(()));
+
+    let FreeSlots::Bitmap { bitmap, .. } = &slab.slots else {
+      panic!("class {} should use a bitmap", NSTACK);
+    };
+    bitmap.store()[0].fetch_or(0b10, core::sync::atomic::Ordering::Relaxed);
+    assert_eq!(
+      slab.validate(),
+      Err("slab bitmap's used count doesn't match its set bits")
+    );
+
+    bitmap.store()[0].fetch_and(!0b10, core::sync::atomic::Ordering::Relaxed);
+    slab.deallocate(p).expect("dealloc");
+  }
+
+  #[cfg(not(feature = "poison"))]
+  #[test]
+  fn zeroed_slots_on_reclaimed_pages_skip_the_memset() {
+    let mut bump = Bump::new(CHUNK_SIZE);
+    let class_idx = ScIdx(NSTACK);
+    let class = class_at(class_idx);
+    let SlabPages(slab_size) = pages_for(class_idx);
+    let arena = unsafe { Arena::new(ArenaId(5), CHUNK_SIZE).expect("arena") };
+    let mut slab_ptr = Slab::new(&mut bump, class, slab_size, arena).expect("create slab");
+    let slab = unsafe { slab_ptr.as_mut() };
+    let user = slab.user_len();
+    let zeroed = |p: NonNull<u8>| {
+      unsafe { core::slice::from_raw_parts(p.as_ptr(), user) }
+        .iter()
+        .all(|b| *b == 0)
+    };
+
+    let mut slots: Vec<_> = (0..slab_size / class.0)
+      .map(|_| slab.allocate().expect("alloc"))
+      .collect();
+    slots
+      .iter()
+      .for_each(|p| unsafe { p.as_ptr().write_bytes(0xAB, class.0) });
+    // Keep the last slot live and free the rest downwards, so slot 0 comes back first.
+    let kept = slots.pop().unwrap();
+    slots
+      .iter()
+      .rev()
+      .for_each(|p| slab.deallocate(*p).expect("dealloc"));
+    assert!(slab.reclaim_empty_pages().expect("reclaim") > 0);
+
+    let fills = stats::zero_fills();
+    let fresh = slab.allocate_zeroed().expect("alloc zeroed");
+    assert_eq!(fresh, slots[0]);
+    assert!(zeroed(fresh));
+    assert_eq!(stats::zero_fills(), fills);
+
+    // Handed out since the reclaim, so it has to be cleared by hand this time.
+    unsafe { fresh.as_ptr().write_bytes(0xCD, class.0) };
+    slab.deallocate(fresh).expect("dealloc");
+    let dirty = slab.allocate_zeroed().expect("alloc zeroed");
+    assert_eq!(dirty, fresh);
+    assert!(zeroed(dirty));
+    assert_eq!(stats::zero_fills(), fills + 1);
+
+    slab.deallocate(dirty).expect("dealloc");
+    slab.deallocate(kept).expect("dealloc");
+  }
+
+  #[cfg(feature = "poison")]
+  #[test]
+  fn poison_detects_write_after_free() {
+    use crate::testing::{
+      saw,
+      watch_faults,
+    };
+
+    watch_faults();
+    let mut bump = Bump::new(CHUNK_SIZE);
+    let class_idx = class_for(QUANTUM).unwrap();
+    let class = class_at(class_idx);
+    let SlabPages(slab_size) = pages_for(class_idx);
+    let arena = unsafe { Arena::new(ArenaId(6), CHUNK_SIZE).expect("arena") };
+    let mut slab_ptr = Slab::new(&mut bump, class, slab_size, arena).expect("create slab");
+    let slab = unsafe { slab_ptr.as_mut() };
+
+    let p = slab.allocate().expect("alloc");
+    slab.deallocate(p).expect("dealloc");
+    let freed = unsafe { core::slice::from_raw_parts(p.as_ptr(), class.0) };
+    // Tiny classes keep the free-stack link in the first word.
+    let link = core::mem::size_of::<FreeLink>();
+    assert!(freed[link..].iter().all(|b| *b == POISON));
+
+    unsafe { p.as_ptr().add(class.0 - 1).write(0x11) };
+    let again = slab.allocate().expect("realloc");
+    assert_eq!(again, p);
+    assert!(saw(Fault::WriteAfterFree(p.as_ptr() as usize)));
+  }
+
+  #[test]
+  fn repeated_stack_free_is_refused() {
+    use crate::testing::{
+      saw,
+      watch_faults,
+    };
+
+    watch_faults();
+    let mut bump = Bump::new(CHUNK_SIZE);
+    let arena = unsafe { Arena::new(ArenaId(31), CHUNK_SIZE).expect("arena") };
+    let class = class_at(ScIdx(0));
+    let SlabPages(slab_size) = pages_for(ScIdx(0));
+    let mut slab_ptr = Slab::new(&mut bump, class, slab_size, arena).expect("create slab");
+    let slab = unsafe { slab_ptr.as_mut() };
+
+    let kept = slab.allocate().expect("alloc");
+    let p = slab.allocate().expect("alloc");
+    slab.deallocate(p).expect("dealloc");
+    assert!(matches!(slab.deallocate(p), Err(SlabError::DoubleFree)));
+    assert!(saw(Fault::DoubleFree(p.as_ptr() as usize)));
+    assert_eq!(slab.live_count(), 1);
+    slab.deallocate(kept).expect("dealloc");
+    assert!(matches!(slab.deallocate(kept), Err(SlabError::DoubleFree)));
+    assert_eq!(slab.live_count(), 0);
+  }
+
+  #[cfg(feature = "debug-checks")]
+  #[test]
+  fn double_free_is_reported() {
+    use crate::testing::{
+      saw,
+      watch_faults,
+    };
+
+    watch_faults();
+    let mut bump = Bump::new(CHUNK_SIZE);
+    let arena = unsafe { Arena::new(ArenaId(29), CHUNK_SIZE).expect("arena") };
+    // A stack class and a bitmap class.
+    for class_idx in [ScIdx(0), ScIdx(NSTACK)] {
+      let class = class_at(class_idx);
+      let SlabPages(slab_size) = pages_for(class_idx);
+      let mut slab_ptr = Slab::new(&mut bump, class, slab_size, arena).expect("create slab");
+      let slab = unsafe { slab_ptr.as_mut() };
+
+      let kept = slab.allocate().expect("alloc");
+      let p = slab.allocate().expect("alloc");
+      slab.deallocate(p).expect("dealloc");
+      assert!(matches!(slab.deallocate(p), Err(SlabError::DoubleFree)));
+      assert!(saw(Fault::DoubleFree(p.as_ptr() as usize)));
+      assert_eq!(slab.live_count(), 1);
+      assert_ne!(
+        slab.allocate().expect("alloc"),
+        slab.allocate().expect("alloc")
+      );
+      slab.deallocate(kept).expect("dealloc");
+    }
+  }
+
+  #[cfg(feature = "redzone")]
+  #[test]
+  fn redzone_detects_overflow_on_free() {
+    use crate::{
+      classes::class_for_layout,
+      testing::{
+        saw,
+        watch_faults,
+      },
+    };
+
+    watch_faults();
+    let mut bump = Bump::new(CHUNK_SIZE);
+    let class_idx = class_for_layout(Layout::from_size_align(24, 8).unwrap()).unwrap();
+    let class = class_at(class_idx);
+    assert!(class.0 >= 24 + REDZONE);
+    let SlabPages(slab_size) = pages_for(class_idx);
+    let arena = unsafe { Arena::new(ArenaId(14), CHUNK_SIZE).expect("arena") };
+    let mut slab_ptr = Slab::new(&mut bump, class, slab_size, arena).expect("create slab");
+    let slab = unsafe { slab_ptr.as_mut() };
+
+    let clean = slab.allocate().expect("alloc");
+    slab.deallocate(clean).expect("dealloc");
+    assert!(!saw(Fault::RedzoneOverwrite(clean.as_ptr() as usize)));
+
+    let p = slab.allocate().expect("alloc");
+    unsafe { p.as_ptr().add(class.0 - 1).write(0) };
+    slab.deallocate(p).expect("dealloc");
+    assert!(saw(Fault::RedzoneOverwrite(p.as_ptr() as usize)));
+  }
+
+  #[cfg(not(feature = "poison"))]
+  #[test]
+  fn reclaim_drops_only_fully_free_pages() {
+    use basealloc_sys::GLOBAL_SYSTEM;
+
+    let ps = page_size();
+    // A bitmap class whose slab spans a few pages.
+    let class_idx = (NSTACK..)
+      .map(ScIdx)
+      .find(|idx| pages_for(*idx).0 >= 4 * ps)
+      .unwrap();
+    let class = class_at(class_idx);
+    let SlabPages(slab_size) = pages_for(class_idx);
+    let mut bump = Bump::new(CHUNK_SIZE);
+    let arena = unsafe { Arena::new(ArenaId(15), CHUNK_SIZE).expect("arena") };
+    let mut slab_ptr = Slab::new(&mut bump, class, slab_size, arena).expect("create slab");
+    let slab = unsafe { slab_ptr.as_mut() };
+
+    let slots: Vec<_> = (0..slab_size / class.0)
+      .map(|_| slab.allocate().expect("alloc"))
+      .collect();
+    slots.iter().for_each(|p| unsafe { p.as_ptr().write(1) });
+    let pages = slab_size / ps;
+    assert_eq!(slab.reclaim_empty_pages().unwrap(), 0);
+
+    // Free every slot touching the second page.
+    let page = slab.extent().as_ref()[ps..2 * ps].as_ptr_range();
+    for p in slots.iter().copied() {
+      let end = unsafe { p.as_ptr().add(class.0) } as *const u8;
+      if end > page.start && (p.as_ptr() as *const u8) < page.end {
+        slab.deallocate(p).expect("dealloc");
+      }
+    }
+
+    assert_eq!(slab.reclaim_empty_pages().unwrap(), 1);
+    let resident = |slab: &Slab, range: core::ops::Range<usize>| unsafe {
+      GLOBAL_SYSTEM.resident_pages(&slab.extent().as_ref()[range])
+    };
+    assert_eq!(resident(slab, ps..2 * ps).unwrap(), 0);
+    assert_eq!(resident(slab, 0..slab_size).unwrap(), pages - 1);
+
+    // The slot goes back on the reclaimed page and faults it in again on use.
+    let reused = slab.allocate().expect("alloc after reclaim");
+    unsafe { reused.as_ptr().write(1) };
+    assert_eq!(resident(slab, 0..slab_size).unwrap(), pages);
+  }
+
+  #[test]
+  fn odd_classes_are_padded_to_min_align() {
+    let mut bump = Bump::new(CHUNK_SIZE);
+    let arena = unsafe { Arena::new(ArenaId(5), CHUNK_SIZE).expect("arena") };
+    // Not a real class: 40 bytes would leave every other slot 8-byte aligned.
+    let class = SizeClass(40, ScIdx(NSTACK));
+    let size = 3 * page_size();
+    let mut slab_ptr = Slab::new(&mut bump, class, size, arena).expect("create slab");
+    let slab = unsafe { slab_ptr.as_mut() };
+
+    let align = slab.guaranteed_align();
+    assert_eq!(align, min_align());
+    let slots: Vec<_> = (0..size / align_up(40, align).unwrap())
+      .map(|_| slab.allocate().expect("alloc"))
+      .collect();
+    assert!(slab.allocate().is_err());
+    for p in &slots {
+      assert!(
+        (p.as_ptr() as usize).is_multiple_of(align),
+        "{:p}",
+        p.as_ptr()
+      );
+    }
+    slots
+      .into_iter()
+      .for_each(|p| slab.deallocate(p).expect("dealloc"));
+  }
+
+  #[test]
+  fn guaranteed_align_follows_the_class() {
+    let mut bump = Bump::new(CHUNK_SIZE);
+    let arena = unsafe { Arena::new(ArenaId(5), CHUNK_SIZE).expect("arena") };
+    for size in [48, 64, 80, 1280] {
+      let class_idx = class_for(size).unwrap();
+      let SlabPages(slab_size) = pages_for(class_idx);
+      let slab = Slab::new(&mut bump, class_at(class_idx), slab_size, arena).expect("slab");
+      let expected = 1 << class_at(class_idx).0.trailing_zeros();
+      assert_eq!(unsafe { slab.as_ref() }.guaranteed_align(), expected);
+    }
+  }
+
+  #[test]
+  fn uneven_slab_size_is_rejected() {
+    let mut bump = Bump::new(CHUNK_SIZE);
+    let class_idx = class_for(48).unwrap();
+    let class = class_at(class_idx);
+    let SlabPages(slab_size) = pages_for(class_idx);
+    let arena = unsafe { Arena::new(ArenaId(5), CHUNK_SIZE).expect("arena") };
+
+    for size in [slab_size + QUANTUM, class.0 - 1, 0] {
+      let result = Slab::new(&mut bump, class, size, arena);
+      assert!(
+        matches!(result, Err(SlabError::InvalidSize { .. })),
+        "size {}",
+        size
+      );
+    }
+  }
+
+  #[test]
+  fn drop_settles_live_count() {
+    // No other test in this crate touches this class.
+    let class_idx = ScIdx(30);
+    let before = crate::stats::live(class_idx);
+
+    let mut bump = Bump::new(CHUNK_SIZE);
+    let class = class_at(class_idx);
+    let SlabPages(slab_size) = pages_for(class_idx);
+    let arena = unsafe { Arena::new(ArenaId(5), CHUNK_SIZE).expect("arena") };
+    let mut slab_ptr = Slab::new(&mut bump, class, slab_size, arena).expect("create slab");
+    let slab = unsafe { slab_ptr.as_mut() };
+
+    (0..3).for_each(|_| _ = slab.allocate().expect("alloc"));
+    assert_eq!(crate::stats::live(class_idx), before + 3);
+
+    unsafe { core::ptr::drop_in_place(slab_ptr.as_ptr()) };
+    assert_eq!(crate::stats::live(class_idx), before);
+  }
+
+  fn drain_and_refill(class_idx: ScIdx) {
+    let mut bump = Bump::new(CHUNK_SIZE);
+    let class = class_at(class_idx);
+    let SlabPages(slab_size) = pages_for(class_idx);
+    let arena = unsafe { Arena::new(ArenaId(7), CHUNK_SIZE).expect("arena") };
+    let mut slab_ptr = Slab::new(&mut bump, class, slab_size, arena).expect("create slab");
+    let slab = unsafe { slab_ptr.as_mut() };
+
+    let mut slots: Vec<_> = (0..slab_size / class.0)
+      .map(|_| slab.allocate().expect("alloc"))
+      .collect();
+    assert!(matches!(slab.allocate(), Err(SlabError::OutOfMemory)));
+    slots.sort_unstable();
+    slots.dedup();
+    assert_eq!(slots.len(), slab_size / class.0);
+
+    for p in slots.iter().rev() {
+      assert!(!slab.is_empty());
+      slab.deallocate(*p).expect("dealloc");
+    }
+    assert!(slab.is_empty());
+    assert!(slots.contains(&slab.allocate().expect("alloc after drain")));
+  }
+
+  #[test]
+  fn stack_slots_drain_and_refill() {
+    drain_and_refill(ScIdx(0));
+    drain_and_refill(ScIdx(NSTACK - 1));
+  }
+
+  #[test]
+  fn bitmap_slots_drain_and_refill() {
+    drain_and_refill(ScIdx(NSTACK));
+  }
 }
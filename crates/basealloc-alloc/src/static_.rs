@@ -1,6 +1,8 @@
 use core::{
+  cell::Cell,
   ptr::NonNull,
   sync::atomic::{
+    AtomicBool,
     AtomicPtr,
     AtomicUsize,
     Ordering,
@@ -15,6 +17,13 @@ use basealloc_sync::{
   lazy::LazyLock,
   local::ThreadLocal,
 };
+use basealloc_sys::{
+  hook::{
+    Fault,
+    report,
+  },
+  prim::current_cpu,
+};
 use getset::Getters;
 
 use crate::{
@@ -24,19 +33,163 @@ use crate::{
   arena::{
     Arena,
     ArenaId,
-    ArenaResult,
   },
   lookup::ArenaMap,
 };
 
+// Free slots weighed by `pick_free` before settling on one.
+const ARENA_SAMPLES: usize = 4;
+
 // Storage
 static BM_STORE: [BitmapWord; ARENA_BMS] = [const { BitmapWord::new(0) }; ARENA_BMS];
 static BM_LAST: AtomicUsize = AtomicUsize::new(0);
 static STATIC: LazyLock<Static> = LazyLock::new(|| Static::new(&BM_STORE));
 pub static ARENA_MAP: ArenaMap = ArenaMap::new(CHUNK_SIZE);
 
-static THREAD_ARENA: ThreadLocal<AtomicPtr<Arena>> =
-  ThreadLocal::new(|| AtomicPtr::new(acquire_arena().unwrap())); // TODO: add new container to impl drop
+static THREAD_ARENA: ThreadLocal<ThreadArena> = ThreadLocal::new(ThreadArena::select);
+// Numbers threads in the order they first allocate, for selectors.
+static THREAD_SEQ: AtomicUsize = AtomicUsize::new(0);
+
+pub static DEFAULT_SELECTOR: &dyn ArenaSelector = &RoundRobinSelector;
+// Holds a thin pointer to a `&'static dyn ArenaSelector` so it fits in one atomic.
+static SELECTOR: AtomicPtr<&'static dyn ArenaSelector> =
+  AtomicPtr::new(&DEFAULT_SELECTOR as *const &'static dyn ArenaSelector as *mut _);
+
+/// Bytes of static storage behind the arena table and map.
+pub(crate) const FOOTPRINT: usize = size_of::<[BitmapWord; ARENA_BMS]>()
+  + size_of::<AtomicUsize>()
+  + size_of::<LazyLock<Static>>()
+  + size_of::<ArenaMap>()
+  + size_of::<ThreadLocal<ThreadArena>>()
+  + size_of::<AtomicUsize>()
+  + size_of::<AtomicPtr<&'static dyn ArenaSelector>>();
+
+struct ThreadArena {
+  arena: AtomicPtr<Arena>,
+  // Only claimed arenas go back to the pool on exit; shared picks stay with others.
+  claimed: AtomicBool,
+}
+
+impl ThreadArena {
+  fn select() -> Self {
+    let thread = THREAD_SEQ.fetch_add(1, Ordering::Relaxed);
+    let stats = ArenaStats {
+      claimed: Cell::new(None),
+    };
+    let pick = current_arena_selector().select(thread, &stats);
+    let claimed = stats.claimed.get();
+    if let Some(stray) = claimed.filter(|id| Some(*id) != pick) {
+      unclaim(stray.0);
+    }
+
+    let arena = pick
+      .and_then(arena_at)
+      .map_or(core::ptr::null_mut(), |arena| {
+        arena as *const Arena as *mut _
+      });
+    Self {
+      arena: AtomicPtr::new(arena),
+      claimed: AtomicBool::new(claimed.is_some_and(|id| Some(id) == pick)),
+    }
+  }
+
+  /// Moves this thread onto `arena`, as a shared pick, handing back any claim.
+  fn rebind(&self, arena: &'static Arena) {
+    let old = self
+      .arena
+      .swap(arena as *const Arena as *mut _, Ordering::AcqRel);
+    if let Some(old) = NonNull::new(old)
+      && self.claimed.swap(false, Ordering::AcqRel)
+    {
+      release_arena(unsafe { old.as_ref() });
+    }
+  }
+}
+
+impl Drop for ThreadArena {
+  fn drop(&mut self) {
+    let arena_ptr = self.arena.load(Ordering::Acquire);
+    if let Some(arena) = NonNull::new(arena_ptr)
+      && self.claimed.load(Ordering::Acquire)
+    {
+      release_arena(unsafe { arena.as_ref() });
+    }
+  }
+}
+
+/// What a selector can weigh when picking an arena, and how it claims one.
+pub struct ArenaStats {
+  claimed: Cell<Option<ArenaId>>,
+}
+
+impl ArenaStats {
+  /// Allocations arena `id` has served; `0` for slots not built yet.
+  pub fn load(&self, id: ArenaId) -> usize {
+    load_of(id.0)
+  }
+
+  /// Whether some thread holds arena `id` to itself.
+  pub fn is_claimed(&self, id: ArenaId) -> bool {
+    matches!(STATIC.bitmap().get(id.0), Ok(true))
+  }
+
+  /// Claims the least-loaded of the next few free arenas, after the last one claimed.
+  ///
+  /// Returning the claimed id keeps that arena to this thread until it exits; at most
+  /// one claim per selection holds.
+  pub fn claim_free(&self) -> Option<ArenaId> {
+    let id = ArenaId(claim_free()?);
+    if let Some(previous) = self.claimed.replace(Some(id)) {
+      unclaim(previous.0);
+    }
+    Some(id)
+  }
+}
+
+/// Picks the arena a thread allocates from, once, before its first allocation.
+pub trait ArenaSelector: Sync {
+  /// The arena for the `thread`th thread to allocate, or `None` for the fallback arena.
+  ///
+  /// Ids from `MAX_ARENAS` up count as `None`. Picks not claimed through `stats` are
+  /// shared, so any number of threads may land on one arena.
+  fn select(&self, thread: usize, stats: &ArenaStats) -> Option<ArenaId>;
+}
+
+/// Claims the least-loaded of the next few free arenas, so every thread gets an arena
+/// to itself while there are enough to go around. The default.
+pub struct RoundRobinSelector;
+
+impl ArenaSelector for RoundRobinSelector {
+  fn select(&self, _thread: usize, stats: &ArenaStats) -> Option<ArenaId> {
+    stats.claim_free()
+  }
+}
+
+/// Shares one arena among the threads on each CPU, by where a thread first allocates.
+///
+/// Threads keep their arena if they migrate later. Where the CPU can't be queried,
+/// threads are spread by their sequence number instead.
+pub struct PerCpuSelector;
+
+impl ArenaSelector for PerCpuSelector {
+  fn select(&self, thread: usize, _stats: &ArenaStats) -> Option<ArenaId> {
+    Some(ArenaId(current_cpu().unwrap_or(thread) % MAX_ARENAS))
+  }
+}
+
+/// Replaces the selector threads consult before their first allocation.
+///
+/// Threads that already have an arena keep it. Pass `&DEFAULT_SELECTOR` to restore
+/// the default.
+pub fn set_arena_selector(selector: &'static &'static dyn ArenaSelector) {
+  let raw = selector as *const &'static dyn ArenaSelector as *mut _;
+  SELECTOR.store(raw, Ordering::Release);
+}
+
+pub fn current_arena_selector() -> &'static dyn ArenaSelector {
+  // SAFETY: `SELECTOR` only ever holds pointers derived from `&'static` references.
+  unsafe { *SELECTOR.load(Ordering::Acquire) }
+}
 
 #[derive(Getters)]
 struct Static {
@@ -48,7 +201,7 @@ struct Static {
 
 impl Static {
   pub fn new(store: &'static [BitmapWord]) -> Self {
-    let bitmap = Bitmap::zero(store, ARENA_BMS).unwrap();
+    let bitmap = Bitmap::zero(store, MAX_ARENAS).unwrap();
     let arenas: [AtomicPtr<Arena>; MAX_ARENAS] =
       core::array::from_fn(|_| AtomicPtr::new(core::ptr::null_mut()));
 
@@ -56,52 +209,307 @@ impl Static {
   }
 }
 
+/// Sets up the arena table and the per-thread arena key.
+///
+/// Returns `false` if thread-local storage is unavailable.
+pub fn init_statics() -> bool {
+  LazyLock::force(&STATIC);
+  THREAD_ARENA.register()
+}
+
 pub fn lookup_arena(addr: usize) -> Option<ArenaId> {
   ARENA_MAP.lookup(addr)
 }
 
-pub fn get_arena(arena_id: ArenaId) -> Option<&'static mut Arena> {
+pub fn get_arena(arena_id: ArenaId) -> Option<&'static Arena> {
   let static_ = &*STATIC;
-  let arena_ptr = static_.arenas()[arena_id.0].load(Ordering::Acquire);
+  let arena_ptr = static_.arenas().get(arena_id.0)?.load(Ordering::Acquire);
   if arena_ptr.is_null() {
     None
   } else {
-    Some(unsafe { &mut *arena_ptr })
+    Some(unsafe { &*arena_ptr })
   }
 }
 
-fn create_arena(at: ArenaId) -> ArenaResult<&'static mut Arena> {
+/// Calls `f` on every arena created so far, in index order.
+///
+/// Arenas are never freed once created (released ones return to the pool), so every
+/// pointer seen here stays valid; one created concurrently may be missed.
+pub fn for_each_arena(mut f: impl FnMut(&'static Arena)) {
   let static_ = &*STATIC;
-  let mut arena = unsafe { Arena::new(at, CHUNK_SIZE)? };
-  static_.arenas()[at.0].store(arena.as_ptr(), Ordering::Release);
+  for slot in static_.arenas().iter() {
+    if let Some(arena) = NonNull::new(slot.load(Ordering::Acquire)) {
+      f(unsafe { arena.as_ref() });
+    }
+  }
+}
+
+/// Checks the arena table's bitmap and every arena in it; see `Arena::validate`.
+pub fn validate_arenas() -> Result<(), &'static str> {
+  let bitmap = STATIC.bitmap();
+  if bitmap.used() != bitmap.count_set() {
+    return Err("arena bitmap's used count doesn't match its set bits");
+  }
 
-  Ok(unsafe { arena.as_mut() })
+  let mut result = Ok(());
+  for_each_arena(|arena| {
+    if result.is_ok() {
+      result = arena.validate();
+    }
+  });
+  result
 }
 
-fn acquire_arena() -> Option<&'static mut Arena> {
+/// Arena `at`, built on first use; `None` if `at` is out of range or can't be built.
+///
+/// Shared picks can race to build the same slot; the loser drops its copy.
+fn arena_at(at: ArenaId) -> Option<&'static Arena> {
+  let slot = STATIC.arenas().get(at.0)?;
+  if let Some(arena) = get_arena(at) {
+    return Some(arena);
+  }
+
+  let arena = unsafe { Arena::new(at, CHUNK_SIZE).ok()? };
+  let null = core::ptr::null_mut();
+  match slot.compare_exchange(null, arena.as_ptr(), Ordering::AcqRel, Ordering::Acquire) {
+    Ok(_) => Some(unsafe { arena.as_ref() }),
+    Err(winner) => {
+      unsafe { core::ptr::drop_in_place(arena.as_ptr()) };
+      Some(unsafe { &*winner })
+    }
+  }
+}
+
+fn load_of(idx: usize) -> usize {
+  get_arena(ArenaId(idx)).map_or(0, Arena::load)
+}
+
+/// The least-loaded of the next few free slots from `start`; unbuilt slots count as idle.
+fn pick_free(bitmap: &Bitmap, start: usize) -> Option<usize> {
+  let first = bitmap.find_fc(Some(start))?;
+  let (mut best, mut best_load) = (first, load_of(first));
+  let mut idx = first;
+  for _ in 1..ARENA_SAMPLES {
+    if best_load == 0 {
+      break;
+    }
+    idx = match bitmap.find_fc(Some((idx + 1) % MAX_ARENAS)) {
+      Some(next) if next != first => next,
+      _ => break,
+    };
+    let load = load_of(idx);
+    if load < best_load {
+      (best, best_load) = (idx, load);
+    }
+  }
+  Some(best)
+}
+
+/// Claims a free slot in the arena table, starting after the last one claimed.
+fn claim_free() -> Option<usize> {
   let static_ = &*STATIC;
   let last = BM_LAST.load(Ordering::Acquire);
-  let idx = static_.bitmap().find_fc(Some(last))?;
-  static_.bitmap().set(idx).ok()?;
+  let idx = static_
+    .bitmap()
+    .try_acquire(|bitmap| pick_free(bitmap, last))?;
 
-  BM_LAST.store((idx + 1) % ARENA_BMS, Ordering::Release);
+  BM_LAST.store((idx + 1) % MAX_ARENAS, Ordering::Release);
+  Some(idx)
+}
 
-  let arena_ptr = static_.arenas()[idx].load(Ordering::Acquire);
-  if !arena_ptr.is_null() {
-    let arena = unsafe { &mut *arena_ptr };
-    return Some(arena);
+#[cfg(test)]
+fn acquire_arena() -> Option<&'static Arena> {
+  claim_free().and_then(|idx| arena_at(ArenaId(idx)))
+}
+
+/// Returns `arena` to the pool so another thread can acquire it.
+///
+/// Arenas outside the pool (like the fallback arena) are reported and ignored.
+pub fn release_arena(arena: &Arena) {
+  let ArenaId(idx) = arena.index();
+  if idx >= MAX_ARENAS {
+    report(Fault::InvalidArena(idx));
+    return;
   }
 
-  create_arena(ArenaId(idx)).ok()
+  unclaim(idx);
 }
 
+fn unclaim(idx: usize) {
+  let static_ = &*STATIC;
+  if matches!(static_.bitmap().get(idx), Ok(true)) {
+    let _ = static_.bitmap().clear(idx);
+  }
+}
+
+/// Binds the calling thread to arena `id`, building it if needed, in place of the one
+/// its selector picked; any claimed arena goes back to the pool.
+///
+/// Pinning is shared: other threads may use `id` too. Returns `false` if `id` is out
+/// of range or can't be built, or thread-local storage is unavailable.
+pub fn pin_this_arena(id: ArenaId) -> bool {
+  let Some(arena) = arena_at(id) else {
+    return false;
+  };
+  THREAD_ARENA.with(|ta| ta.rebind(arena)).is_some()
+}
+
+/// Returns this thread's arena, or `None` when thread-local storage is
+/// unavailable and the caller should use a shared arena instead.
 pub fn acquire_this_arena() -> Option<NonNull<Arena>> {
-  THREAD_ARENA.with(|ta| {
-    let ptr = ta.load(Ordering::Acquire);
-    if ptr.is_null() {
-      return None;
+  THREAD_ARENA
+    .with(|ta| NonNull::new(ta.arena.load(Ordering::Acquire)))
+    .flatten()
+}
+
+#[cfg(test)]
+mod tests {
+  use core::ptr::drop_in_place;
+
+  use super::*;
+  use crate::{
+    classes::ScIdx,
+    testing::{
+      saw,
+      watch_faults,
+    },
+  };
+
+  #[test]
+  fn release_out_of_range_is_noop() {
+    watch_faults();
+    let arena = unsafe { Arena::new(ArenaId(usize::MAX), CHUNK_SIZE).expect("arena") };
+
+    release_arena(unsafe { arena.as_ref() });
+
+    assert!(saw(Fault::InvalidArena(usize::MAX)));
+    unsafe { drop_in_place(arena.as_ptr()) };
+  }
+
+  #[test]
+  fn acquire_never_exceeds_max_arenas() {
+    static STORE: [BitmapWord; ARENA_BMS] = [const { BitmapWord::new(0) }; ARENA_BMS];
+    let static_ = Static::new(&STORE);
+    assert_eq!(static_.bitmap().bits(), MAX_ARENAS);
+
+    let claim = || static_.bitmap().try_acquire(|bitmap| pick_free(bitmap, 0));
+    let ids: Vec<usize> = core::iter::from_fn(claim).collect();
+    assert_eq!(ids.len(), MAX_ARENAS);
+    assert!(ids.iter().all(|idx| *idx < MAX_ARENAS));
+  }
+
+  #[test]
+  fn footprint_matches_statics() {
+    let statics = size_of_val(&BM_STORE)
+      + size_of_val(&BM_LAST)
+      + size_of_val(&STATIC)
+      + size_of_val(&ARENA_MAP)
+      + size_of_val(&THREAD_ARENA)
+      + size_of_val(&THREAD_SEQ)
+      + size_of_val(&SELECTOR);
+    assert_eq!(FOOTPRINT, statics);
+    // The arena pointers dominate, and live inline in `STATIC`.
+    assert!(size_of_val(&STATIC) >= MAX_ARENAS * size_of::<usize>());
+  }
+
+  #[test]
+  fn cursor_stays_within_max_arenas() {
+    // Walks the cursor around the whole table a few times.
+    for _ in 0..3 * MAX_ARENAS {
+      let arena = acquire_arena().expect("acquire");
+      assert!(arena.index().0 < MAX_ARENAS);
+      assert!(BM_LAST.load(Ordering::Acquire) < MAX_ARENAS);
+      release_arena(arena);
+    }
+  }
+
+  #[test]
+  fn pick_free_prefers_least_loaded() {
+    static STORE: [BitmapWord; ARENA_BMS] = [const { BitmapWord::new(0) }; ARENA_BMS];
+    let sc = ScIdx(3);
+    // Claimed in the real table too, so no other thread allocates from them meanwhile.
+    let claim = || {
+      STATIC
+        .bitmap()
+        .try_acquire(|bitmap| bitmap.find_fc(Some(MAX_ARENAS / 2)))
+    };
+    let mut ids: Vec<usize> = core::iter::from_fn(claim).take(3).collect();
+    ids.sort_unstable();
+    assert_eq!(ids.len(), 3);
+    let arenas: Vec<&Arena> = ids
+      .iter()
+      .map(|id| arena_at(ArenaId(*id)).unwrap())
+      .collect();
+
+    // Only the last free slot stays below the others' load.
+    let idle = arenas[2].load();
+    let mut slots = Vec::new();
+    for arena in &arenas[..2] {
+      while arena.load() <= idle {
+        slots.push((*arena, arena.allocate(sc).expect("alloc")));
+      }
+    }
+
+    let bitmap = Bitmap::zero(&STORE, MAX_ARENAS).unwrap();
+    (0..MAX_ARENAS).for_each(|idx| bitmap.set(idx).unwrap());
+    ids.iter().for_each(|id| bitmap.clear(*id).unwrap());
+    assert_eq!(pick_free(&bitmap, ids[0]), Some(ids[2]));
+
+    for (arena, slot) in slots {
+      arena.deallocate(slot).expect("dealloc");
     }
+    ids.into_iter().for_each(unclaim);
+    bitmap.clear_all();
+  }
+
+  #[test]
+  fn per_cpu_picks_stay_in_range() {
+    let stats = ArenaStats {
+      claimed: Cell::new(None),
+    };
+    for thread in [0, 1, MAX_ARENAS + 5, usize::MAX] {
+      let ArenaId(idx) = PerCpuSelector.select(thread, &stats).unwrap();
+      assert!(idx < MAX_ARENAS);
+    }
+    assert_eq!(stats.claimed.get(), None);
+  }
+
+  #[test]
+  fn pick_free_wraps_to_free_slots() {
+    static STORE: [BitmapWord; ARENA_BMS] = [const { BitmapWord::new(0) }; ARENA_BMS];
+    let bitmap = Bitmap::zero(&STORE, MAX_ARENAS).unwrap();
+    (0..MAX_ARENAS - 2).for_each(|idx| bitmap.set(idx).unwrap());
 
-    Some(unsafe { NonNull::new_unchecked(ptr) })
-  })
+    // Only the last two slots are free; the search wraps back to them.
+    let pick = pick_free(&bitmap, 0).unwrap();
+    assert!(pick >= MAX_ARENAS - 2);
+    assert!(bitmap.try_set(pick).unwrap());
+    assert_eq!(
+      pick_free(&bitmap, pick),
+      (MAX_ARENAS - 2..MAX_ARENAS).find(|i| *i != pick)
+    );
+    bitmap.clear_all();
+  }
+
+  #[test]
+  fn concurrent_acquire_is_exclusive() {
+    let mut ids: Vec<usize> = std::thread::scope(|scope| {
+      let handles: Vec<_> = (0..8)
+        .map(|_| scope.spawn(|| acquire_arena().map(|arena| arena.index().0)))
+        .collect();
+      handles
+        .into_iter()
+        .filter_map(|h| h.join().unwrap())
+        .collect()
+    });
+
+    for id in ids.iter() {
+      release_arena(get_arena(ArenaId(*id)).unwrap());
+    }
+
+    let acquired = ids.len();
+    ids.sort_unstable();
+    ids.dedup();
+    assert_eq!(ids.len(), acquired);
+  }
 }
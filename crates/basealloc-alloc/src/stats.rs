@@ -0,0 +1,61 @@
+use core::sync::atomic::{
+  AtomicUsize,
+  Ordering,
+};
+
+use crate::classes::{
+  NSCLASSES,
+  ScIdx,
+};
+
+// Slots handed out by slabs, per class. Slots parked in a tcache count as live.
+static LIVE: [AtomicUsize; NSCLASSES] = [const { AtomicUsize::new(0) }; NSCLASSES];
+// What `live` reports as zero; set by `reset` instead of touching `LIVE`, which keeps
+// counting every slot still out.
+static BASE: [AtomicUsize; NSCLASSES] = [const { AtomicUsize::new(0) }; NSCLASSES];
+
+// Zeroed allocations that had to be cleared by hand.
+static ZERO_FILLS: AtomicUsize = AtomicUsize::new(0);
+
+pub(crate) const FOOTPRINT: usize =
+  2 * size_of::<[AtomicUsize; NSCLASSES]>() + size_of::<AtomicUsize>();
+
+#[inline]
+pub(crate) fn add_live(sc: ScIdx, count: usize) {
+  LIVE[sc.0].fetch_add(count, Ordering::Relaxed);
+}
+
+#[inline]
+pub(crate) fn sub_live(sc: ScIdx, count: usize) {
+  LIVE[sc.0].fetch_sub(count, Ordering::Relaxed);
+}
+
+#[inline]
+pub(crate) fn add_zero_fill() {
+  ZERO_FILLS.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Zeroed small allocations that needed a memset since the last `reset`; the rest came
+/// from pages the kernel had already zeroed.
+pub fn zero_fills() -> usize {
+  ZERO_FILLS.load(Ordering::Relaxed)
+}
+
+/// Slots of class `sc` handed out minus those freed since the last `reset`, across all
+/// arenas; negative while more slots from before it were freed than new ones handed out.
+pub fn live(sc: ScIdx) -> isize {
+  let live = LIVE[sc.0].load(Ordering::Relaxed);
+  live.wrapping_sub(BASE[sc.0].load(Ordering::Relaxed)) as isize
+}
+
+/// Makes every counter read zero without touching live allocations.
+///
+/// Racy under concurrency: counts taken while other threads allocate may be off.
+/// Slots handed out before the reset no longer show up, and freeing them pulls `live`
+/// below zero.
+pub fn reset() {
+  for (base, live) in BASE.iter().zip(LIVE.iter()) {
+    base.store(live.load(Ordering::Relaxed), Ordering::Relaxed);
+  }
+  ZERO_FILLS.store(0, Ordering::Relaxed);
+}
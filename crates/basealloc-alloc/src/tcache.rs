@@ -7,13 +7,14 @@ use basealloc_extent::{
   Extent,
   ExtentError,
 };
-use basealloc_ring::Ring;
+use basealloc_ring::UnsyncRing;
 use basealloc_sync::local::ThreadLocal;
 use basealloc_sys::{
   misc::UnsafeStore,
   prim::{
     PrimError,
     page_align,
+    page_size,
   },
   system::SysOption,
 };
@@ -45,11 +46,11 @@ pub type TCacheResult<T> = Result<T, TCacheError>;
 
 struct CacheBin {
   store: UnsafeStore<*mut u8>,
-  ring: Ring,
+  ring: UnsyncRing,
 }
 
 pub struct TCache {
-  _backing: Extent,
+  backing: Extent,
   caches: [CacheBin; NSCLASSES],
 }
 
@@ -60,7 +61,7 @@ impl TCache {
     let caches = Self::new_caches(&extent);
 
     Ok(Self {
-      _backing: extent,
+      backing: extent,
       caches,
     })
   }
@@ -68,11 +69,13 @@ impl TCache {
   fn new_caches(extent: &Extent) -> [CacheBin; NSCLASSES] {
     let exstart = extent.as_ref().as_ptr() as *mut u8;
 
+    let mut offset = 0;
     core::array::from_fn(|i| {
       let class_idx = ScIdx(i);
-      let range = Self::get_range(0, class_idx);
+      let range = Self::get_range(offset, class_idx);
+      offset = range.end;
       let store = Self::construct_store(exstart, range);
-      let ring = Ring::new();
+      let ring = UnsyncRing::new();
 
       CacheBin { store, ring }
     })
@@ -103,12 +106,40 @@ impl TCache {
     &mut self.caches[class_idx.0]
   }
 
-  fn refill_cache(&mut self, backing: &mut Arena, sc: ScIdx) -> TCacheResult<()> {
+  /// The extent holding every cache bin's slot storage.
+  pub fn backing(&self) -> &Extent {
+    &self.backing
+  }
+
+  /// Faults in every page of the bin storage so the first push per class doesn't.
+  pub fn prewarm(&mut self) {
+    let region = self.backing.as_mut();
+    for offset in (0..region.len()).step_by(page_size()) {
+      // Rewrite what's there; live bins may already hold pointers.
+      let byte = unsafe { region.as_mut_ptr().add(offset) };
+      unsafe { byte.write_volatile(byte.read_volatile()) };
+    }
+  }
+
+  /// Number of slots currently parked for `sc`.
+  pub fn cached(&self, sc: ScIdx) -> usize {
+    self.caches[sc.0].ring.len()
+  }
+
+  /// Pops a cached slot for `sc` without refilling from the arena.
+  pub fn take(&mut self, sc: ScIdx) -> Option<NonNull<u8>> {
+    let cache = self.cache_for(sc);
+    let buf = cache.store.as_mut_slice();
+    let ptr_ref = cache.ring.pop(buf)?;
+    NonNull::new(*ptr_ref)
+  }
+
+  fn refill_cache(&mut self, backing: &Arena, sc: ScIdx) -> TCacheResult<()> {
     let CacheSlots(cache_size) = cache_for(sc);
     let cache = self.cache_for(sc);
     let buf = cache.store.as_mut_slice();
 
-    let refill_count = cache_size.min(buf.len() - cache.ring.len());
+    let refill_count = cache_size.min(cache.ring.remaining(buf));
 
     for _ in 0..refill_count {
       let ptr = backing.allocate(sc).map_err(TCacheError::ArenaError)?;
@@ -120,7 +151,7 @@ impl TCache {
     Ok(())
   }
 
-  pub fn allocate(&mut self, backing: &mut Arena, sc: ScIdx) -> TCacheResult<NonNull<u8>> {
+  pub fn allocate(&mut self, backing: &Arena, sc: ScIdx) -> TCacheResult<NonNull<u8>> {
     let cache = self.cache_for(sc);
     let buf = cache.store.as_mut_slice();
 
@@ -136,12 +167,7 @@ impl TCache {
     Ok(unsafe { NonNull::new_unchecked(*ptr_ref) })
   }
 
-  pub fn deallocate(
-    &mut self,
-    backing: &mut Arena,
-    ptr: NonNull<u8>,
-    sc: ScIdx,
-  ) -> TCacheResult<()> {
+  pub fn deallocate(&mut self, backing: &Arena, ptr: NonNull<u8>, sc: ScIdx) -> TCacheResult<()> {
     let should_flush = {
       let cache = self.cache_for(sc);
       let buf = cache.store.as_mut_slice();
@@ -155,15 +181,13 @@ impl TCache {
     let cache = self.cache_for(sc);
     let buf = cache.store.as_mut_slice();
     if cache.ring.push(buf, ptr.as_ptr()).is_err() {
-      backing
-        .deallocate(ptr)
-        .map_err(TCacheError::ArenaError)?;
+      backing.deallocate(ptr).map_err(TCacheError::ArenaError)?;
     }
 
     Ok(())
   }
 
-  fn flush_cache(&mut self, backing: &mut Arena, sc: ScIdx) -> TCacheResult<()> {
+  fn flush_cache(&mut self, backing: &Arena, sc: ScIdx) -> TCacheResult<()> {
     let cache = self.cache_for(sc);
     let buf = cache.store.as_mut_slice();
 
@@ -172,9 +196,7 @@ impl TCache {
     for _ in 0..flush_count {
       if let Some(ptr_ref) = cache.ring.pop(buf) {
         let ptr = unsafe { NonNull::new_unchecked(*ptr_ref) };
-        backing
-          .deallocate(ptr)
-          .map_err(TCacheError::ArenaError)?;
+        backing.deallocate(ptr).map_err(TCacheError::ArenaError)?;
       } else {
         break;
       }
@@ -183,8 +205,8 @@ impl TCache {
     Ok(())
   }
 
-  //TODO: unused
-  pub fn flush_all(&mut self, backing: &mut Arena) -> TCacheResult<()> {
+  /// Returns every cached slot to `backing`; the cache stays usable.
+  pub fn flush_all(&mut self, backing: &Arena) -> TCacheResult<()> {
     for i in 0..NSCLASSES {
       let sc = ScIdx(i);
       let cache = self.cache_for(sc);
@@ -192,9 +214,7 @@ impl TCache {
 
       while let Some(ptr_ref) = cache.ring.pop(buf) {
         let ptr = unsafe { NonNull::new_unchecked(*ptr_ref) };
-        backing
-          .deallocate(ptr)
-          .map_err(TCacheError::ArenaError)?;
+        backing.deallocate(ptr).map_err(TCacheError::ArenaError)?;
       }
     }
     Ok(())
@@ -203,6 +223,13 @@ impl TCache {
 
 static TCACHE: ThreadLocal<TCache> = ThreadLocal::new(|| TCache::new(total_cache_size()).unwrap());
 
+pub(crate) const FOOTPRINT: usize = size_of::<ThreadLocal<TCache>>();
+
+/// Creates the tcache key up front; `false` if thread-local storage is unavailable.
+pub fn register_tcache() -> bool {
+  TCACHE.register()
+}
+
 pub fn acquire_tcache() -> Option<NonNull<TCache>> {
-  Some(TCACHE.with(|tc| NonNull::from(tc)))
+  TCACHE.with(|tc| NonNull::from(tc))
 }
@@ -15,6 +15,7 @@ pub mod tests;
 pub enum BitmapError {
   InsufficientSize { have: usize, need: usize },
   OutOfBounds { index: usize, size: usize },
+  Misaligned { addr: usize, align: usize },
 }
 
 pub type BitmapWord = AtomicUsize;
@@ -145,6 +146,70 @@ impl Bitmap {
     Ok(bitmap)
   }
 
+  /// Carves the words out of a raw byte buffer and clears `bits` of them, like `zero`.
+  ///
+  /// `bytes` must be aligned for `BitmapWord` and hold `bytes(bits)` bytes; bytes past
+  /// the last whole word are left alone. Like every store, it has to outlive the bitmap.
+  pub fn from_bytes(bytes: &mut [u8], bits: usize) -> Result<Self, BitmapError> {
+    let addr = bytes.as_ptr() as usize;
+    let align = core::mem::align_of::<BitmapWord>();
+    if !addr.is_multiple_of(align) {
+      return Err(BitmapError::Misaligned { addr, align });
+    }
+
+    let words = bytes.len() / core::mem::size_of::<BitmapWord>();
+    // SAFETY: aligned, in bounds, and `&mut` rules out other views of the bytes.
+    let store = unsafe { core::slice::from_raw_parts(addr as *const BitmapWord, words) };
+    Self::zero(store, bits)
+  }
+
+  /// Wraps `store` as-is, recounting the bits already set.
+  pub fn restore(store: &[BitmapWord], bits: usize) -> Result<Self, BitmapError> {
+    let bitmap = unsafe { Self::from_parts_unchecked(store, bits, 0)? };
+    bitmap.used.store(bitmap.count_set(), Ordering::Relaxed);
+    Ok(bitmap)
+  }
+
+  /// Wraps `store` as-is, trusting `used` instead of recounting.
+  ///
+  /// # Safety
+  ///
+  /// `used` must equal the number of bits set below `bits`; `is_full`, `is_clear` and
+  /// the allocation paths rely on it. Debug builds check it.
+  pub unsafe fn from_parts(
+    store: &[BitmapWord],
+    bits: usize,
+    used: usize,
+  ) -> Result<Self, BitmapError> {
+    let bitmap = unsafe { Self::from_parts_unchecked(store, bits, used)? };
+    debug_assert_eq!(
+      bitmap.count_set(),
+      used,
+      "used count doesn't match the store"
+    );
+    Ok(bitmap)
+  }
+
+  unsafe fn from_parts_unchecked(
+    store: &[BitmapWord],
+    bits: usize,
+    used: usize,
+  ) -> Result<Self, BitmapError> {
+    let available = store.len() * USIZE_BITS;
+    if bits > available {
+      return Err(BitmapError::InsufficientSize {
+        have: available,
+        need: bits,
+      });
+    }
+
+    Ok(Self {
+      store: BmStore::from(store),
+      bits,
+      used: AtomicUsize::new(used),
+    })
+  }
+
   pub fn check(&self, fields: usize) -> Result<(), BitmapError> {
     let total_bits = self.store.as_slice().len() * USIZE_BITS;
     if fields > total_bits {
@@ -253,6 +318,74 @@ impl Bitmap {
     self.find_bit(start, |v| v ^ usize::MAX)
   }
 
+  /// First index of `n` adjacent clear bits, lowest first; runs don't wrap around.
+  ///
+  /// Hops past each set bit that interrupts a candidate run. A zero-length run finds nothing.
+  pub fn find_fc_run(&self, n: usize) -> Option<usize> {
+    let mut start = 0;
+    while n > 0 && start + n <= self.bits {
+      // `find_fc` wraps, so a hit below `start` means nothing is clear past it.
+      let run = self.find_fc(Some(start)).filter(|bit| *bit >= start)?;
+      let end = run.checked_add(n).filter(|end| *end <= self.bits)?;
+      match self
+        .find_fs(Some(run))
+        .filter(|set| (run..end).contains(set))
+      {
+        Some(set) => start = set + 1,
+        None => return Some(run),
+      }
+    }
+    None
+  }
+
+  /// `(start, length)` of the longest stretch of clear bits, lowest first on a tie;
+  /// `(0, 0)` when no bit is clear.
+  ///
+  /// Runs carry across word boundaries; padding past `bits` ends a run.
+  pub fn longest_clear_run(&self) -> (usize, usize) {
+    let store = self.store.as_slice();
+    let words = Self::words(self.bits).min(store.len());
+    let tail = bit_index(self.bits);
+    let mut run = ClearRun::default();
+
+    for (idx, word) in store[..words].iter().enumerate() {
+      let in_tail = idx + 1 == words && tail != 0;
+      let valid = if in_tail {
+        !mask_from(tail)
+      } else {
+        usize::MAX
+      };
+      run.scan(idx * USIZE_BITS, !word.load(Ordering::Relaxed) & valid);
+    }
+    run.longest()
+  }
+
+  /// Atomically sets the clear bit `pick` chooses and returns it; `None` once `pick`
+  /// finds none.
+  ///
+  /// Picks again if another thread claims the chosen bit first.
+  pub fn try_acquire(&self, pick: impl Fn(&Self) -> Option<usize>) -> Option<usize> {
+    loop {
+      let index = pick(self)?;
+      if let Ok(true) = self.try_set(index) {
+        return Some(index);
+      }
+    }
+  }
+
+  /// Atomically sets `index`; `false` if it was already set, e.g. by another thread.
+  pub fn try_set(&self, index: usize) -> Result<bool, BitmapError> {
+    self.position(index)?;
+    let store = self.store.as_slice();
+    let mask = bit_mask(index);
+    let prev = store[word_index(index)].fetch_or(mask, Ordering::AcqRel);
+    if prev & mask != 0 {
+      return Ok(false);
+    }
+    self.used.fetch_add(1, Ordering::Relaxed);
+    Ok(true)
+  }
+
   fn find_bit<F>(&self, start: Option<usize>, transform: F) -> Option<usize>
   where
     F: Fn(usize) -> usize + Copy,
@@ -291,6 +424,8 @@ impl Bitmap {
       return None;
     }
 
+    // A word-aligned start leaves nothing below it in its own word, so the end
+    // mask is empty and the full words before it cover the wrap.
     let wrap_end_mask = (1usize << start_offset).wrapping_sub(1);
     let wrap_to_word = start_word;
     let wrap_start_mask = if wrap_to_word == 0 {
@@ -302,6 +437,38 @@ impl Bitmap {
     self.iter_range(0, wrap_to_word, wrap_start_mask, wrap_end_mask, transform)
   }
 
+  #[inline]
+  pub fn used(&self) -> usize {
+    self.used.load(Ordering::Relaxed)
+  }
+
+  /// Set bits counted from the words themselves, independent of `used`.
+  pub fn count_set(&self) -> usize {
+    let store = self.store.as_slice();
+    let words = Self::words(self.bits).min(store.len());
+    let tail = bit_index(self.bits);
+
+    store[..words]
+      .iter()
+      .enumerate()
+      .map(|(idx, word)| {
+        let value = word.load(Ordering::Relaxed);
+        let in_tail = idx + 1 == words && tail != 0;
+        let mask = if in_tail {
+          !mask_from(tail)
+        } else {
+          usize::MAX
+        };
+        (value & mask).count_ones() as usize
+      })
+      .sum()
+  }
+
+  /// Clear bits within `bits`; padding past the end never counts.
+  pub fn count_clear(&self) -> usize {
+    self.bits - self.count_set()
+  }
+
   #[inline]
   pub fn is_clear(&self) -> bool {
     self.used.load(Ordering::Relaxed) == 0
@@ -317,3 +484,54 @@ impl Bitmap {
     self.used.load(Ordering::Relaxed) < self.bits
   }
 }
+
+// The clear run being walked and the longest one closed so far.
+#[derive(Default)]
+struct ClearRun {
+  start: usize,
+  len: usize,
+  best: (usize, usize),
+}
+
+impl ClearRun {
+  // Walks the word starting at bit `base`, where `clear` has a one for every clear bit.
+  fn scan(&mut self, base: usize, clear: usize) {
+    let mut bit = 0;
+    while bit < USIZE_BITS {
+      let rest = clear >> bit;
+      if rest & 1 == 1 {
+        if self.len == 0 {
+          self.start = base + bit;
+        }
+        let ones = rest.trailing_ones() as usize;
+        self.len += ones;
+        bit += ones;
+      } else {
+        self.close();
+        bit += (rest.trailing_zeros() as usize).min(USIZE_BITS - bit);
+      }
+    }
+  }
+
+  fn close(&mut self) {
+    if self.len > self.best.1 {
+      self.best = (self.start, self.len);
+    }
+    self.len = 0;
+  }
+
+  fn longest(mut self) -> (usize, usize) {
+    self.close();
+    self.best
+  }
+}
+
+#[cfg(feature = "debug-checks")]
+impl Drop for Bitmap {
+  fn drop(&mut self) {
+    let used = self.used();
+    if used != 0 {
+      basealloc_sys::hook::report(basealloc_sys::hook::Fault::LeakedBits(used));
+    }
+  }
+}
@@ -187,3 +187,279 @@ fn test_const_functionality() {
   assert_eq!(bitmap.bits(), 64);
   assert_eq!(bitmap.available(), 64);
 }
+
+#[test]
+fn test_try_acquire_is_exclusive() {
+  const THREADS: usize = 8;
+  const PER_THREAD: usize = 16;
+
+  let storage: [AtomicUsize; 2] = [AtomicUsize::new(0), AtomicUsize::new(0)];
+  let bitmap = Bitmap::zero(&storage, THREADS * PER_THREAD).unwrap();
+  let first_clear = |bitmap: &Bitmap| bitmap.find_fc(None);
+
+  let mut claimed: Vec<usize> = std::thread::scope(|scope| {
+    let handles: Vec<_> = (0..THREADS)
+      .map(|_| {
+        scope.spawn(|| {
+          (0..PER_THREAD)
+            .filter_map(|_| bitmap.try_acquire(first_clear))
+            .collect::<Vec<_>>()
+        })
+      })
+      .collect();
+    handles
+      .into_iter()
+      .flat_map(|h| h.join().unwrap())
+      .collect()
+  });
+
+  claimed.sort_unstable();
+  claimed.dedup();
+  assert_eq!(claimed.len(), THREADS * PER_THREAD);
+  assert!(bitmap.is_full());
+  assert_eq!(bitmap.try_acquire(first_clear), None);
+  bitmap.clear_all();
+}
+
+#[test]
+fn test_try_set_claims_once() {
+  let storage: [BitmapWord; 2] = [const { BitmapWord::new(0) }; 2];
+  let bitmap = Bitmap::zero(&storage, 70).unwrap();
+
+  assert!(bitmap.try_set(65).unwrap());
+  assert!(!bitmap.try_set(65).unwrap());
+  assert_eq!(bitmap.used(), 1);
+  assert!(bitmap.try_set(70).is_err());
+  bitmap.clear(65).unwrap();
+}
+
+#[test]
+fn test_try_set_race_has_one_winner() {
+  const THREADS: usize = 16;
+  let storage: [BitmapWord; 2] = [const { BitmapWord::new(0) }; 2];
+  let bitmap = Bitmap::zero(&storage, 128).unwrap();
+  let start = std::sync::Barrier::new(THREADS);
+
+  for index in [0, 63, 64, 127] {
+    let wins = std::thread::scope(|scope| {
+      let racers: Vec<_> = (0..THREADS)
+        .map(|_| {
+          scope.spawn(|| {
+            start.wait();
+            bitmap.try_set(index).unwrap()
+          })
+        })
+        .collect();
+      racers
+        .into_iter()
+        .map(|r| r.join().unwrap())
+        .filter(|won| *won)
+        .count()
+    });
+    assert_eq!(wins, 1, "index {}", index);
+  }
+  assert_eq!(bitmap.used(), 4);
+  bitmap.clear_all();
+}
+
+#[test]
+fn test_find_wraps_from_word_aligned_start() {
+  let storage: [AtomicUsize; 2] = [AtomicUsize::new(0), AtomicUsize::new(0)];
+  let bitmap = Bitmap::zero(&storage, 128).unwrap();
+  bitmap.set(10).unwrap();
+  assert_eq!(bitmap.find_fs(Some(64)), Some(10));
+
+  bitmap.set(70).unwrap();
+  assert_eq!(bitmap.find_fs(Some(64)), Some(70));
+  assert_eq!(bitmap.find_fs(Some(71)), Some(10));
+
+  (64..128).for_each(|i| _ = bitmap.set(i));
+  (0..64).filter(|i| *i != 10).for_each(|i| _ = bitmap.set(i));
+  bitmap.clear(10).unwrap();
+  assert_eq!(bitmap.find_fc(Some(64)), Some(10));
+  bitmap.clear_all();
+}
+
+#[test]
+fn test_count_set_and_clear() {
+  let storage: [AtomicUsize; 3] = core::array::from_fn(|_| AtomicUsize::new(0));
+  let bitmap = Bitmap::zero(&storage, 150).unwrap();
+  assert_eq!(bitmap.count_clear(), 150);
+
+  for i in [0, 5, 63, 64, 100, 149] {
+    bitmap.set(i).unwrap();
+  }
+  // Padding bits past `bits` must not be counted either way.
+  storage[2].fetch_or(
+    usize::MAX << (150 % 64),
+    core::sync::atomic::Ordering::Relaxed,
+  );
+
+  assert_eq!(bitmap.count_set(), 6);
+  assert_eq!(bitmap.count_set(), bitmap.used());
+  assert_eq!(bitmap.count_set() + bitmap.count_clear(), 150);
+  bitmap.clear_all();
+}
+
+#[test]
+fn test_restore_and_from_parts() {
+  let storage: [AtomicUsize; 2] = [AtomicUsize::new(usize::MAX), AtomicUsize::new(0b101)];
+  let restored = Bitmap::restore(&storage, 70).unwrap();
+  assert_eq!(restored.used(), 66);
+
+  let full = unsafe { Bitmap::from_parts(&storage[..1], 64, 64) }.unwrap();
+  assert!(full.is_full() && !full.is_clear());
+  assert_eq!(full.find_fc(None), None);
+  full.clear_all();
+  restored.clear_all();
+
+  let empty_store: [AtomicUsize; 1] = [AtomicUsize::new(0)];
+  let empty = unsafe { Bitmap::from_parts(&empty_store, 64, 0) }.unwrap();
+  assert!(empty.is_clear() && !empty.is_full());
+}
+
+#[cfg(debug_assertions)]
+#[test]
+#[should_panic(expected = "used count")]
+fn test_from_parts_rejects_wrong_count_in_debug() {
+  let storage: [AtomicUsize; 1] = [AtomicUsize::new(0b11)];
+  let _ = unsafe { Bitmap::from_parts(&storage, 64, 1) };
+}
+
+#[cfg(feature = "debug-checks")]
+mod debug_checks {
+  use std::cell::RefCell;
+
+  use basealloc_sys::hook::{
+    Fault,
+    set_error_hook,
+  };
+
+  use super::*;
+
+  std::thread_local! {
+    // Other tests drop bitmaps with bits set concurrently; only watch this thread.
+    static SEEN: RefCell<Vec<Fault>> = const { RefCell::new(Vec::new()) };
+  }
+
+  fn record(fault: Fault) {
+    SEEN.with(|seen| seen.borrow_mut().push(fault));
+  }
+
+  fn drop_with(set: &[usize]) -> Vec<Fault> {
+    set_error_hook(Some(record));
+    let storage: [AtomicUsize; 1] = [AtomicUsize::new(0)];
+    let bitmap = Bitmap::zero(&storage, 64).unwrap();
+    set.iter().for_each(|i| bitmap.set(*i).unwrap());
+    drop(bitmap);
+    SEEN.with(|seen| seen.take())
+  }
+
+  #[test]
+  fn test_drop_reports_leaked_bits() {
+    assert_eq!(drop_with(&[3, 9, 40]), [Fault::LeakedBits(3)]);
+  }
+
+  #[test]
+  fn test_clean_drop_is_silent() {
+    assert!(drop_with(&[]).is_empty());
+  }
+}
+
+#[test]
+fn test_from_bytes_carves_words() {
+  const WORDS: usize = 3;
+  let mut backing = [usize::MAX; WORDS];
+  let bytes =
+    unsafe { core::slice::from_raw_parts_mut(backing.as_mut_ptr() as *mut u8, Bitmap::bytes(130)) };
+
+  let bitmap = Bitmap::from_bytes(bytes, 130).unwrap();
+  assert_eq!(bitmap.bits(), 130);
+  assert_eq!(bitmap.store().len(), WORDS);
+  assert!(bitmap.is_clear());
+  bitmap.set(129).unwrap();
+  assert!(bitmap.get(129).unwrap());
+
+  // One word short of what 130 bits need.
+  let short = &mut bytes[..Bitmap::bytes(64)];
+  assert!(matches!(
+    Bitmap::from_bytes(short, 130),
+    Err(BitmapError::InsufficientSize {
+      have: 64,
+      need: 130
+    })
+  ));
+}
+
+#[test]
+fn test_from_bytes_rejects_misaligned() {
+  let mut backing = [0usize; 3];
+  let bytes = unsafe {
+    core::slice::from_raw_parts_mut(backing.as_mut_ptr() as *mut u8, Bitmap::bytes(128) + 1)
+  };
+
+  let misaligned = &mut bytes[1..];
+  let addr = misaligned.as_ptr() as usize;
+  assert!(matches!(
+    Bitmap::from_bytes(misaligned, 64),
+    Err(BitmapError::Misaligned { addr: a, .. }) if a == addr
+  ));
+}
+
+#[test]
+fn test_find_fc_run_skips_short_gaps() {
+  let storage: [AtomicUsize; 2] = [AtomicUsize::new(0), AtomicUsize::new(0)];
+  let bitmap = Bitmap::zero(&storage, 100).unwrap();
+  for bit in [0, 3, 30, 62, 66] {
+    bitmap.set(bit).unwrap();
+  }
+
+  assert_eq!(bitmap.find_fc_run(2), Some(1));
+  assert_eq!(bitmap.find_fc_run(3), Some(4));
+  // Only the tail 67..100 holds more than 31.
+  assert_eq!(bitmap.find_fc_run(31), Some(31));
+  assert_eq!(bitmap.find_fc_run(32), Some(67));
+  assert_eq!(bitmap.find_fc_run(33), Some(67));
+  assert_eq!(bitmap.find_fc_run(34), None);
+  assert_eq!(bitmap.find_fc_run(0), None);
+}
+
+#[test]
+fn test_longest_clear_run_spans_words() {
+  let storage: [AtomicUsize; 3] = [
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+  ];
+  let bitmap = Bitmap::zero(&storage, 150).unwrap();
+  for bit in [0, 3, 30, 62, 140] {
+    bitmap.set(bit).unwrap();
+  }
+
+  // 63..140 crosses two word boundaries and beats the 9 bits left at the tail.
+  assert_eq!(bitmap.longest_clear_run(), (63, 77));
+  bitmap.set(100).unwrap();
+  assert_eq!(bitmap.longest_clear_run(), (101, 39));
+  // Splitting 101..140 leaves 63..100 as the longest.
+  bitmap.set(120).unwrap();
+  assert_eq!(bitmap.longest_clear_run(), (63, 37));
+  bitmap.clear_all();
+}
+
+#[test]
+fn test_longest_clear_run_edges() {
+  let storage: [AtomicUsize; 2] = [AtomicUsize::new(0), AtomicUsize::new(0)];
+  let bitmap = Bitmap::zero(&storage, 100).unwrap();
+  assert_eq!(bitmap.longest_clear_run(), (0, 100));
+
+  // Padding past bit 100 must not extend the tail run.
+  bitmap.set(49).unwrap();
+  assert_eq!(bitmap.longest_clear_run(), (50, 50));
+  bitmap.set(99).unwrap();
+  // 0..49 and 50..99 tie; the lower one wins.
+  assert_eq!(bitmap.longest_clear_run(), (0, 49));
+
+  bitmap.set_all();
+  assert_eq!(bitmap.longest_clear_run(), (0, 0));
+  bitmap.clear_all();
+}
@@ -1,17 +1,28 @@
 #![cfg_attr(not(test), no_std)]
 
 use core::{
+  cell::UnsafeCell,
   cmp,
   mem::MaybeUninit,
-  ops::Range,
+  ops::{
+    Deref,
+    Range,
+  },
+  ptr::NonNull,
+  sync::atomic::{
+    AtomicUsize,
+    Ordering,
+  },
 };
 
 use basealloc_sys::{
-  GLOBAL_SYSTEM,
+  current_system,
   misc::Giveup,
+  prim::is_page_aligned,
   system::{
     SysError,
     SysOption,
+    System,
   },
 };
 
@@ -19,21 +30,123 @@ use basealloc_sys::{
 pub enum ExtentError {
   SystemError(SysError),
   OutOfBounds,
+  /// Mapping it would take the live extents past `memory_limit()`.
+  LimitExceeded,
+  /// The `SharedCell` still holds an extent with handles to it.
+  CellInUse,
 }
 
 pub type ExtentResult<T> = Result<T, ExtentError>;
 
+// Bytes mapped by live extents, and the ceiling new ones are held to.
+static MAPPED: AtomicUsize = AtomicUsize::new(0);
+static LIMIT: AtomicUsize = AtomicUsize::new(usize::MAX);
+
+/// Caps the bytes all live extents together may map; `usize::MAX` lifts the cap.
+///
+/// Extents mapped before lowering it stay valid; only new ones fail, with
+/// `LimitExceeded`. Reserved extents count in full, committed or not.
+pub fn set_memory_limit(bytes: usize) {
+  LIMIT.store(bytes, Ordering::Relaxed);
+}
+
+pub fn memory_limit() -> usize {
+  LIMIT.load(Ordering::Relaxed)
+}
+
+/// Bytes mapped by live extents, whatever their backend.
+pub fn mapped_bytes() -> usize {
+  MAPPED.load(Ordering::Relaxed)
+}
+
+fn charge(size: usize) -> ExtentResult<()> {
+  MAPPED
+    .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |mapped| {
+      mapped
+        .checked_add(size)
+        .filter(|total| *total <= memory_limit())
+    })
+    .map(|_| ())
+    .map_err(|_| ExtentError::LimitExceeded)
+}
+
+fn uncharge(size: usize) {
+  MAPPED.fetch_sub(size, Ordering::Relaxed);
+}
+
+// Bytes spot-checked in a fresh mapping.
+#[cfg(feature = "debug-checks")]
+const ZERO_SAMPLES: usize = 4;
+
+/// Whether the byte at `at` reads as non-zero, skipping pages that aren't resident:
+/// those fault in as zero, and reading them would populate them.
+#[cfg(feature = "debug-checks")]
+fn sample_dirty(slice: &[u8], at: usize, system: &dyn System) -> bool {
+  let ps = basealloc_sys::prim::page_size();
+  let start = at / ps * ps;
+  let Some(page) = slice.get(start..cmp::min(start + ps, slice.len())) else {
+    return false;
+  };
+  if let Ok(0) = unsafe { system.resident_pages(page) } {
+    return false;
+  }
+  slice.get(at).is_some_and(|byte| *byte != 0)
+}
+
+/// Reports a fresh mapping that doesn't read as zero, which the skipped memsets
+/// downstream rely on. Samples a few bytes spread over `slice` rather than all of it.
+#[cfg(feature = "debug-checks")]
+fn check_zeroed(slice: &[u8], system: &dyn System) {
+  let Some(last) = slice.len().checked_sub(1) else {
+    return;
+  };
+  let dirty = (0..ZERO_SAMPLES)
+    .map(|i| last / (ZERO_SAMPLES - 1) * i)
+    .chain([last])
+    .any(|at| sample_dirty(slice, at, system));
+  if dirty {
+    let base = slice.as_ptr() as usize;
+    basealloc_sys::hook::report(basealloc_sys::hook::Fault::DirtyCommit(base));
+  }
+}
+
 pub struct Extent {
   slice: &'static mut [u8],
   activated: bool,
+  // Whether `slice` is this extent's own mapping; a `view` leaves unmapping to its parent.
+  owned: bool,
+  // The backend that mapped `slice`; it also has to unmap it.
+  system: &'static dyn System,
 }
 
 impl Extent {
   pub fn new(size: usize, options: SysOption) -> ExtentResult<Extent> {
-    let slice = unsafe { GLOBAL_SYSTEM.alloc(size, options) }.map_err(ExtentError::SystemError)?;
+    Self::new_in(size, options, current_system())
+  }
+
+  /// Maps the extent through `system` instead of the current global backend.
+  pub fn new_in(
+    size: usize,
+    options: SysOption,
+    system: &'static dyn System,
+  ) -> ExtentResult<Extent> {
+    charge(size)?;
+    let slice = unsafe { system.alloc(size, options) }.map_err(|err| {
+      uncharge(size);
+      ExtentError::SystemError(err)
+    })?;
     let activated = matches!(options, SysOption::Commit);
+    #[cfg(feature = "debug-checks")]
+    if activated {
+      check_zeroed(slice, system);
+    }
 
-    Ok(Extent { slice, activated })
+    Ok(Extent {
+      slice,
+      activated,
+      owned: true,
+      system,
+    })
   }
 
   pub fn check(&self, range: Range<usize>) -> ExtentResult<()> {
@@ -48,7 +161,7 @@ impl Extent {
   }
 
   pub fn modify(&mut self, opt: SysOption) -> ExtentResult<()> {
-    unsafe { GLOBAL_SYSTEM.modify(self.slice, opt) }.map_err(ExtentError::SystemError)?;
+    unsafe { self.system.modify(self.slice, opt) }.map_err(ExtentError::SystemError)?;
     match opt {
       SysOption::Commit => self.activated = true,
       SysOption::Reclaim => self.activated = false,
@@ -57,6 +170,84 @@ impl Extent {
     Ok(())
   }
 
+  /// Commits `range` only, so a reserved extent can be made usable piece by piece.
+  pub fn commit_range(&mut self, range: Range<usize>) -> ExtentResult<()> {
+    self.check(range.clone())?;
+    unsafe { self.system.modify(&self.slice[range], SysOption::Commit) }
+      .map_err(ExtentError::SystemError)
+  }
+
+  /// Drops the backing of `range` only; the extent stays active and the range refaults as zeros.
+  pub fn reclaim_range(&mut self, range: Range<usize>) -> ExtentResult<()> {
+    self.check(range.clone())?;
+    unsafe { self.system.modify(&self.slice[range], SysOption::Reclaim) }
+      .map_err(ExtentError::SystemError)
+  }
+
+  /// Takes in `extra` bytes mapped right after the extent, committed if it is active.
+  ///
+  /// Fails, leaving the extent as it was, when the backend can't map exactly there.
+  pub fn grow(&mut self, extra: usize) -> ExtentResult<()> {
+    charge(extra)?;
+    let options = if self.activated {
+      SysOption::Commit
+    } else {
+      SysOption::Reserve
+    };
+    unsafe { self.system.grow(self.slice, extra, options) }.map_err(|err| {
+      uncharge(extra);
+      ExtentError::SystemError(err)
+    })?;
+    let len = self.slice.len() + extra;
+    self.slice = unsafe { core::slice::from_raw_parts_mut(self.slice.as_mut_ptr(), len) };
+    Ok(())
+  }
+
+  /// Splits off the first `at` bytes as an extent of their own and keeps the rest, so
+  /// one reservation can be handed out piece by piece. Each part unmaps only itself.
+  ///
+  /// `at` must be page-aligned. The backend has to unmap part of a mapping, as `munmap`
+  /// can; the new part is as active as the extent was.
+  pub fn split_front(&mut self, at: usize) -> ExtentResult<Extent> {
+    self.check(0..at)?;
+    if is_page_aligned(at) != Ok(true) {
+      return Err(ExtentError::OutOfBounds);
+    }
+
+    let (head, tail) = core::mem::take(&mut self.slice).split_at_mut(at);
+    self.slice = tail;
+    Ok(Extent {
+      slice: head,
+      activated: self.activated,
+      owned: self.owned,
+      system: self.system,
+    })
+  }
+
+  /// An extent over `range` of this one that doesn't own its pages, e.g. one slab of a
+  /// region several share. Dropping it gives the pages back but leaves them mapped.
+  ///
+  /// `range` must be page-aligned; the view is as active as the extent is.
+  ///
+  /// # Safety
+  ///
+  /// The view must be dropped before this extent, and the two must not be written
+  /// through at once.
+  pub unsafe fn view(&self, range: Range<usize>) -> ExtentResult<Extent> {
+    self.check(range.clone())?;
+    if is_page_aligned(range.start) != Ok(true) || is_page_aligned(range.end) != Ok(true) {
+      return Err(ExtentError::OutOfBounds);
+    }
+
+    let start = unsafe { self.slice.as_ptr().add(range.start) } as *mut u8;
+    Ok(Extent {
+      slice: unsafe { core::slice::from_raw_parts_mut(start, range.len()) },
+      activated: self.activated,
+      owned: false,
+      system: self.system,
+    })
+  }
+
   pub fn activate(&mut self) -> ExtentResult<()> {
     if self.activated {
       return Ok(());
@@ -94,10 +285,157 @@ impl Giveup for Extent {
     let mut target = MaybeUninit::uninit();
     core::mem::swap(&mut self, unsafe { &mut *target.as_mut_ptr() });
     self.slice = &mut [];
+    self.owned = true;
+    // `self` is garbage after the swap; give its drop a valid backend to no-op on.
+    self.system = current_system();
     Ok(unsafe { target.assume_init() })
   }
 }
 
+/// Orders extents by base address for address-indexed sets.
+///
+/// Two keys with the same base are equal whatever their lengths, so a set keyed by it
+/// holds at most one extent per address.
+#[derive(Debug, Clone, Copy)]
+pub struct ExtentKey {
+  pub base: usize,
+  pub len: usize,
+}
+
+impl ExtentKey {
+  pub fn end(&self) -> usize {
+    self.base + self.len
+  }
+}
+
+impl From<&Extent> for ExtentKey {
+  fn from(extent: &Extent) -> Self {
+    Self {
+      base: extent.slice.as_ptr() as usize,
+      len: extent.slice.len(),
+    }
+  }
+}
+
+impl PartialEq for ExtentKey {
+  fn eq(&self, other: &Self) -> bool {
+    self.base == other.base
+  }
+}
+
+impl Eq for ExtentKey {}
+
+impl PartialOrd for ExtentKey {
+  fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
+    Some(self.cmp(other))
+  }
+}
+
+impl Ord for ExtentKey {
+  fn cmp(&self, other: &Self) -> cmp::Ordering {
+    self.base.cmp(&other.base)
+  }
+}
+
+/// Home of an extent several owners share, with the count of `SharedExtent` handles to it.
+///
+/// Lives wherever the owners can all reach it for as long as any handle does, typically a
+/// bump or a static; it can be reused once the last handle dropped.
+pub struct SharedCell {
+  extent: UnsafeCell<MaybeUninit<Extent>>,
+  refs: AtomicUsize,
+}
+
+// The extent is only written while no handle exists and only read through handles.
+unsafe impl Sync for SharedCell {}
+
+impl SharedCell {
+  pub const fn new() -> Self {
+    Self {
+      extent: UnsafeCell::new(MaybeUninit::uninit()),
+      refs: AtomicUsize::new(0),
+    }
+  }
+
+  /// Live handles to the extent in this cell.
+  pub fn refs(&self) -> usize {
+    self.refs.load(Ordering::Acquire)
+  }
+}
+
+impl Default for SharedCell {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+/// A counted handle to an extent backing several owners, e.g. slabs carved from one mapping.
+///
+/// Cloning adds a handle; the extent is dropped, and so unmapped, with the last one.
+pub struct SharedExtent {
+  cell: NonNull<SharedCell>,
+}
+
+unsafe impl Send for SharedExtent {}
+unsafe impl Sync for SharedExtent {}
+
+impl SharedExtent {
+  /// Moves `extent` into `cell` and hands out the first handle to it.
+  ///
+  /// Fails with `CellInUse`, dropping `extent`, while the cell still has handles.
+  pub fn new(extent: Extent, cell: &'static SharedCell) -> ExtentResult<Self> {
+    cell
+      .refs
+      .compare_exchange(0, 1, Ordering::Acquire, Ordering::Relaxed)
+      .map_err(|_| ExtentError::CellInUse)?;
+    unsafe { (*cell.extent.get()).write(extent) };
+    Ok(Self {
+      cell: NonNull::from(cell),
+    })
+  }
+
+  /// Live handles to the extent, this one included.
+  pub fn refs(&self) -> usize {
+    self.cell().refs()
+  }
+
+  fn cell(&self) -> &SharedCell {
+    unsafe { self.cell.as_ref() }
+  }
+}
+
+impl Clone for SharedExtent {
+  fn clone(&self) -> Self {
+    self.cell().refs.fetch_add(1, Ordering::Relaxed);
+    Self { cell: self.cell }
+  }
+}
+
+impl Deref for SharedExtent {
+  type Target = Extent;
+
+  fn deref(&self) -> &Extent {
+    unsafe { (*self.cell().extent.get()).assume_init_ref() }
+  }
+}
+
+impl Drop for SharedExtent {
+  fn drop(&mut self) {
+    let refs = &self.cell().refs;
+    let mut now = refs.load(Ordering::Acquire);
+    // The last handle releases the cell only once the extent is gone, so `new` can't
+    // refill it mid-drop.
+    while now > 1 {
+      match refs.compare_exchange(now, now - 1, Ordering::Release, Ordering::Acquire) {
+        Ok(_) => return,
+        Err(seen) => now = seen,
+      }
+    }
+    unsafe { (*self.cell().extent.get()).assume_init_drop() };
+    refs.store(0, Ordering::Release);
+  }
+}
+
 impl AsRef<[u8]> for Extent {
   fn as_ref(&self) -> &[u8] {
     self.slice
@@ -112,7 +450,21 @@ impl AsMut<[u8]> for Extent {
 
 impl Drop for Extent {
   fn drop(&mut self) {
-    let _ = unsafe { GLOBAL_SYSTEM.dealloc(self.slice) };
+    // A view's pages stay mapped for its parent.
+    if !self.owned {
+      if self.activated {
+        let _ = unsafe { self.system.modify(self.slice, SysOption::Reclaim) };
+      }
+      return;
+    }
+    // An extent may live inside its own mapping; nothing of `self` is readable after.
+    let _base = self.slice.as_ptr() as usize;
+    uncharge(self.slice.len());
+    let _result = unsafe { self.system.dealloc(self.slice) };
+    #[cfg(feature = "debug-checks")]
+    if _result.is_err() {
+      basealloc_sys::hook::report(basealloc_sys::hook::Fault::UnmapFailed(_base));
+    }
   }
 }
 
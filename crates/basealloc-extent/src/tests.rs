@@ -1,5 +1,14 @@
+use core::sync::atomic::{
+  AtomicUsize,
+  Ordering,
+};
+
 use super::*;
-use basealloc_sys::prelude::*;
+use basealloc_sys::{
+  counting::CountingSystem,
+  prelude::*,
+  set_system,
+};
 
 #[test]
 fn test_extent_new() {
@@ -46,6 +55,7 @@ fn test_extent_check_valid() {
 }
 
 #[test]
+#[allow(clippy::reversed_empty_ranges)]
 fn test_extent_check_invalid() {
   let ps = page_size();
   let extent = Extent::new(ps, SysOption::Commit).unwrap();
@@ -70,6 +80,76 @@ fn test_extent_drop() {
   drop(extent);
 }
 
+#[test]
+fn test_extent_deactivate_drops_residency() {
+  let ps = page_size();
+  let mut extent = Extent::new(4 * ps, SysOption::Commit).unwrap();
+  extent.as_mut().fill(1);
+  assert_eq!(
+    unsafe { GLOBAL_SYSTEM.resident_pages(extent.as_ref()) }.unwrap(),
+    4
+  );
+
+  extent.deactivate().unwrap();
+  assert_eq!(
+    unsafe { GLOBAL_SYSTEM.resident_pages(extent.as_ref()) }.unwrap(),
+    0
+  );
+}
+
+#[test]
+fn test_extent_reclaim_range_keeps_the_rest() {
+  let ps = page_size();
+  let mut extent = Extent::new(4 * ps, SysOption::Commit).unwrap();
+  extent.as_mut().fill(1);
+
+  extent.reclaim_range(ps..3 * ps).unwrap();
+  assert!(extent.is_activated());
+  assert_eq!(
+    unsafe { GLOBAL_SYSTEM.resident_pages(extent.as_ref()) }.unwrap(),
+    2
+  );
+  assert!(extent.as_ref()[ps..3 * ps].iter().all(|b| *b == 0));
+  assert!(extent.reclaim_range(3 * ps..5 * ps).is_err());
+}
+
+#[test]
+fn test_extent_key_orders_by_base() {
+  let ps = page_size();
+  let small = Extent::new(ps, SysOption::Commit).unwrap();
+  let large = Extent::new(4 * ps, SysOption::Commit).unwrap();
+  let (a, b) = (ExtentKey::from(&small), ExtentKey::from(&large));
+
+  assert_eq!(a.base, small.as_ref().as_ptr() as usize);
+  assert_eq!((a.len, b.len), (ps, 4 * ps));
+  assert_eq!(a.cmp(&b), a.base.cmp(&b.base));
+  assert_eq!(b.end(), b.base + 4 * ps);
+
+  let mut keys = [b, a];
+  keys.sort();
+  assert!(keys[0].base < keys[1].base);
+}
+
+#[test]
+fn test_extent_key_equal_by_base_only() {
+  let short = ExtentKey {
+    base: 0x10000,
+    len: 4096,
+  };
+  let long = ExtentKey {
+    base: 0x10000,
+    len: 1 << 20,
+  };
+  let after = ExtentKey {
+    base: 0x20000,
+    len: 4096,
+  };
+
+  assert_eq!(short, long);
+  assert_eq!(short.cmp(&long), core::cmp::Ordering::Equal);
+  assert!(long < after && short < after);
+}
+
 #[test]
 fn test_extent_giveup() {
   let ps = page_size();
@@ -79,3 +159,207 @@ fn test_extent_giveup() {
   assert!(extent2.is_ok());
   assert_eq!(extent2.unwrap().as_ref().len(), len);
 }
+
+struct CountingMock {
+  allocs: AtomicUsize,
+}
+
+// Only counts one size so extents created by parallel tests don't interfere.
+const MOCK_PAGES: usize = 37;
+
+unsafe impl System for CountingMock {
+  unsafe fn alloc<'mem>(&self, size: usize, options: SysOption) -> SysResult<&'mem mut [u8]> {
+    if size == MOCK_PAGES * page_size() {
+      self.allocs.fetch_add(1, Ordering::Relaxed);
+    }
+    unsafe { GLOBAL_SYSTEM.alloc(size, options) }
+  }
+
+  unsafe fn modify(&self, slice: &[u8], options: SysOption) -> SysResult<()> {
+    unsafe { GLOBAL_SYSTEM.modify(slice, options) }
+  }
+
+  unsafe fn dealloc(&self, slice: &[u8]) -> SysResult<()> {
+    unsafe { GLOBAL_SYSTEM.dealloc(slice) }
+  }
+}
+
+static MOCK: CountingMock = CountingMock {
+  allocs: AtomicUsize::new(0),
+};
+static MOCK_SYSTEM: &dyn System = &MOCK;
+
+#[test]
+fn test_extent_uses_current_system() {
+  set_system(&MOCK_SYSTEM);
+  let extent = Extent::new(MOCK_PAGES * page_size(), SysOption::Commit).unwrap();
+  set_system(&GLOBAL_SYSTEM);
+
+  assert_eq!(MOCK.allocs.load(Ordering::Relaxed), 1);
+  drop(extent);
+}
+
+#[cfg(feature = "debug-checks")]
+mod debug_checks {
+  use std::cell::RefCell;
+
+  use basealloc_sys::hook::{
+    Fault,
+    set_error_hook,
+  };
+
+  use super::*;
+
+  std::thread_local! {
+    static SEEN: RefCell<Vec<Fault>> = const { RefCell::new(Vec::new()) };
+  }
+
+  fn record(fault: Fault) {
+    SEEN.with(|seen| seen.borrow_mut().push(fault));
+  }
+
+  // Unmaps for real so nothing leaks, then claims it failed.
+  struct FailingUnmap;
+
+  unsafe impl System for FailingUnmap {
+    unsafe fn alloc<'mem>(&self, size: usize, options: SysOption) -> SysResult<&'mem mut [u8]> {
+      unsafe { GLOBAL_SYSTEM.alloc(size, options) }
+    }
+
+    unsafe fn dealloc(&self, slice: &[u8]) -> SysResult<()> {
+      unsafe { GLOBAL_SYSTEM.dealloc(slice) }?;
+      Err(SysError::InvalidArgument)
+    }
+  }
+
+  static FAILING: FailingUnmap = FailingUnmap;
+
+  #[test]
+  fn test_failed_unmap_reports_fault() {
+    set_error_hook(Some(record));
+    let extent = Extent::new_in(page_size(), SysOption::Commit, &FAILING).unwrap();
+    let base = extent.as_ref().as_ptr() as usize;
+    drop(extent);
+    assert_eq!(SEEN.with(|seen| seen.take()), [Fault::UnmapFailed(base)]);
+  }
+
+  #[test]
+  fn test_clean_unmap_is_silent() {
+    set_error_hook(Some(record));
+    drop(Extent::new(page_size(), SysOption::Commit).unwrap());
+    assert!(SEEN.with(|seen| seen.take()).is_empty());
+  }
+
+  // Hands out mappings with garbage in them, like a backend that recycles memory.
+  struct DirtyCommit;
+
+  unsafe impl System for DirtyCommit {
+    unsafe fn alloc<'mem>(&self, size: usize, _: SysOption) -> SysResult<&'mem mut [u8]> {
+      let slice = unsafe { GLOBAL_SYSTEM.alloc(size, SysOption::Commit) }?;
+      slice[size - 1] = 0xAA;
+      Ok(slice)
+    }
+
+    unsafe fn dealloc(&self, slice: &[u8]) -> SysResult<()> {
+      unsafe { GLOBAL_SYSTEM.dealloc(slice) }
+    }
+  }
+
+  static DIRTY: DirtyCommit = DirtyCommit;
+
+  #[test]
+  fn test_dirty_commit_reports_fault() {
+    set_error_hook(Some(record));
+    let extent = Extent::new_in(3 * page_size(), SysOption::Commit, &DIRTY).unwrap();
+    let base = extent.as_ref().as_ptr() as usize;
+    assert_eq!(SEEN.with(|seen| seen.take()), [Fault::DirtyCommit(base)]);
+
+    // Reserved memory makes no promise until it's committed.
+    drop(Extent::new_in(page_size(), SysOption::Reserve, &DIRTY).unwrap());
+    assert!(SEEN.with(|seen| seen.take()).is_empty());
+  }
+}
+
+static UNMAPS: CountingSystem = CountingSystem::new(GLOBAL_SYSTEM);
+static VIEWED: CountingSystem = CountingSystem::new(GLOBAL_SYSTEM);
+static SHARED: SharedCell = SharedCell::new();
+
+#[test]
+fn test_shared_extent_unmaps_with_the_last_handle() {
+  let extent = Extent::new_in(2 * page_size(), SysOption::Commit, &UNMAPS).unwrap();
+  let first = SharedExtent::new(extent, &SHARED).unwrap();
+  let second = first.clone();
+  assert_eq!(SHARED.refs(), 2);
+  assert_eq!(first.as_ref().as_ptr(), second.as_ref().as_ptr());
+
+  let unmaps = UNMAPS.deallocs();
+  let busy = Extent::new_in(page_size(), SysOption::Reserve, &UNMAPS).unwrap();
+  assert!(matches!(
+    SharedExtent::new(busy, &SHARED),
+    Err(ExtentError::CellInUse)
+  ));
+  assert_eq!(UNMAPS.deallocs(), unmaps + 1);
+
+  drop(first);
+  assert_eq!(UNMAPS.deallocs(), unmaps + 1);
+  drop(second);
+  assert_eq!(UNMAPS.deallocs(), unmaps + 2);
+  assert_eq!(SHARED.refs(), 0);
+}
+
+#[test]
+fn test_view_leaves_the_pages_mapped() {
+  let ps = page_size();
+  let mut whole = Extent::new_in(3 * ps, SysOption::Reserve, &VIEWED).unwrap();
+  let base = whole.as_ref().as_ptr();
+  assert!(matches!(
+    unsafe { whole.view(ps..ps + 1) },
+    Err(ExtentError::OutOfBounds)
+  ));
+  assert!(matches!(
+    unsafe { whole.view(ps..4 * ps) },
+    Err(ExtentError::OutOfBounds)
+  ));
+
+  let mut middle = unsafe { whole.view(ps..2 * ps) }.unwrap();
+  assert_eq!(middle.as_ref().as_ptr(), base.wrapping_add(ps));
+  middle.activate().unwrap();
+  middle.as_mut().fill(0xA5);
+  drop(middle);
+  assert_eq!(VIEWED.deallocs(), 0);
+
+  // The view's pages went back, but are still there to commit again.
+  whole.activate().unwrap();
+  assert!(whole.as_ref().iter().all(|b| *b == 0));
+  drop(whole);
+  assert_eq!(VIEWED.deallocs(), 1);
+}
+
+#[test]
+fn test_split_front_parts_unmap_on_their_own() {
+  let ps = page_size();
+  let mut rest = Extent::new(4 * ps, SysOption::Reserve).unwrap();
+  let base = rest.as_ref().as_ptr();
+  assert!(matches!(
+    rest.split_front(ps + 1),
+    Err(ExtentError::OutOfBounds)
+  ));
+  assert!(matches!(
+    rest.split_front(5 * ps),
+    Err(ExtentError::OutOfBounds)
+  ));
+
+  let mut front = rest.split_front(ps).unwrap();
+  assert_eq!((front.as_ref().as_ptr(), front.size()), (base, ps));
+  assert_eq!(rest.as_ref().as_ptr(), base.wrapping_add(ps));
+  assert_eq!(rest.size(), 3 * ps);
+  front.activate().unwrap();
+  front.as_mut().fill(0xA5);
+
+  // Unmapping the front leaves the rest mapped.
+  drop(front);
+  rest.activate().unwrap();
+  rest.as_mut().fill(0x5A);
+  let back = rest.split_front(ps).unwrap();
+  assert!(back.is_activated() && back.as_ref().iter().all(|b| *b == 0x5A));
+}
@@ -24,14 +24,20 @@ use basealloc_list::{
   HasLink,
   Link,
   List,
+  ListIter,
 };
 use basealloc_sys::{
+  current_system,
   math::align_up,
   prim::{
     PrimError,
     page_align,
+    page_size,
+  },
+  system::{
+    SysOption,
+    System,
   },
-  system::SysOption,
 };
 
 #[derive(Debug)]
@@ -48,6 +54,8 @@ pub struct Chunk {
   link: ManuallyDrop<Link<Self>>,
   fixed: ManuallyDrop<Fixed>,
   extent: ManuallyDrop<Extent>,
+  // The extent is only reserved; bytes below this are committed.
+  committed: usize,
 }
 
 impl Chunk {
@@ -60,8 +68,13 @@ impl Chunk {
     page_align(aligned).map_err(ChunkError::PrimError)
   }
 
-  pub fn new(size: usize) -> ChunkResult<NonNull<Self>> {
-    let mut extent = Extent::new(size, SysOption::Commit).map_err(ChunkError::ExtentError)?;
+  pub fn new(size: usize, system: &'static dyn System) -> ChunkResult<NonNull<Self>> {
+    let mut extent =
+      Extent::new_in(size, SysOption::Reserve, system).map_err(ChunkError::ExtentError)?;
+    let header = Self::data_offset()?;
+    extent
+      .commit_range(0..header)
+      .map_err(ChunkError::ExtentError)?;
     let mut fixed = Fixed::new(extent.as_mut());
     let chunk_ptr = fixed
       .create::<Self>(extent.as_mut())
@@ -71,6 +84,7 @@ impl Chunk {
     chunk.link = ManuallyDrop::new(Link::default());
     chunk.fixed = ManuallyDrop::new(fixed);
     chunk.extent = ManuallyDrop::new(extent);
+    chunk.committed = header;
 
     Ok(unsafe { NonNull::new_unchecked(chunk_ptr) })
   }
@@ -82,12 +96,48 @@ impl Chunk {
     Ok(unsafe { &mut *ptr })
   }
 
-  pub fn allocate(&mut self, layout: Layout) -> ChunkResult<&mut [u8]> {
-    let extent_slice = self.extent.as_mut();
+  fn offset(&self) -> usize {
+    self.fixed.offset()
+  }
+
+  // The header is the first thing carved from the chunk.
+  fn rewind(&mut self, offset: usize) {
+    self.fixed.rewind(offset.max(Self::SELF_LAYOUT.size()));
+  }
+
+  fn commit_to(&mut self, end: usize) -> ChunkResult<()> {
+    if end <= self.committed {
+      return Ok(());
+    }
+
+    let target = page_align(end).map_err(ChunkError::PrimError)?;
+    let target = target.min(self.extent.size());
     self
-      .fixed
-      .allocate(extent_slice, layout)
-      .map_err(ChunkError::FixedError)
+      .extent
+      .commit_range(self.committed..target)
+      .map_err(ChunkError::ExtentError)?;
+    self.committed = target;
+    Ok(())
+  }
+
+  pub fn allocate(&mut self, layout: Layout) -> ChunkResult<&mut [u8]> {
+    let before = self.fixed.offset();
+    let start = {
+      let extent_slice = self.extent.as_mut();
+      let base = extent_slice.as_ptr() as usize;
+      let slice = self
+        .fixed
+        .allocate(extent_slice, layout)
+        .map_err(ChunkError::FixedError)?;
+      slice.as_ptr() as usize - base
+    };
+
+    let end = start + layout.size();
+    if let Err(err) = self.commit_to(end) {
+      self.fixed.rewind(before);
+      return Err(err);
+    }
+    Ok(&mut self.extent.as_mut()[start..end])
   }
 }
 
@@ -111,16 +161,25 @@ impl HasLink for Chunk {
   }
 }
 
+/// A point in a `Bump` to rewind to; see `Bump::rewind`.
+#[derive(Clone, Copy)]
+pub struct BumpMark {
+  chunk: Option<NonNull<Chunk>>,
+  offset: usize,
+}
+
 pub type BumpError = ChunkError;
 pub type BumpResult<T> = Result<T, BumpError>;
 
 unsafe impl Send for Bump {}
-unsafe impl Sync for Bump {} 
+unsafe impl Sync for Bump {}
 
 pub struct Bump {
   head: Option<NonNull<Chunk>>, // TODO: turn into atomics
   tail: Option<NonNull<Chunk>>, // TODO: turn into atomics
   chunk_size: usize,
+  // `None` maps chunks through whatever backend is current at the time.
+  system: Option<&'static dyn System>,
 }
 
 impl Bump {
@@ -129,17 +188,35 @@ impl Bump {
       head: None,
       tail: None,
       chunk_size,
+      system: None,
+    }
+  }
+
+  /// A bump whose chunks always come from `system`.
+  pub const fn new_in(chunk_size: usize, system: &'static dyn System) -> Self {
+    Self {
+      head: None,
+      tail: None,
+      chunk_size,
+      system: Some(system),
     }
   }
 
   fn obtain_chunk(&self, layout: Layout) -> BumpResult<NonNull<Chunk>> {
     let header = Chunk::data_offset()?;
+    // Chunks are only page aligned; leave room to slide a wider alignment into place.
+    let slack = if layout.align() > page_size() {
+      layout.align()
+    } else {
+      0
+    };
     let required = header
       .checked_add(layout.size())
+      .and_then(|required| required.checked_add(slack))
       .ok_or(ChunkError::Overflow)?;
     let chunk_size = cmp::max(self.chunk_size, required);
     let chunk_size = page_align(chunk_size).map_err(ChunkError::PrimError)?;
-    Chunk::new(chunk_size)
+    Chunk::new(chunk_size, self.system.unwrap_or_else(current_system))
   }
 
   pub fn create<T>(&mut self) -> BumpResult<*mut MaybeUninit<T>> {
@@ -148,11 +225,28 @@ impl Bump {
     Ok(bytes.as_mut_ptr() as *mut MaybeUninit<T>)
   }
 
+  /// Moves `init` into fresh bump memory without zeroing it first.
+  pub fn create_with<T>(&mut self, init: T) -> BumpResult<NonNull<T>> {
+    let slot = self.create::<T>()?;
+    let ptr = unsafe { (*slot).write(init) as *mut T };
+    Ok(unsafe { NonNull::new_unchecked(ptr) })
+  }
+
+  fn try_tail(&mut self, layout: Layout) -> Option<&mut [u8]> {
+    // Chunks past the tail are left over from a rewind and still mapped.
+    while let Some(mut tail) = self.tail {
+      if let Ok(slice) = unsafe { tail.as_mut().allocate(layout) } {
+        return Some(slice);
+      }
+      self.tail = Some(unsafe { tail.as_ref() }.link().next()?);
+    }
+    None
+  }
+
   pub fn allocate(&mut self, layout: Layout) -> BumpResult<&mut [u8]> {
-    if let Some(mut tail) = self.tail
-      && let Ok(slice) = unsafe { tail.as_mut().allocate(layout) }
-    {
-      return Ok(slice);
+    if let Some(slice) = self.try_tail(layout) {
+      // SAFETY: Only detaches the borrow from `self`; the memory lives in a chunk.
+      return Ok(unsafe { &mut *(slice as *mut [u8]) });
     }
 
     let mut new_chunk = self.obtain_chunk(layout)?;
@@ -167,6 +261,54 @@ impl Bump {
 
     unsafe { new_chunk.as_mut().allocate(layout) }
   }
+
+  pub fn mark(&self) -> BumpMark {
+    BumpMark {
+      chunk: self.tail,
+      offset: self
+        .tail
+        .map_or(0, |tail| unsafe { tail.as_ref() }.offset()),
+    }
+  }
+
+  /// Forgets every allocation made after `mark` but keeps all chunks mapped for reuse.
+  ///
+  /// # Safety
+  ///
+  /// `mark` must come from this bump, and nothing allocated after it may be used again.
+  pub unsafe fn rewind(&mut self, mark: BumpMark) {
+    let mut current = mark.chunk.or(self.head);
+    let mut offset = mark.offset;
+    self.tail = current;
+
+    while let Some(mut chunk) = current {
+      let chunk_mut = unsafe { chunk.as_mut() };
+      chunk_mut.rewind(offset);
+      offset = 0;
+      current = chunk_mut.link().next();
+    }
+  }
+
+  /// Gives up the bump without unmapping its chunks, so everything carved from them
+  /// stays valid for the rest of the process.
+  ///
+  /// For process-lifetime owners whose memory may still be reached during teardown;
+  /// the chunks can't be freed afterwards.
+  pub fn leak(self) {
+    core::mem::forget(self);
+  }
+
+  pub fn chunk_count(&self) -> usize {
+    self.chunks().count()
+  }
+
+  /// `(base address, size)` of every mapped chunk, in list order.
+  pub fn chunks(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+    ListIter::new(self.head).map(|chunk: &mut Chunk| {
+      let extent = chunk.extent.as_ref();
+      (extent.as_ptr() as usize, extent.len())
+    })
+  }
 }
 
 impl Drop for Bump {
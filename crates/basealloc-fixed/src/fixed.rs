@@ -54,7 +54,12 @@ impl Fixed {
     Ok(end)
   }
 
-  fn reserve(&self, slice_ptr: usize, required: usize, align: usize) -> FixedResult<usize> {
+  pub(crate) fn reserve(
+    &self,
+    slice_ptr: usize,
+    required: usize,
+    align: usize,
+  ) -> FixedResult<usize> {
     loop {
       let current = self.offset.load(Ordering::Acquire);
 
@@ -97,6 +102,15 @@ impl Fixed {
     Ok(&mut slice[start..end])
   }
 
+  pub fn offset(&self) -> usize {
+    self.offset.load(Ordering::Acquire)
+  }
+
+  /// Moves the cursor back to `offset`; everything past it may be handed out again.
+  pub fn rewind(&self, offset: usize) {
+    self.offset.store(offset.min(self.max), Ordering::Release);
+  }
+
   pub fn create<T>(&mut self, slice: &mut [u8]) -> FixedResult<*mut T> {
     let layout = Layout::new::<T>();
     let bytes = self.allocate(slice, layout)?;
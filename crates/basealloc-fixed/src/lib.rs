@@ -2,6 +2,7 @@
 
 pub mod bump;
 mod fixed;
+pub mod pool;
 
 #[cfg(test)]
 mod tests;
@@ -0,0 +1,93 @@
+use core::{
+  mem::{
+    align_of,
+    size_of,
+  },
+  ptr::NonNull,
+};
+
+use basealloc_sys::{
+  math::align_up,
+  prim::page_size,
+  system::{
+    SysError,
+    SysOption,
+    SysResult,
+    System,
+  },
+};
+
+use crate::fixed::{
+  Fixed,
+  FixedError,
+};
+
+/// A `System` that hands out pages from one caller-provided buffer and never maps.
+///
+/// Nothing is ever returned to the buffer; once it is used up, `alloc` fails with
+/// `OutOfMemory`.
+pub struct FixedSystem {
+  fixed: Fixed,
+  base: NonNull<u8>,
+}
+
+impl FixedSystem {
+  /// Places the system at the start of `buf` and uses the rest as its pool.
+  pub fn carve(buf: &'static mut [u8]) -> SysResult<&'static Self> {
+    let start = buf.as_mut_ptr() as usize;
+    let header = align_up(start, align_of::<Self>()).ok_or(SysError::InvalidArgument)?;
+    let pool = header
+      .checked_add(size_of::<Self>())
+      .ok_or(SysError::InvalidArgument)?;
+    let len = (start + buf.len())
+      .checked_sub(pool)
+      .ok_or(SysError::OutOfMemory)?;
+
+    let pool_slice = unsafe { core::slice::from_raw_parts(pool as *const u8, len) };
+    let this = header as *mut Self;
+    unsafe {
+      this.write(Self {
+        fixed: Fixed::new(pool_slice),
+        base: NonNull::new_unchecked(pool as *mut u8),
+      })
+    };
+    Ok(unsafe { &*this })
+  }
+
+  fn map_err(err: FixedError) -> SysError {
+    match err {
+      FixedError::OutOfMemory => SysError::OutOfMemory,
+      FixedError::Invalid => SysError::InvalidArgument,
+    }
+  }
+}
+
+unsafe impl System for FixedSystem {
+  unsafe fn alloc<'mem>(&self, size: usize, _: SysOption) -> SysResult<&'mem mut [u8]> {
+    let base = self.base.as_ptr() as usize;
+    let start = self
+      .fixed
+      .reserve(base, size, page_size())
+      .map_err(Self::map_err)?;
+
+    let slice = unsafe { core::slice::from_raw_parts_mut(self.base.as_ptr().add(start), size) };
+    // Callers expect fresh mappings to read as zero.
+    slice.fill(0);
+    Ok(slice)
+  }
+
+  unsafe fn modify(&self, slice: &[u8], options: SysOption) -> SysResult<()> {
+    // Nothing to hand back, but reclaimed memory has to refault as zeros like a mapping's.
+    if let SysOption::Reclaim = options {
+      unsafe { (slice.as_ptr() as *mut u8).write_bytes(0, slice.len()) };
+    }
+    Ok(())
+  }
+
+  unsafe fn dealloc(&self, _: &[u8]) -> SysResult<()> {
+    Ok(())
+  }
+}
+
+unsafe impl Send for FixedSystem {}
+unsafe impl Sync for FixedSystem {}
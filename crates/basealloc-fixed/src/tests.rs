@@ -1,11 +1,21 @@
 use core::alloc::Layout;
 
+use basealloc_sys::{
+  prim::page_size,
+  system::{
+    SysError,
+    SysOption,
+    System,
+  },
+};
+
 use crate::{
   bump::Bump,
   fixed::{
     Fixed,
     FixedError,
   },
+  pool::FixedSystem,
 };
 
 #[test]
@@ -100,3 +110,155 @@ fn bump_create_zeroed() {
     sample.b = 2;
   }
 }
+
+#[test]
+fn bump_create_with_stores_value() {
+  #[repr(C, align(32))]
+  struct Sample {
+    a: u64,
+    b: [u8; 24],
+  }
+
+  let mut bump = Bump::new(0);
+  let _ = bump
+    .allocate(Layout::from_size_align(3, 1).unwrap())
+    .unwrap();
+  let ptr = bump
+    .create_with(Sample {
+      a: 0xDEAD_BEEF,
+      b: [0xAB; 24],
+    })
+    .unwrap();
+
+  assert_eq!(ptr.as_ptr() as usize % core::mem::align_of::<Sample>(), 0);
+  let sample = unsafe { ptr.as_ref() };
+  assert_eq!(sample.a, 0xDEAD_BEEF);
+  assert_eq!(sample.b, [0xAB; 24]);
+}
+
+#[test]
+fn bump_rewind_reuses_chunks() {
+  let mut bump = Bump::new(0);
+  let layout = Layout::from_size_align(1024, 8).unwrap();
+  let keep = bump.allocate(layout).unwrap().as_ptr() as usize;
+  let mark = bump.mark();
+
+  let first: Vec<usize> = (0..16)
+    .map(|_| bump.allocate(layout).unwrap().as_ptr() as usize)
+    .collect();
+  let chunks = bump.chunk_count();
+  assert!(chunks > 1);
+
+  unsafe { bump.rewind(mark) };
+  let second: Vec<usize> = (0..16)
+    .map(|_| bump.allocate(layout).unwrap().as_ptr() as usize)
+    .collect();
+
+  assert_eq!(first, second);
+  assert_eq!(bump.chunk_count(), chunks);
+  assert!(!second.contains(&keep));
+}
+
+#[test]
+fn bump_chunks_report_each_mapping() {
+  let ps = page_size();
+  let mut bump = Bump::new(ps);
+  let big = Layout::from_size_align(2 * ps, 8).unwrap();
+  let first = bump.allocate(Layout::new::<u64>()).unwrap().as_ptr() as usize;
+  let second = bump.allocate(big).unwrap().as_ptr() as usize;
+
+  let chunks: Vec<(usize, usize)> = bump.chunks().collect();
+  assert_eq!(chunks.len(), 2);
+  assert!(chunks.iter().all(|(_, size)| size.is_multiple_of(ps)));
+  // The second chunk grew past `chunk_size` to fit the header and both pages.
+  assert!(chunks[1].1 > 2 * ps);
+  for ((base, size), (ptr, len)) in chunks.iter().zip([(first, 8), (second, 2 * ps)]) {
+    assert!(*base <= ptr && ptr + len <= base + size);
+  }
+}
+
+#[test]
+fn chunk_commits_pages_as_the_bump_grows() {
+  use basealloc_sys::{
+    GLOBAL_SYSTEM,
+    counting::CountingSystem,
+  };
+
+  static COUNTING: CountingSystem = CountingSystem::new(GLOBAL_SYSTEM);
+  let ps = page_size();
+  let mut bump = Bump::new_in(64 * ps, &COUNTING);
+
+  bump
+    .allocate(Layout::from_size_align(100, 8).unwrap())
+    .unwrap()
+    .fill(1);
+  let (base, size) = bump.chunks().next().unwrap();
+  let chunk = unsafe { core::slice::from_raw_parts(base as *const u8, size) };
+  assert_eq!(size, 64 * ps);
+  // Only the header's page is committed, and the small block shares it.
+  assert_eq!(COUNTING.modifies(), 1);
+  assert_eq!(unsafe { GLOBAL_SYSTEM.resident_pages(chunk) }.unwrap(), 1);
+
+  let big = bump
+    .allocate(Layout::from_size_align(2 * ps, 8).unwrap())
+    .unwrap();
+  big.fill(1);
+  assert_eq!(COUNTING.modifies(), 2);
+  assert_eq!(unsafe { GLOBAL_SYSTEM.resident_pages(chunk) }.unwrap(), 3);
+}
+
+#[test]
+fn bump_aligns_past_page_size() {
+  let ps = page_size();
+  for align in [2 * ps, 16 * ps, 512 * ps] {
+    let mut bump = Bump::new(0);
+    let layout = Layout::from_size_align(64, align).unwrap();
+    let ptr = bump.allocate(layout).unwrap().as_ptr() as usize;
+    assert!(ptr.is_multiple_of(align), "align {}", align);
+
+    let again = bump.allocate(layout).unwrap().as_ptr() as usize;
+    assert!(again.is_multiple_of(align) && again != ptr);
+  }
+}
+
+#[test]
+fn fixed_system_stays_in_buffer() {
+  let len = 8 * page_size();
+  let buf: &'static mut [u8] = Box::leak(vec![0xAAu8; len].into_boxed_slice());
+  let range = buf.as_ptr() as usize..buf.as_ptr() as usize + len;
+  let system = FixedSystem::carve(buf).unwrap();
+
+  let first = unsafe { system.alloc(page_size(), SysOption::Commit) }.unwrap();
+  assert!(range.contains(&(first.as_ptr() as usize)));
+  assert_eq!(first.as_ptr() as usize % page_size(), 0);
+  assert!(first.iter().all(|b| *b == 0));
+
+  let err = unsafe { system.alloc(len, SysOption::Commit) }.unwrap_err();
+  assert!(matches!(err, SysError::OutOfMemory));
+}
+
+#[test]
+fn leaked_bump_keeps_its_chunks() {
+  use basealloc_sys::{
+    GLOBAL_SYSTEM,
+    counting::CountingSystem,
+  };
+
+  static LEAKED: CountingSystem = CountingSystem::new(GLOBAL_SYSTEM);
+  static DROPPED: CountingSystem = CountingSystem::new(GLOBAL_SYSTEM);
+  let layout = Layout::from_size_align(3 * page_size(), 8).unwrap();
+
+  let mut leaked = Bump::new_in(page_size(), &LEAKED);
+  let slice = leaked.allocate(layout).unwrap();
+  slice.fill(0x5A);
+  let (base, len) = (slice.as_ptr(), slice.len());
+  leaked.leak();
+  assert_eq!(LEAKED.deallocs(), 0);
+  let kept = unsafe { core::slice::from_raw_parts(base, len) };
+  assert!(kept.iter().all(|b| *b == 0x5A));
+
+  let mut dropped = Bump::new_in(page_size(), &DROPPED);
+  dropped.allocate(layout).unwrap();
+  drop(dropped);
+  assert_eq!(DROPPED.deallocs(), DROPPED.allocs());
+}
@@ -65,6 +65,14 @@ where
   }
 }
 
+#[derive(Debug, PartialEq, Eq)]
+pub enum ListError {
+  /// Following `next` from the start comes back around.
+  Cycle,
+  /// A node's `prev` doesn't point at the node linking to it.
+  BrokenLink,
+}
+
 pub struct List {}
 
 impl List {
@@ -148,12 +156,48 @@ impl List {
     item_link.set_prev(None);
   }
 
+  /// Walks the list from `head` checking every back link; returns its length.
+  ///
+  /// `head`'s own `prev` isn't checked, so a list can be validated from any node. A
+  /// `next` leading back into the middle shows up as a broken link, a ring as a cycle.
+  pub fn validate<T>(head: Option<NonNull<T>>) -> Result<usize, ListError>
+  where
+    T: HasLink,
+  {
+    let (mut prev, mut current, mut slow) = (None, head, head);
+    let mut len = 0;
+    while let Some(node) = current {
+      let link = unsafe { node.as_ref() }.link();
+      if prev.is_some() && link.prev() != prev {
+        return Err(ListError::BrokenLink);
+      }
+      len += 1;
+      // Moves at half speed; the walk only meets it again by going around a cycle.
+      if len % 2 == 0 {
+        slow = slow.and_then(|s| unsafe { s.as_ref() }.link().next());
+      }
+      (prev, current) = (current, link.next());
+      if current.is_some() && current == slow {
+        return Err(ListError::Cycle);
+      }
+    }
+    Ok(len)
+  }
+
   pub fn drain<'list, T>(start: &'list mut T) -> ListDrainer<'list, T>
   where
     T: HasLink + 'list,
   {
     ListDrainer::from(start)
   }
+
+  /// Like `drain`, but nodes left when the taker is dropped are neither unlinked nor dropped.
+  pub fn take<'list, T>(start: &'list mut T) -> ListTaker<'list, T>
+  where
+    T: HasLink + 'list,
+  {
+    ListTaker::from(start)
+  }
 }
 
 pub struct ListIter<'list, T>
@@ -266,6 +310,51 @@ where
   }
 }
 
+/// Unlinks and yields each node without ever dropping one; moving them on is up to the caller.
+pub struct ListTaker<'list, T>
+where
+  T: HasLink + 'list,
+{
+  next: Option<NonNull<T>>,
+  marker: PhantomData<&'list T>,
+}
+
+impl<'list, T> ListTaker<'list, T>
+where
+  T: HasLink + 'list,
+{
+  pub fn new(start: Option<NonNull<T>>) -> Self {
+    Self {
+      next: start,
+      marker: PhantomData,
+    }
+  }
+}
+
+impl<'list, T> From<&'list mut T> for ListTaker<'list, T>
+where
+  T: HasLink + 'list,
+{
+  fn from(start: &'list mut T) -> Self {
+    Self::new(Some(NonNull::from(start)))
+  }
+}
+
+impl<'list, T> Iterator for ListTaker<'list, T>
+where
+  T: HasLink + 'list,
+{
+  type Item = &'list mut T;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    let current = self.next.take()?;
+    let current_ref = unsafe { &mut *current.as_ptr() };
+    self.next = current_ref.link().next();
+    List::remove(current_ref);
+    Some(current_ref)
+  }
+}
+
 impl<T> Drop for Link<T>
 where
   T: HasLink,
@@ -285,6 +374,8 @@ pub mod prelude {
     Link,
     List,
     ListDrainer,
+    ListError,
     ListIter,
+    ListTaker,
   };
 }
@@ -109,3 +109,108 @@ fn test_drainer() {
   assert!(node3.link().next().is_none());
   assert!(node3.link().prev().is_none());
 }
+
+fn chain(nodes: &mut [TestNode]) {
+  for i in 1..nodes.len() {
+    let (done, rest) = nodes.split_at_mut(i);
+    List::insert_after(&mut rest[0], &mut done[i - 1]);
+  }
+}
+
+#[test]
+fn test_validate_counts_a_sound_list() {
+  let mut nodes: Vec<_> = (0..5).map(TestNode::new).collect();
+  chain(&mut nodes);
+
+  assert_eq!(List::validate(Some(NonNull::from(&nodes[0]))), Ok(5));
+  assert_eq!(List::validate(Some(NonNull::from(&nodes[2]))), Ok(3));
+  assert_eq!(List::validate::<TestNode>(None), Ok(0));
+}
+
+#[test]
+fn test_validate_finds_a_cycle() {
+  let mut nodes: Vec<_> = (0..5).map(TestNode::new).collect();
+  chain(&mut nodes);
+  // Back links stay consistent all the way round, so only the cycle check can stop it.
+  let (first, last) = (NonNull::from(&nodes[0]), NonNull::from(&nodes[4]));
+  nodes[4].link().set_next(Some(first));
+  nodes[0].link().set_prev(Some(last));
+
+  assert_eq!(
+    List::validate(Some(NonNull::from(&nodes[0]))),
+    Err(ListError::Cycle)
+  );
+}
+
+#[test]
+fn test_validate_finds_a_broken_back_link() {
+  let mut nodes: Vec<_> = (0..3).map(TestNode::new).collect();
+  chain(&mut nodes);
+  nodes[2].link().set_prev(None);
+
+  assert_eq!(
+    List::validate(Some(NonNull::from(&nodes[0]))),
+    Err(ListError::BrokenLink)
+  );
+}
+
+mod take {
+  use core::sync::atomic::{
+    AtomicUsize,
+    Ordering,
+  };
+
+  use super::*;
+
+  static DROPS: AtomicUsize = AtomicUsize::new(0);
+
+  struct Counted {
+    value: i32,
+    link: Link<Self>,
+  }
+
+  impl Drop for Counted {
+    fn drop(&mut self) {
+      DROPS.fetch_add(1, Ordering::Relaxed);
+    }
+  }
+
+  impl HasLink for Counted {
+    fn link(&self) -> &Link<Self> {
+      &self.link
+    }
+
+    fn link_mut(&mut self) -> &mut Link<Self> {
+      &mut self.link
+    }
+  }
+
+  fn counted(value: i32) -> Counted {
+    Counted {
+      value,
+      link: Link::default(),
+    }
+  }
+
+  #[test]
+  fn test_taker_moves_nodes_without_dropping() {
+    let mut nodes: Vec<_> = (1..=4).map(counted).collect();
+    let mut target = counted(0);
+    for i in 1..nodes.len() {
+      let (done, rest) = nodes.split_at_mut(i);
+      List::insert_after(&mut rest[0], &mut done[i - 1]);
+    }
+
+    // Splice 1..=3 after `target`; 4 is never reached, so the taker leaves it alone.
+    let mut at = NonNull::from(&mut target);
+    for node in List::take(&mut nodes[0]).take(3) {
+      List::insert_after(node, unsafe { at.as_mut() });
+      at = NonNull::from(node);
+    }
+
+    assert_eq!(DROPS.load(Ordering::Relaxed), 0);
+    let values: Vec<i32> = ListIter::from(&target).map(|n| n.value).collect();
+    assert_eq!(values, [0, 1, 2, 3]);
+    assert!(nodes[3].link().prev().is_none() && nodes[3].link().next().is_none());
+  }
+}
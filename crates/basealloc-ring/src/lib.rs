@@ -1,8 +1,15 @@
 #![cfg_attr(not(test), no_std)]
 
-use core::sync::atomic::{
-  AtomicUsize,
-  Ordering,
+use core::{
+  cell::{
+    Cell,
+    UnsafeCell,
+  },
+  mem::MaybeUninit,
+  sync::atomic::{
+    AtomicUsize,
+    Ordering,
+  },
 };
 
 #[derive(Debug, PartialEq, Eq)]
@@ -43,6 +50,11 @@ impl Ring {
     self.len() == buf.len()
   }
 
+  /// Free slots left in `buf`.
+  pub fn remaining<T>(&self, buf: &[T]) -> usize {
+    buf.len() - self.len()
+  }
+
   fn next_idx(current: usize, capacity: usize) -> usize {
     (current + 1) % capacity
   }
@@ -78,5 +90,178 @@ impl Ring {
   }
 }
 
+/// `Ring` for a single thread: plain cells instead of atomics.
+///
+/// Same FIFO behaviour over a caller-provided buffer, for rings that never leave their
+/// thread, like the tcache bins. Not `Sync`.
+pub struct UnsyncRing {
+  head: Cell<usize>,
+  tail: Cell<usize>,
+  len: Cell<usize>,
+}
+
+impl Default for UnsyncRing {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl UnsyncRing {
+  pub const fn new() -> Self {
+    Self {
+      head: Cell::new(0),
+      tail: Cell::new(0),
+      len: Cell::new(0),
+    }
+  }
+
+  pub fn len(&self) -> usize {
+    self.len.get()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.len() == 0
+  }
+
+  pub fn is_full<T>(&self, buf: &[T]) -> bool {
+    self.len() == buf.len()
+  }
+
+  /// Free slots left in `buf`.
+  pub fn remaining<T>(&self, buf: &[T]) -> usize {
+    buf.len() - self.len()
+  }
+
+  pub fn push<T>(&self, buf: &mut [T], val: T) -> Result<(), RingError<T>> {
+    if self.is_full(buf) {
+      return Err(RingError::Full(val));
+    }
+
+    let head = self.head.get();
+    buf[head] = val;
+    self.head.set(Ring::next_idx(head, buf.len()));
+    self.len.set(self.len.get() + 1);
+
+    Ok(())
+  }
+
+  pub fn pop<'a, T>(&self, buf: &'a [T]) -> Option<&'a T> {
+    if self.is_empty() {
+      return None;
+    }
+
+    let tail = self.tail.get();
+    let val = &buf[tail];
+    self.tail.set(Ring::next_idx(tail, buf.len()));
+    self.len.set(self.len.get() - 1);
+
+    Some(val)
+  }
+}
+
+struct Slot<T> {
+  // Equals the position a producer may claim, or that position + 1 once it is filled.
+  seq: AtomicUsize,
+  val: UnsafeCell<MaybeUninit<T>>,
+}
+
+/// A bounded queue any number of threads may push into while a single thread pops.
+pub struct MpscRing<T, const N: usize> {
+  slots: [Slot<T>; N],
+  head: AtomicUsize,
+  tail: AtomicUsize,
+}
+
+impl<T, const N: usize> Default for MpscRing<T, N> {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl<T, const N: usize> MpscRing<T, N> {
+  pub fn new() -> Self {
+    const { assert!(N > 0) };
+    Self {
+      slots: core::array::from_fn(|i| Slot {
+        seq: AtomicUsize::new(i),
+        val: UnsafeCell::new(MaybeUninit::uninit()),
+      }),
+      head: AtomicUsize::new(0),
+      tail: AtomicUsize::new(0),
+    }
+  }
+
+  /// Approximate while producers are active.
+  pub fn len(&self) -> usize {
+    let tail = self.tail.load(Ordering::Acquire);
+    let head = self.head.load(Ordering::Acquire);
+    head.wrapping_sub(tail).min(N)
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.len() == 0
+  }
+
+  pub fn push(&self, val: T) -> Result<(), RingError<T>> {
+    let mut pos = self.head.load(Ordering::Relaxed);
+    loop {
+      let slot = &self.slots[pos % N];
+      let lag = slot.seq.load(Ordering::Acquire).wrapping_sub(pos) as isize;
+
+      if lag < 0 {
+        return Err(RingError::Full(val));
+      }
+      if lag > 0 {
+        pos = self.head.load(Ordering::Relaxed);
+        continue;
+      }
+
+      match self.head.compare_exchange_weak(
+        pos,
+        pos.wrapping_add(1),
+        Ordering::Relaxed,
+        Ordering::Relaxed,
+      ) {
+        Ok(_) => {
+          Self::fill(slot, pos, val);
+          return Ok(());
+        }
+        Err(current) => pos = current,
+      }
+    }
+  }
+
+  fn fill(slot: &Slot<T>, pos: usize, val: T) {
+    unsafe { (*slot.val.get()).write(val) };
+    slot.seq.store(pos.wrapping_add(1), Ordering::Release);
+  }
+
+  /// # Safety
+  ///
+  /// Only one thread may pop at a time.
+  pub unsafe fn pop(&self) -> Option<T> {
+    let pos = self.tail.load(Ordering::Relaxed);
+    let slot = &self.slots[pos % N];
+    if slot.seq.load(Ordering::Acquire) != pos.wrapping_add(1) {
+      return None;
+    }
+
+    let val = unsafe { (*slot.val.get()).assume_init_read() };
+    slot.seq.store(pos.wrapping_add(N), Ordering::Release);
+    self.tail.store(pos.wrapping_add(1), Ordering::Release);
+    Some(val)
+  }
+}
+
+impl<T, const N: usize> Drop for MpscRing<T, N> {
+  fn drop(&mut self) {
+    // SAFETY: `&mut self` rules out any other consumer.
+    while unsafe { self.pop() }.is_some() {}
+  }
+}
+
+unsafe impl<T: Send, const N: usize> Send for MpscRing<T, N> {}
+unsafe impl<T: Send, const N: usize> Sync for MpscRing<T, N> {}
+
 #[cfg(test)]
 mod tests;
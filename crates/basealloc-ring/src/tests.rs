@@ -114,6 +114,23 @@ fn interleaved_push_pop() {
   assert_eq!(ring.len(), 2);
 }
 
+#[test]
+fn remaining_tracks_len() {
+  let ring = Ring::new();
+  let mut buf = [0u32; 4];
+  assert_eq!(ring.remaining(&buf), 4);
+
+  for op in [true, true, false, true, true, true, false, false] {
+    if op {
+      let _ = ring.push(&mut buf, 7);
+    } else {
+      ring.pop(&buf);
+    }
+    assert_eq!(ring.remaining(&buf) + ring.len(), buf.len());
+  }
+  assert_eq!(ring.remaining(&buf), 2);
+}
+
 #[test]
 fn works_with_pointers() {
   let ring = Ring::new();
@@ -150,3 +167,81 @@ fn stress_test_large_capacity() {
 
   assert!(ring.is_empty());
 }
+
+#[test]
+fn mpsc_reports_full_and_drains_in_order() {
+  let ring: MpscRing<u32, 3> = MpscRing::new();
+  for i in 0..3 {
+    assert!(ring.push(i).is_ok());
+  }
+  assert_eq!(ring.push(9), Err(RingError::Full(9)));
+  assert_eq!(ring.len(), 3);
+
+  assert_eq!(unsafe { ring.pop() }, Some(0));
+  assert!(ring.push(3).is_ok());
+  let drained: Vec<_> = core::iter::from_fn(|| unsafe { ring.pop() }).collect();
+  assert_eq!(drained, [1, 2, 3]);
+  assert!(ring.is_empty());
+}
+
+#[test]
+fn mpsc_delivers_every_item_once() {
+  const PRODUCERS: usize = 4;
+  const PER_PRODUCER: usize = 20_000;
+  const TOTAL: usize = PRODUCERS * PER_PRODUCER;
+
+  let ring: MpscRing<usize, 64> = MpscRing::new();
+  let mut seen = vec![false; TOTAL];
+
+  std::thread::scope(|scope| {
+    for p in 0..PRODUCERS {
+      let ring = &ring;
+      scope.spawn(move || {
+        for i in 0..PER_PRODUCER {
+          let mut val = p * PER_PRODUCER + i;
+          while let Err(RingError::Full(back)) = ring.push(val) {
+            val = back;
+            std::thread::yield_now();
+          }
+        }
+      });
+    }
+
+    let mut popped = 0;
+    while popped < TOTAL {
+      match unsafe { ring.pop() } {
+        Some(val) => {
+          assert!(!seen[val], "{} popped twice", val);
+          seen[val] = true;
+          popped += 1;
+        }
+        None => std::thread::yield_now(),
+      }
+    }
+  });
+
+  assert!(seen.iter().all(|s| *s));
+  assert!(unsafe { ring.pop() }.is_none());
+}
+
+#[test]
+fn unsync_matches_atomic_ring() {
+  let (ring, unsync) = (Ring::new(), UnsyncRing::new());
+  let (mut buf, mut unsync_buf) = ([0u32; 5], [0u32; 5]);
+
+  // A fixed LCG mixes pushes and pops, wrapping both rings and hitting full and empty.
+  let mut state = 7u32;
+  for step in 0..1000 {
+    state = state.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+    if (state >> 16).is_multiple_of(3) {
+      assert_eq!(ring.pop(&buf).copied(), unsync.pop(&unsync_buf).copied());
+    } else {
+      assert_eq!(
+        ring.push(&mut buf, step),
+        unsync.push(&mut unsync_buf, step)
+      );
+    }
+    assert_eq!(ring.len(), unsync.len());
+    assert_eq!(ring.is_full(&buf), unsync.is_full(&unsync_buf));
+  }
+}
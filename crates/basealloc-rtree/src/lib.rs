@@ -1,8 +1,11 @@
 #![cfg_attr(not(test), no_std)]
 
 use core::{
+  cell::UnsafeCell,
+  mem::MaybeUninit,
   ptr::NonNull,
   sync::atomic::{
+    AtomicBool,
     AtomicPtr,
     Ordering,
   },
@@ -12,7 +15,11 @@ use basealloc_fixed::bump::{
   Bump,
   BumpError,
 };
-use basealloc_sys::prim::va_size;
+use basealloc_sys::{
+  prim::va_size,
+  system::System,
+};
+use spin::Mutex;
 
 #[derive(Debug)]
 pub enum RTreeError {
@@ -22,21 +29,63 @@ pub enum RTreeError {
 
 pub type RTreeResult<T> = Result<T, RTreeError>;
 
+#[cfg(test)]
+std::thread_local! {
+  // Root-to-node walks made by this thread, so tests can compare access patterns.
+  static DESCENTS: core::cell::Cell<usize> = const { core::cell::Cell::new(0) };
+}
+
+#[inline(always)]
+fn count_descent() {
+  #[cfg(test)]
+  DESCENTS.with(|descents| descents.set(descents.get() + 1));
+}
+
 struct RNode<T, const FANOUT: usize> {
-  value: Option<T>,
+  value: UnsafeCell<MaybeUninit<T>>,
+  // Published after `value` is written; readers only touch `value` once they see it.
+  present: AtomicBool,
   children: [AtomicPtr<RNode<T, FANOUT>>; FANOUT],
   parent: AtomicPtr<RNode<T, FANOUT>>,
 }
 
 impl<T, const FANOUT: usize> RNode<T, FANOUT> {
-  pub fn new(value: Option<T>) -> Self {
+  pub fn new() -> Self {
     Self {
-      value,
+      value: UnsafeCell::new(MaybeUninit::uninit()),
+      present: AtomicBool::new(false),
       children: core::array::from_fn(|_| AtomicPtr::new(core::ptr::null_mut())),
       parent: AtomicPtr::new(core::ptr::null_mut()),
     }
   }
 
+  fn get(&self) -> Option<&T> {
+    let present = self.present.load(Ordering::Acquire);
+    present.then(|| unsafe { (*self.value.get()).assume_init_ref() })
+  }
+
+  fn get_mut(&mut self) -> Option<&mut T> {
+    let present = *self.present.get_mut();
+    present.then(|| unsafe { self.value.get_mut().assume_init_mut() })
+  }
+
+  /// Writer only; the tree's writer lock must be held.
+  fn put(&self, val: T) -> RTreeResult<()> {
+    if self.present.load(Ordering::Acquire) {
+      return Err(RTreeError::AlreadyPresent);
+    }
+    unsafe { (*self.value.get()).write(val) };
+    self.present.store(true, Ordering::Release);
+    Ok(())
+  }
+
+  /// Writer only. Readers that saw the value keep reading intact bytes; only a
+  /// later `put` overwrites them.
+  fn take(&self) -> Option<T> {
+    let present = self.present.swap(false, Ordering::AcqRel);
+    present.then(|| unsafe { (*self.value.get()).assume_init_read() })
+  }
+
   #[inline(always)]
   fn load_child(&self, idx: usize) -> Option<NonNull<RNode<T, FANOUT>>> {
     let raw = self.children[idx].load(Ordering::Acquire);
@@ -55,7 +104,7 @@ impl<T, const FANOUT: usize> RNode<T, FANOUT> {
       .is_ok()
   }
 
-  fn clear_child(&mut self, target: NonNull<RNode<T, FANOUT>>) -> bool {
+  fn clear_child(&self, target: NonNull<RNode<T, FANOUT>>) -> bool {
     for child in self.children.iter() {
       loop {
         let current_ptr = child.load(Ordering::Acquire);
@@ -79,8 +128,15 @@ impl<T, const FANOUT: usize> RNode<T, FANOUT> {
   }
 }
 
+/// A radix tree with lock-free lookups.
+///
+/// Lookups only load atomics and never block. Writers serialize on the node bump's
+/// lock, and nodes are never unmapped before the tree is dropped. A reference from
+/// `lookup` stays intact until its key is inserted again, so callers must not hold one
+/// across a remove and re-insert of the same key.
 pub struct RTree<T, const FANOUT: usize> {
-  bump: Bump,
+  // Doubles as the writer lock.
+  bump: Mutex<Bump>,
   root: AtomicPtr<RNode<T, FANOUT>>,
 }
 
@@ -90,7 +146,15 @@ impl<T, const FANOUT: usize> RTree<T, FANOUT> {
 
   pub const fn new(chunk_size: usize) -> Self {
     Self {
-      bump: Bump::new(chunk_size),
+      bump: Mutex::new(Bump::new(chunk_size)),
+      root: AtomicPtr::new(core::ptr::null_mut()),
+    }
+  }
+
+  /// A tree whose nodes are always mapped through `system`.
+  pub const fn new_in(chunk_size: usize, system: &'static dyn System) -> Self {
+    Self {
+      bump: Mutex::new(Bump::new_in(chunk_size, system)),
       root: AtomicPtr::new(core::ptr::null_mut()),
     }
   }
@@ -99,16 +163,8 @@ impl<T, const FANOUT: usize> RTree<T, FANOUT> {
     va_size().div_ceil(Self::BPL)
   }
 
-  fn new_node(&mut self, value: Option<T>) -> RTreeResult<NonNull<RNode<T, FANOUT>>> {
-    let node = self
-      .bump
-      .create::<RNode<T, FANOUT>>()
-      .map_err(RTreeError::Bump)?;
-
-    let tmp = RNode::new(value);
-    unsafe { (*node).write(tmp) };
-    // SAFETY: `Bump::create` never returns a null pointer on success.
-    Ok(unsafe { NonNull::new_unchecked((*node).as_mut_ptr()) })
+  fn new_node(bump: &mut Bump) -> RTreeResult<NonNull<RNode<T, FANOUT>>> {
+    bump.create_with(RNode::new()).map_err(RTreeError::Bump)
   }
 
   #[inline(always)]
@@ -117,13 +173,13 @@ impl<T, const FANOUT: usize> RTree<T, FANOUT> {
     (key >> shift) & Self::MASK
   }
 
-  fn ensure_root(&mut self) -> RTreeResult<NonNull<RNode<T, FANOUT>>> {
+  fn ensure_root(&self, bump: &mut Bump) -> RTreeResult<NonNull<RNode<T, FANOUT>>> {
     let current = self.root.load(Ordering::Acquire);
     if let Some(root) = NonNull::new(current) {
       return Ok(root);
     }
 
-    let new_root = self.new_node(None)?;
+    let new_root = Self::new_node(bump)?;
 
     match self.root.compare_exchange_weak(
       core::ptr::null_mut(),
@@ -136,47 +192,109 @@ impl<T, const FANOUT: usize> RTree<T, FANOUT> {
     }
   }
 
-  fn store(mut node: NonNull<RNode<T, FANOUT>>, val: T) -> RTreeResult<()> {
-    let n = unsafe { node.as_mut() };
-    if n.value.is_some() {
-      Err(RTreeError::AlreadyPresent)
-    } else {
-      n.value = Some(val);
-      Ok(())
-    }
-  }
-
-  pub fn insert(&mut self, key: usize, val: T) -> RTreeResult<()> {
-    let leaf = self.ensure_leaf(key)?;
-    Self::store(leaf, val)
+  pub fn insert(&self, key: usize, val: T) -> RTreeResult<()> {
+    let mut bump = self.bump.lock();
+    let leaf = self.ensure_leaf(&mut bump, key)?;
+    unsafe { leaf.as_ref() }.put(val)
   }
 
+  /// Safe to call concurrently with writers; see the type docs.
   pub fn lookup(&self, key: usize) -> Option<&T> {
     let node = self.leaf(key)?;
-    let node_ref = unsafe { node.as_ref() };
-    node_ref.value.as_ref()
+    unsafe { node.as_ref() }.get()
   }
 
   pub fn lookup_mut(&mut self, key: usize) -> Option<&mut T> {
     let mut node = self.leaf(key)?;
-    let node_mut = unsafe { node.as_mut() };
-    node_mut.value.as_mut()
+    unsafe { node.as_mut() }.get_mut()
   }
 
-  pub fn remove(&mut self, key: usize) -> Option<T> {
-    let mut node = self.leaf(key)?;
-    let node_mut = unsafe { node.as_mut() };
-    let val = node_mut.value.take();
+  pub fn remove(&self, key: usize) -> Option<T> {
+    let _writer = self.bump.lock();
+    let node = self.leaf(key)?;
+    let val = unsafe { node.as_ref() }.take();
     self.prune(node);
     val
   }
 
+  /// Removes every key in `first..=last` under one writer lock and returns how many
+  /// held a value.
+  ///
+  /// Descends once per leaf parent rather than once per key, so a run of `FANOUT`
+  /// consecutive keys costs a single walk, and prunes each parent once.
+  pub fn remove_range(&self, first: usize, last: usize) -> usize {
+    let _writer = self.bump.lock();
+    let mut removed = 0;
+    let mut key = first;
+    while key <= last {
+      // Keys differing only in their last index hang off the same parent.
+      let group_last = (key | Self::MASK).min(last);
+      if let Some(parent) = self.parent_of(key) {
+        removed += self.take_leaves(parent, key, group_last);
+      }
+      let Some(next) = group_last.checked_add(1) else {
+        break;
+      };
+      key = next;
+    }
+    removed
+  }
+
+  /// Writer only. Empties the leaves for `first..=last` below `parent`.
+  fn take_leaves(&self, parent: NonNull<RNode<T, FANOUT>>, first: usize, last: usize) -> usize {
+    let parent_ref = unsafe { parent.as_ref() };
+    let mut removed = 0;
+    for key in first..=last {
+      let Some(leaf) = parent_ref.load_child(key & Self::MASK) else {
+        continue;
+      };
+      removed += usize::from(unsafe { leaf.as_ref() }.take().is_some());
+      if self.should_remove_node(leaf) {
+        parent_ref.clear_child(leaf);
+      }
+    }
+    self.prune(parent);
+    removed
+  }
+
+  /// Returns the first stored value matching `pred`, walking keys in ascending order.
+  pub fn find<P>(&self, mut pred: P) -> Option<&T>
+  where
+    P: FnMut(&T) -> bool,
+  {
+    let root = NonNull::new(self.root.load(Ordering::Acquire))?;
+    self.find_in(root, &mut pred)
+  }
+
+  fn find_in<P>(&self, node: NonNull<RNode<T, FANOUT>>, pred: &mut P) -> Option<&T>
+  where
+    P: FnMut(&T) -> bool,
+  {
+    let n = unsafe { node.as_ref() };
+    if let Some(val) = n.get().filter(|val| pred(val)) {
+      return Some(val);
+    }
+
+    n.children
+      .iter()
+      .filter_map(|child| NonNull::new(child.load(Ordering::Acquire)))
+      .find_map(|child| self.find_in(child, pred))
+  }
+
   fn leaf(&self, key: usize) -> Option<NonNull<RNode<T, FANOUT>>> {
+    self.descend(key, Self::levels())
+  }
+
+  fn parent_of(&self, key: usize) -> Option<NonNull<RNode<T, FANOUT>>> {
+    self.descend(key, Self::levels() - 1)
+  }
+
+  fn descend(&self, key: usize, depth: usize) -> Option<NonNull<RNode<T, FANOUT>>> {
+    count_descent();
     let root_ptr = self.root.load(Ordering::Acquire);
     let mut current = NonNull::new(root_ptr)?;
 
-    let levels = Self::levels();
-    for level in 0..levels {
+    for level in 0..depth {
       let idx = Self::index_for(key, level);
       current = unsafe { current.as_ref().load_child(idx)? };
     }
@@ -184,20 +302,20 @@ impl<T, const FANOUT: usize> RTree<T, FANOUT> {
     Some(current)
   }
 
-  fn ensure_leaf(&mut self, key: usize) -> RTreeResult<NonNull<RNode<T, FANOUT>>> {
-    let mut current = self.ensure_root()?;
+  fn ensure_leaf(&self, bump: &mut Bump, key: usize) -> RTreeResult<NonNull<RNode<T, FANOUT>>> {
+    let mut current = self.ensure_root(bump)?;
     let levels = Self::levels();
 
     for level in 0..levels {
       let idx = Self::index_for(key, level);
-      current = self.ensure_child(current, idx)?;
+      current = Self::ensure_child(bump, current, idx)?;
     }
 
     Ok(current)
   }
 
   fn ensure_child(
-    &mut self,
+    bump: &mut Bump,
     parent: NonNull<RNode<T, FANOUT>>,
     idx: usize,
   ) -> RTreeResult<NonNull<RNode<T, FANOUT>>> {
@@ -207,7 +325,7 @@ impl<T, const FANOUT: usize> RTree<T, FANOUT> {
         return Ok(child);
       }
 
-      let mut new_child = self.new_node(None)?;
+      let mut new_child = Self::new_node(bump)?;
       unsafe {
         new_child
           .as_mut()
@@ -221,7 +339,7 @@ impl<T, const FANOUT: usize> RTree<T, FANOUT> {
     }
   }
 
-  fn prune(&mut self, mut node: NonNull<RNode<T, FANOUT>>) {
+  fn prune(&self, mut node: NonNull<RNode<T, FANOUT>>) {
     loop {
       let should_remove = self.should_remove_node(node);
       if !should_remove {
@@ -233,9 +351,8 @@ impl<T, const FANOUT: usize> RTree<T, FANOUT> {
         self.root.store(core::ptr::null_mut(), Ordering::Release);
         break;
       } else {
-        let mut parent_node = unsafe { NonNull::new_unchecked(parent_ptr) };
-        let parent_node_mut = unsafe { parent_node.as_mut() };
-        let _ = parent_node_mut.clear_child(node);
+        let parent_node = unsafe { NonNull::new_unchecked(parent_ptr) };
+        let _ = unsafe { parent_node.as_ref() }.clear_child(node);
         node = parent_node;
       }
     }
@@ -243,7 +360,7 @@ impl<T, const FANOUT: usize> RTree<T, FANOUT> {
 
   fn should_remove_node(&self, node: NonNull<RNode<T, FANOUT>>) -> bool {
     let n = unsafe { node.as_ref() };
-    n.value.is_none()
+    !n.present.load(Ordering::Acquire)
       && n
         .children
         .iter()
@@ -252,7 +369,8 @@ impl<T, const FANOUT: usize> RTree<T, FANOUT> {
 }
 
 unsafe impl<T: Send, const FANOUT: usize> Send for RTree<T, FANOUT> {}
-unsafe impl<T: Sync, const FANOUT: usize> Sync for RTree<T, FANOUT> {}
+// Shared inserts move values in from other threads, so `Sync` needs `Send` too.
+unsafe impl<T: Send + Sync, const FANOUT: usize> Sync for RTree<T, FANOUT> {}
 
 #[cfg(test)]
 mod tests;
@@ -1,4 +1,5 @@
 use super::{
+  DESCENTS,
   RTree,
   RTreeError,
 };
@@ -8,7 +9,7 @@ const FANOUT: usize = 1 << 4;
 
 #[test]
 fn insert_and_lookup_round_trip() {
-  let mut tree: RTree<usize, FANOUT> = RTree::new(CHUNK);
+  let tree: RTree<usize, FANOUT> = RTree::new(CHUNK);
 
   tree.insert(0x1234, 42).expect("insert should succeed");
   assert_eq!(tree.lookup(0x1234), Some(&42));
@@ -20,7 +21,7 @@ fn insert_and_lookup_round_trip() {
 
 #[test]
 fn duplicate_insert_fails() {
-  let mut tree: RTree<usize, FANOUT> = RTree::new(CHUNK);
+  let tree: RTree<usize, FANOUT> = RTree::new(CHUNK);
   tree.insert(0xDEAD, 1).expect("first insert succeeds");
   let err = tree.insert(0xDEAD, 2).expect_err("duplicate should fail");
   assert!(matches!(err, RTreeError::AlreadyPresent));
@@ -31,7 +32,7 @@ fn duplicate_insert_fails() {
 
 #[test]
 fn remove_prunes_empty_path() {
-  let mut tree: RTree<usize, FANOUT> = RTree::new(CHUNK);
+  let tree: RTree<usize, FANOUT> = RTree::new(CHUNK);
   let key = 0xABCD;
   tree.insert(key, 77).expect("insert");
   let removed = tree.remove(key);
@@ -41,7 +42,7 @@ fn remove_prunes_empty_path() {
 
 #[test]
 fn multiple_entries_share_prefix() {
-  let mut tree: RTree<usize, FANOUT> = RTree::new(CHUNK);
+  let tree: RTree<usize, FANOUT> = RTree::new(CHUNK);
   let base = 0x12340000usize;
   let keys = [base, base + 1, base + FANOUT];
 
@@ -69,3 +70,64 @@ fn lookup_mut_allows_updates() {
   assert_eq!(tree.lookup(key), Some(&99));
   assert_eq!(tree.remove(key), Some(99));
 }
+
+#[test]
+fn find_walks_in_key_order() {
+  let tree: RTree<usize, FANOUT> = RTree::new(CHUNK);
+  for key in [0x3000, 0x1000, 0x2000] {
+    tree.insert(key, key + 1).expect("insert");
+  }
+
+  assert_eq!(tree.find(|_| true), Some(&0x1001));
+  assert_eq!(tree.find(|v| *v > 0x1001), Some(&0x2001));
+  assert_eq!(tree.find(|v| *v == 7), None);
+}
+
+#[test]
+fn lookups_race_inserts_without_tearing() {
+  const KEYS: usize = 4096;
+  let tree: RTree<(usize, usize), FANOUT> = RTree::new(CHUNK);
+
+  std::thread::scope(|scope| {
+    let readers: Vec<_> = (0..4)
+      .map(|_| {
+        scope.spawn(|| {
+          let mut seen = 0;
+          while seen < KEYS {
+            seen = (0..KEYS)
+              .filter_map(|key| tree.lookup(key << 12))
+              .inspect(|(key, check)| assert_eq!(*check, !*key, "torn value"))
+              .count();
+          }
+        })
+      })
+      .collect();
+
+    for key in 0..KEYS {
+      tree.insert(key << 12, (key, !key)).expect("insert");
+    }
+    readers.into_iter().for_each(|r| r.join().unwrap());
+  });
+}
+
+#[test]
+fn remove_range_walks_once_per_parent() {
+  const KEYS: usize = 8 * FANOUT;
+  let first = 3 * FANOUT + 5;
+  let tree: RTree<usize, FANOUT> = RTree::new(CHUNK);
+  for key in first - 2..first + KEYS + 2 {
+    tree.insert(key, key).expect("insert");
+  }
+
+  let before = DESCENTS.with(|descents| descents.get());
+  assert_eq!(tree.remove_range(first, first + KEYS - 1), KEYS);
+  let walks = DESCENTS.with(|descents| descents.get()) - before;
+  // The range straddles nine parents; removing key by key would walk `KEYS` times.
+  assert_eq!(walks, KEYS / FANOUT + 1);
+
+  assert!((first..first + KEYS).all(|key| tree.lookup(key).is_none()));
+  for key in (first - 2..first).chain(first + KEYS..first + KEYS + 2) {
+    assert_eq!(tree.lookup(key), Some(&key));
+  }
+  assert_eq!(tree.remove_range(first, first + KEYS - 1), 0);
+}
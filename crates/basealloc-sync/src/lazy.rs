@@ -44,6 +44,15 @@ where
     data.value.as_ref().unwrap()
   }
 
+  /// The value if it was already built; never runs the initializer.
+  pub fn get(this: &LazyLock<T, F>) -> Option<&T> {
+    if !this.once.is_completed() {
+      return None;
+    }
+    let data = unsafe { &*this.data.get() };
+    data.value.as_ref()
+  }
+
   pub fn force_mut(this: &mut LazyLock<T, F>) -> &mut T {
     this.once.call_once(|| {
       let data = unsafe { &mut *this.data.get() };
@@ -1,4 +1,7 @@
 #![cfg_attr(not(test), no_std)]
 
 pub mod lazy;
-pub mod local;
\ No newline at end of file
+pub mod local;
+
+#[cfg(test)]
+pub mod tests;
@@ -11,8 +11,13 @@ use basealloc_sys::{
 
 use crate::lazy::LazyLock;
 
+/// A per-thread `T`, each in its own page-rounded mapping.
+///
+/// The key's destructor drops the value and unmaps it when its thread exits, so thread
+/// churn doesn't accumulate memory.
 pub struct ThreadLocal<T, F = fn() -> T> {
-  key: LazyLock<libc::pthread_key_t>,
+  // `None` once key creation failed (e.g. `PTHREAD_KEYS_MAX` exhausted).
+  key: LazyLock<Option<libc::pthread_key_t>>,
   init: F,
   _marker: PhantomData<T>,
 }
@@ -30,17 +35,14 @@ unsafe extern "C" fn tls_detor<T>(ptr: *mut libc::c_void) {
   let t_slice = unsafe { core::slice::from_raw_parts_mut(ptr as *mut u8, pga_size) };
 
   unsafe {
-    GLOBAL_SYSTEM.dealloc(&t_slice).unwrap();
+    GLOBAL_SYSTEM.dealloc(t_slice).unwrap();
   }
 }
 
-fn obtain_key<T>() -> libc::pthread_key_t {
+fn obtain_key<T>() -> Option<libc::pthread_key_t> {
   let mut key: libc::pthread_key_t = 0;
   let ret = unsafe { libc::pthread_key_create(&mut key, Some(tls_detor::<T>)) };
-  if ret != 0 {
-    panic!("Failed to create pthread key: {}", ret);
-  }
-  key
+  (ret == 0).then_some(key)
 }
 
 impl<T, F> ThreadLocal<T, F>
@@ -55,36 +57,58 @@ where
     }
   }
 
-  fn get_or_init(&self) -> *mut T {
-    let key = *self.key;
+  /// A thread local whose key creation "failed", for exercising fallbacks.
+  #[cfg(test)]
+  pub(crate) const fn unkeyed(init: F) -> Self {
+    Self {
+      key: LazyLock::new(|| None),
+      init,
+      _marker: PhantomData,
+    }
+  }
+
+  /// Creates the key without initializing this thread's value.
+  ///
+  /// Returns `false` if no thread-local storage is available.
+  pub fn register(&self) -> bool {
+    self.key.is_some()
+  }
+
+  fn get_or_init(&self) -> Option<*mut T> {
+    let key = (*self.key)?;
     let ptr = unsafe { libc::pthread_getspecific(key) } as *mut T;
     if !ptr.is_null() {
-      return ptr;
+      return Some(ptr);
     }
 
     let t_layout = Layout::new::<T>();
-    let pga_size = page_align(t_layout.size()).unwrap();
-    let mem = unsafe { GLOBAL_SYSTEM.alloc(pga_size, SysOption::Commit).unwrap() };
+    let pga_size = page_align(t_layout.size()).ok()?;
+    let mem = unsafe { GLOBAL_SYSTEM.alloc(pga_size, SysOption::Commit) }.ok()?;
 
     let uninit = mem.as_ptr() as *mut T;
 
     unsafe { uninit.write((self.init)()) };
     let ret = unsafe { libc::pthread_setspecific(key, uninit.cast()) };
     if ret != 0 {
-      panic!("pthread_setspecific failed: {}", ret);
+      unsafe { tls_detor::<T>(uninit.cast()) };
+      return None;
     }
-    uninit
+    Some(uninit)
   }
 
-  pub fn with<R>(&self, f: impl FnOnce(&mut T) -> R) -> R {
-    let ptr = self.get_or_init();
-    f(unsafe { &mut *ptr })
+  /// Runs `f` on this thread's value, or returns `None` if no thread-local
+  /// storage is available so callers can take a shared fallback instead.
+  pub fn with<R>(&self, f: impl FnOnce(&mut T) -> R) -> Option<R> {
+    let ptr = self.get_or_init()?;
+    Some(f(unsafe { &mut *ptr }))
   }
 }
 
 impl<T, F> Drop for ThreadLocal<T, F> {
   fn drop(&mut self) {
-    let _ = unsafe { libc::pthread_key_delete(*self.key) };
+    if let Some(key) = *self.key {
+      let _ = unsafe { libc::pthread_key_delete(key) };
+    }
   }
 }
 
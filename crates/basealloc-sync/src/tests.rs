@@ -0,0 +1,62 @@
+use core::sync::atomic::{
+  AtomicUsize,
+  Ordering,
+};
+
+use crate::local::ThreadLocal;
+
+static INITS: AtomicUsize = AtomicUsize::new(0);
+
+fn counted() -> usize {
+  INITS.fetch_add(1, Ordering::Relaxed);
+  7
+}
+
+#[test]
+fn thread_local_is_per_thread() {
+  let local: ThreadLocal<usize> = ThreadLocal::new(|| 0);
+  assert_eq!(local.with(|v| *v += 1), Some(()));
+  assert_eq!(local.with(|v| *v), Some(1));
+
+  std::thread::scope(|scope| {
+    scope.spawn(|| assert_eq!(local.with(|v| *v), Some(0)));
+  });
+}
+
+#[test]
+fn thread_local_without_key_degrades() {
+  let local: ThreadLocal<usize> = ThreadLocal::unkeyed(counted);
+  assert_eq!(local.with(|v| *v), None);
+  assert_eq!(INITS.load(Ordering::Relaxed), 0);
+}
+
+#[test]
+fn register_creates_key_without_init() {
+  let local: ThreadLocal<usize> = ThreadLocal::new(|| panic!("register must not initialize"));
+  assert!(local.register());
+  assert!(!ThreadLocal::<usize>::unkeyed(counted).register());
+}
+
+static DROPS: AtomicUsize = AtomicUsize::new(0);
+
+struct Tracked;
+
+impl Drop for Tracked {
+  fn drop(&mut self) {
+    DROPS.fetch_add(1, Ordering::Relaxed);
+  }
+}
+
+static TRACKED: ThreadLocal<Tracked> = ThreadLocal::new(|| Tracked);
+
+#[test]
+fn exited_threads_give_their_slot_back() {
+  const THREADS: usize = 64;
+  // `join` waits for the key destructors too; a scope only waits for the closure.
+  for _ in 0..THREADS {
+    std::thread::spawn(|| TRACKED.with(|_| ()).unwrap())
+      .join()
+      .unwrap();
+  }
+  assert_eq!(DROPS.load(Ordering::Relaxed), THREADS);
+}
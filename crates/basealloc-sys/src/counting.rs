@@ -0,0 +1,103 @@
+use core::sync::atomic::{
+  AtomicUsize,
+  Ordering,
+};
+
+use crate::system::{
+  SysOption,
+  SysResult,
+  System,
+};
+
+/// Forwards to `inner` while counting calls, for tests that assert on mapping behaviour.
+pub struct CountingSystem {
+  inner: &'static dyn System,
+  allocs: AtomicUsize,
+  deallocs: AtomicUsize,
+  modifies: AtomicUsize,
+  bytes: AtomicUsize,
+}
+
+impl CountingSystem {
+  pub const fn new(inner: &'static dyn System) -> Self {
+    Self {
+      inner,
+      allocs: AtomicUsize::new(0),
+      deallocs: AtomicUsize::new(0),
+      modifies: AtomicUsize::new(0),
+      bytes: AtomicUsize::new(0),
+    }
+  }
+
+  pub fn allocs(&self) -> usize {
+    self.allocs.load(Ordering::Relaxed)
+  }
+
+  pub fn deallocs(&self) -> usize {
+    self.deallocs.load(Ordering::Relaxed)
+  }
+
+  pub fn modifies(&self) -> usize {
+    self.modifies.load(Ordering::Relaxed)
+  }
+
+  /// Total bytes requested through `alloc`.
+  pub fn bytes(&self) -> usize {
+    self.bytes.load(Ordering::Relaxed)
+  }
+}
+
+unsafe impl System for CountingSystem {
+  unsafe fn alloc<'mem>(&self, size: usize, options: SysOption) -> SysResult<&'mem mut [u8]> {
+    self.allocs.fetch_add(1, Ordering::Relaxed);
+    self.bytes.fetch_add(size, Ordering::Relaxed);
+    unsafe { self.inner.alloc(size, options) }
+  }
+
+  unsafe fn modify(&self, slice: &[u8], options: SysOption) -> SysResult<()> {
+    self.modifies.fetch_add(1, Ordering::Relaxed);
+    unsafe { self.inner.modify(slice, options) }
+  }
+
+  unsafe fn dealloc(&self, slice: &[u8]) -> SysResult<()> {
+    self.deallocs.fetch_add(1, Ordering::Relaxed);
+    unsafe { self.inner.dealloc(slice) }
+  }
+
+  fn committed(&self) -> usize {
+    self.inner.committed()
+  }
+
+  unsafe fn resident_pages(&self, slice: &[u8]) -> SysResult<usize> {
+    unsafe { self.inner.resident_pages(slice) }
+  }
+
+  unsafe fn grow(&self, slice: &[u8], extra: usize, options: SysOption) -> SysResult<()> {
+    unsafe { self.inner.grow(slice, extra, options) }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    GLOBAL_SYSTEM,
+    prim::page_size,
+  };
+
+  #[test]
+  #[cfg(any(target_os = "linux", target_os = "macos"))]
+  fn counts_alloc_and_dealloc() {
+    let counting = CountingSystem::new(GLOBAL_SYSTEM);
+    let size = 2 * page_size();
+
+    let slice = unsafe { counting.alloc(size, SysOption::Commit) }.unwrap();
+    unsafe { counting.modify(slice, SysOption::Reclaim) }.unwrap();
+    unsafe { counting.dealloc(slice) }.unwrap();
+
+    assert_eq!(counting.allocs(), 1);
+    assert_eq!(counting.deallocs(), 1);
+    assert_eq!(counting.modifies(), 1);
+    assert_eq!(counting.bytes(), size);
+  }
+}
@@ -0,0 +1,45 @@
+use core::sync::atomic::{
+  AtomicPtr,
+  Ordering,
+};
+
+/// Faults that can't be bubbled up to a caller (drop paths, thread exit, ...).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+  InvalidArena(usize),
+  WriteAfterFree(usize),
+  /// A bitmap was dropped with this many bits still set.
+  LeakedBits(usize),
+  /// The slot at this address had its trailing redzone overwritten.
+  RedzoneOverwrite(usize),
+  /// Unmapping the mapping at this address failed, e.g. a double unmap or wrong length.
+  UnmapFailed(usize),
+  /// A fresh `Commit` mapping at this address didn't read as zero.
+  DirtyCommit(usize),
+  /// The small object at this address had its header overwritten, or was freed with a
+  /// size or class it wasn't allocated with.
+  CookieMismatch(usize),
+  /// The small object at this address was freed while already free.
+  DoubleFree(usize),
+}
+
+pub type ErrorHook = fn(Fault);
+
+static HOOK: AtomicPtr<()> = AtomicPtr::new(core::ptr::null_mut());
+
+pub fn set_error_hook(hook: Option<ErrorHook>) {
+  let raw = hook.map_or(core::ptr::null_mut(), |h| h as *mut ());
+  HOOK.store(raw, Ordering::Release);
+}
+
+/// Forwards `fault` to the installed hook, if any. Must not allocate.
+pub fn report(fault: Fault) {
+  let raw = HOOK.load(Ordering::Acquire);
+  if raw.is_null() {
+    return;
+  }
+
+  // SAFETY: `HOOK` only ever holds null or a pointer stored from an `ErrorHook`.
+  let hook = unsafe { core::mem::transmute::<*mut (), ErrorHook>(raw) };
+  hook(fault);
+}
@@ -1,12 +1,19 @@
 #![cfg_attr(not(test), no_std)]
 
+#[cfg(any(test, feature = "test-utils"))]
+pub mod counting;
+pub mod hook;
 pub mod math;
 pub mod misc;
 pub mod prim;
 pub mod system;
 pub mod unix;
 
-pub use system::GLOBAL_SYSTEM;
+pub use system::{
+  GLOBAL_SYSTEM,
+  current_system,
+  set_system,
+};
 
 pub mod prelude {
   pub use super::{
@@ -33,6 +40,7 @@ pub mod prelude {
       SysOption,
       SysResult,
       System,
+      current_system,
     },
   };
 }
@@ -219,7 +219,7 @@ mod tests {
     let struct_ptr =
       place_struct_in_buffer::<TestStruct>(&mut buffer).expect("Buffer should fit aligned struct");
 
-    assert!((struct_ptr as usize) % core::mem::align_of::<TestStruct>() == 0);
+    assert!((struct_ptr as usize).is_multiple_of(core::mem::align_of::<TestStruct>()));
 
     unsafe {
       core::ptr::write(struct_ptr, TestStruct { a: 0x123, b: 0x456 });
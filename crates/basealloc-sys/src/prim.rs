@@ -72,6 +72,55 @@ pub fn is_page_aligned(value: usize) -> PrimResult<bool> {
   is_aligned(value, page_size()).ok_or(PrimError::InvalidAlignment)
 }
 
+/// Page-aligned addresses of every page the span `[base, base + len)` touches, lowest first.
+///
+/// `start` is the next page to yield and `end` the page boundary past the span.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PageRange {
+  pub start: usize,
+  pub end: usize,
+}
+
+impl PageRange {
+  /// `Overflow` if the span, rounded out to whole pages, runs past the address space.
+  pub fn new(base: usize, len: usize) -> PrimResult<Self> {
+    let start = page_align_down(base)?;
+    if len == 0 {
+      return Ok(Self { start, end: start });
+    }
+    let end = base.checked_add(len).ok_or(PrimError::Overflow)?;
+    Ok(Self {
+      start,
+      end: page_align(end)?,
+    })
+  }
+
+  /// The highest page still to be yielded.
+  pub fn last_page(&self) -> Option<usize> {
+    (self.start < self.end).then(|| self.end - page_size())
+  }
+}
+
+impl Iterator for PageRange {
+  type Item = usize;
+
+  fn next(&mut self) -> Option<usize> {
+    if self.start >= self.end {
+      return None;
+    }
+    let page = self.start;
+    self.start += page_size();
+    Some(page)
+  }
+
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    let pages = self.end.saturating_sub(self.start) / page_size();
+    (pages, Some(pages))
+  }
+}
+
+impl ExactSizeIterator for PageRange {}
+
 #[cfg(target_pointer_width = "64")]
 pub const fn va_size() -> usize {
   48
@@ -82,6 +131,17 @@ pub const fn va_size() -> usize {
   32
 }
 
+/// The CPU the calling thread is running on, or `None` where that can't be queried.
+#[cfg(target_os = "linux")]
+pub fn current_cpu() -> Option<usize> {
+  usize::try_from(unsafe { libc::sched_getcpu() }).ok()
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn current_cpu() -> Option<usize> {
+  None
+}
+
 #[inline(always)]
 #[cold]
 fn cold_path() {}
@@ -149,4 +209,46 @@ mod tests {
     assert_eq!(is_page_aligned(ps - 1), Ok(false));
     assert_eq!(is_page_aligned(ps * 2), Ok(true));
   }
+
+  #[test]
+  fn test_page_range_single_page() {
+    let ps = page_size();
+    let range = PageRange::new(3 * ps + 8, 16).unwrap();
+    assert_eq!(range.last_page(), Some(3 * ps));
+    assert_eq!(range.collect::<Vec<_>>(), [3 * ps]);
+  }
+
+  #[test]
+  fn test_page_range_multi_page() {
+    let ps = page_size();
+    // Straddles a boundary: the tail of one page and the head of two more.
+    let range = PageRange::new(2 * ps - 1, ps + 2).unwrap();
+    assert_eq!(range.len(), 3);
+    assert_eq!(range.last_page(), Some(3 * ps));
+    assert_eq!(range.collect::<Vec<_>>(), [ps, 2 * ps, 3 * ps]);
+
+    let exact = PageRange::new(4 * ps, 2 * ps).unwrap();
+    assert_eq!(exact.collect::<Vec<_>>(), [4 * ps, 5 * ps]);
+  }
+
+  #[test]
+  fn test_page_range_empty() {
+    let ps = page_size();
+    let mut range = PageRange::new(ps + 1, 0).unwrap();
+    assert_eq!(range.len(), 0);
+    assert_eq!(range.last_page(), None);
+    assert_eq!(range.next(), None);
+  }
+
+  #[test]
+  fn test_page_range_overflow_boundary() {
+    let ps = page_size();
+    let top = usize::MAX - ps + 1;
+    assert_eq!(PageRange::new(top, ps), Err(PrimError::Overflow));
+    assert_eq!(PageRange::new(top, 1), Err(PrimError::Overflow));
+    assert_eq!(PageRange::new(usize::MAX, 1), Err(PrimError::Overflow));
+
+    let below = PageRange::new(top - ps, ps).unwrap();
+    assert_eq!(below.collect::<Vec<_>>(), [top - ps]);
+  }
 }
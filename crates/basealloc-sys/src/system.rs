@@ -1,3 +1,8 @@
+use core::sync::atomic::{
+  AtomicPtr,
+  Ordering,
+};
+
 #[cfg(any(target_os = "linux", target_os = "macos"))]
 use crate::unix::UNIX_SYSTEM;
 
@@ -63,6 +68,39 @@ where
     _ = slice;
     Err(SysError::Unsupported)
   }
+
+  /// Bytes this system has committed and not yet reclaimed or unmapped; `0` if it
+  /// doesn't keep count.
+  ///
+  /// A running total of calls, not of page state: committing a range twice counts it
+  /// twice, and unmapping subtracts the whole mapping even if only part of it was
+  /// committed. It never drops below zero.
+  fn committed(&self) -> usize {
+    0
+  }
+
+  /// Counts how many pages of `slice` are currently resident.
+  ///
+  /// # Safety
+  ///
+  /// Caller must ensure `slice` was previously allocated by this system
+  /// and is still valid (not deallocated).
+  unsafe fn resident_pages(&self, slice: &[u8]) -> SysResult<usize> {
+    _ = slice;
+    Err(SysError::Unsupported)
+  }
+
+  /// Maps `extra` bytes directly after `slice`, with the same `options`, so the two
+  /// read as one mapping. Fails, mapping nothing, if any of those addresses is taken.
+  ///
+  /// # Safety
+  ///
+  /// Caller must ensure `slice` was previously allocated by this system
+  /// and is still valid (not deallocated).
+  unsafe fn grow(&self, slice: &[u8], extra: usize, options: SysOption) -> SysResult<()> {
+    _ = (slice, extra, options);
+    Err(SysError::Unsupported)
+  }
 }
 
 pub struct UnsupportedSystem {}
@@ -74,5 +112,23 @@ pub static GLOBAL_SYSTEM: &dyn System = &UNIX_SYSTEM;
 #[cfg(not(any(target_os = "linux", target_os = "macos")))]
 pub static GLOBAL_SYSTEM: &dyn System = &UnsupportedSystem {};
 
+// Holds a thin pointer to a `&'static dyn System` so it fits in one atomic.
+static CURRENT_SYSTEM: AtomicPtr<&'static dyn System> =
+  AtomicPtr::new(&GLOBAL_SYSTEM as *const &'static dyn System as *mut _);
+
+/// Replaces the backend returned by `current_system`.
+///
+/// Memory already handed out stays bound to the system that produced it.
+/// Pass `&GLOBAL_SYSTEM` to restore the default.
+pub fn set_system(system: &'static &'static dyn System) {
+  let raw = system as *const &'static dyn System as *mut _;
+  CURRENT_SYSTEM.store(raw, Ordering::Release);
+}
+
+pub fn current_system() -> &'static dyn System {
+  // SAFETY: `CURRENT_SYSTEM` only ever holds pointers derived from `&'static` references.
+  unsafe { *CURRENT_SYSTEM.load(Ordering::Acquire) }
+}
+
 #[cfg(test)]
 mod tests;
@@ -148,3 +148,65 @@ fn test_unsupported_system_dealloc() {
     }
   }
 }
+
+#[test]
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+fn test_resident_pages_track_reclaim() {
+  // More than one `mincore` batch.
+  let pages = 80;
+  let size = page_size() * pages;
+
+  unsafe {
+    let slice = GLOBAL_SYSTEM.alloc(size, SysOption::Commit).unwrap();
+    assert_eq!(GLOBAL_SYSTEM.resident_pages(slice).unwrap(), 0);
+
+    slice.fill(42);
+    assert_eq!(GLOBAL_SYSTEM.resident_pages(slice).unwrap(), pages);
+
+    GLOBAL_SYSTEM.modify(slice, SysOption::Reclaim).unwrap();
+    assert_eq!(GLOBAL_SYSTEM.resident_pages(slice).unwrap(), 0);
+
+    slice[0] = 1;
+    slice[size - 1] = 1;
+    assert_eq!(GLOBAL_SYSTEM.resident_pages(slice).unwrap(), 2);
+
+    GLOBAL_SYSTEM.dealloc(slice).unwrap();
+  }
+}
+
+#[test]
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+fn test_resident_pages_rejects_unaligned() {
+  unsafe {
+    let slice = GLOBAL_SYSTEM.alloc(page_size(), SysOption::Commit).unwrap();
+    assert!(GLOBAL_SYSTEM.resident_pages(&slice[1..]).is_err());
+    GLOBAL_SYSTEM.dealloc(slice).unwrap();
+  }
+}
+
+#[test]
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+fn test_committed_returns_to_baseline() {
+  // A private instance, so other tests' mappings don't move the count.
+  let system = crate::unix::UnixSystem::new();
+  let ps = page_size();
+  let baseline = system.committed();
+
+  unsafe {
+    let committed = system.alloc(4 * ps, SysOption::Commit).unwrap();
+    assert_eq!(system.committed(), baseline + 4 * ps);
+
+    let reserved = system.alloc(2 * ps, SysOption::Reserve).unwrap();
+    assert_eq!(system.committed(), baseline + 4 * ps);
+    system.modify(reserved, SysOption::Commit).unwrap();
+    assert_eq!(system.committed(), baseline + 6 * ps);
+
+    system.modify(&committed[..ps], SysOption::Reclaim).unwrap();
+    assert_eq!(system.committed(), baseline + 5 * ps);
+    system.modify(&committed[..ps], SysOption::Commit).unwrap();
+
+    system.dealloc(committed).unwrap();
+    system.dealloc(reserved).unwrap();
+  }
+  assert_eq!(system.committed(), baseline);
+}
@@ -1,6 +1,14 @@
+use core::sync::atomic::{
+  AtomicUsize,
+  Ordering,
+};
+
 #[cfg(any(target_os = "linux", target_os = "macos"))]
 use crate::{
-  prim::is_page_aligned,
+  prim::{
+    is_page_aligned,
+    page_size,
+  },
   system::{
     SysError,
     SysOption,
@@ -9,13 +17,47 @@ use crate::{
   },
 };
 
-pub struct UnixSystem {}
+pub struct UnixSystem {
+  // Running total behind `System::committed`.
+  committed_bytes: AtomicUsize,
+}
+
+impl Default for UnixSystem {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl UnixSystem {
+  pub const fn new() -> Self {
+    Self {
+      committed_bytes: AtomicUsize::new(0),
+    }
+  }
+}
 
 #[cfg(any(target_os = "linux", target_os = "macos"))]
-pub static UNIX_SYSTEM: UnixSystem = UnixSystem {};
+pub static UNIX_SYSTEM: UnixSystem = UnixSystem::new();
 
 #[cfg(any(target_os = "linux", target_os = "macos"))]
 impl UnixSystem {
+  fn charge(&self, options: SysOption, bytes: usize) {
+    match options {
+      SysOption::Commit => {
+        self.committed_bytes.fetch_add(bytes, Ordering::Relaxed);
+      }
+      SysOption::Reserve | SysOption::Reclaim => self.uncharge(bytes),
+    }
+  }
+
+  fn uncharge(&self, bytes: usize) {
+    let _ = self
+      .committed_bytes
+      .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |committed| {
+        Some(committed.saturating_sub(bytes))
+      });
+  }
+
   const fn prot_as(options: SysOption) -> i32 {
     match options {
       SysOption::Commit => libc::PROT_READ | libc::PROT_WRITE,
@@ -39,6 +81,14 @@ impl UnixSystem {
     slice.as_ptr() as *mut libc::c_void
   }
 
+  fn map_prot(options: SysOption) -> SysResult<i32> {
+    match options {
+      SysOption::Reserve => Ok(Self::reserve_prot()),
+      SysOption::Commit => Ok(Self::prot_as(options)),
+      SysOption::Reclaim => Err(SysError::InvalidArgument),
+    }
+  }
+
   fn validate_range(slice: &[u8]) -> Result<(), SysError> {
     let addr = slice.as_ptr() as usize;
     if is_page_aligned(addr) != Ok(true) || is_page_aligned(slice.len()) != Ok(true) {
@@ -50,11 +100,7 @@ impl UnixSystem {
   fn protect(slice: &[u8], options: SysOption) -> Result<(), SysError> {
     Self::validate_range(slice)?;
 
-    let prot = match options {
-      SysOption::Reserve => Self::reserve_prot(),
-      SysOption::Commit => Self::prot_as(options),
-      SysOption::Reclaim => return Err(SysError::InvalidArgument),
-    };
+    let prot = Self::map_prot(options)?;
     let result = unsafe { libc::mprotect(Self::as_c(slice), slice.len(), prot) };
     if result == 0 {
       return Ok(());
@@ -63,6 +109,21 @@ impl UnixSystem {
     Err(SysError::InvalidArgument)
   }
 
+  // Pages queried per `mincore` call; keeps the status buffer on the stack.
+  const MINCORE_BATCH: usize = 64;
+
+  fn resident_in(chunk: &[u8]) -> SysResult<usize> {
+    let mut status = [0u8; Self::MINCORE_BATCH];
+    let result =
+      unsafe { libc::mincore(Self::as_c(chunk), chunk.len(), status.as_mut_ptr().cast()) };
+    if result != 0 {
+      return Err(SysError::InvalidArgument);
+    }
+
+    let pages = chunk.len() / page_size();
+    Ok(status[..pages].iter().filter(|s| **s & 1 != 0).count())
+  }
+
   fn advise(slice: &[u8], options: SysOption) -> SysResult<()> {
     Self::validate_range(slice)?;
 
@@ -91,11 +152,7 @@ unsafe impl System for UnixSystem {
       return Err(SysError::InvalidArgument);
     }
 
-    let prot = match options {
-      SysOption::Reserve => Self::reserve_prot(),
-      SysOption::Commit => Self::prot_as(options),
-      SysOption::Reclaim => return Err(SysError::InvalidArgument),
-    };
+    let prot = Self::map_prot(options)?;
 
     let ptr = unsafe { libc::mmap(core::ptr::null_mut(), size, prot, Self::flags(), -1, 0) };
 
@@ -103,6 +160,9 @@ unsafe impl System for UnixSystem {
       libc::MAP_FAILED => Err(SysError::OutOfMemory),
       _ => {
         let slice = unsafe { core::slice::from_raw_parts_mut(ptr as *mut u8, size) };
+        if let SysOption::Commit = options {
+          self.charge(options, size);
+        }
         Ok(slice)
       }
     }
@@ -116,7 +176,9 @@ unsafe impl System for UnixSystem {
     match options {
       SysOption::Reserve | SysOption::Commit => Self::protect(slice, options),
       SysOption::Reclaim => Self::advise(slice, options),
-    }
+    }?;
+    self.charge(options, slice.len());
+    Ok(())
   }
 
   unsafe fn dealloc(&self, slice: &[u8]) -> SysResult<()> {
@@ -126,9 +188,47 @@ unsafe impl System for UnixSystem {
 
     let result = unsafe { libc::munmap(Self::as_c(slice), slice.len()) };
     if result == 0 {
+      self.uncharge(slice.len());
       return Ok(());
     }
 
     Err(SysError::InvalidArgument)
   }
+
+  fn committed(&self) -> usize {
+    self.committed_bytes.load(Ordering::Relaxed)
+  }
+
+  unsafe fn resident_pages(&self, slice: &[u8]) -> SysResult<usize> {
+    Self::validate_range(slice)?;
+
+    slice
+      .chunks(Self::MINCORE_BATCH * page_size())
+      .try_fold(0, |total, chunk| Ok(total + Self::resident_in(chunk)?))
+  }
+
+  #[cfg(target_os = "linux")]
+  unsafe fn grow(&self, slice: &[u8], extra: usize, options: SysOption) -> SysResult<()> {
+    Self::validate_range(slice)?;
+    if extra == 0 || is_page_aligned(extra) != Ok(true) {
+      return Err(SysError::InvalidArgument);
+    }
+
+    let prot = Self::map_prot(options)?;
+    let end = slice.as_ptr_range().end as *mut libc::c_void;
+    let flags = Self::flags() | libc::MAP_FIXED_NOREPLACE;
+    let ptr = unsafe { libc::mmap(end, extra, prot, flags, -1, 0) };
+    if ptr == libc::MAP_FAILED {
+      return Err(SysError::OutOfMemory);
+    }
+    // Kernels before 4.17 take the flag as a mere hint and may map elsewhere.
+    if ptr != end {
+      unsafe { libc::munmap(ptr, extra) };
+      return Err(SysError::OutOfMemory);
+    }
+    if let SysOption::Commit = options {
+      self.charge(options, extra);
+    }
+    Ok(())
+  }
 }
@@ -33,8 +33,11 @@ pub extern "C" fn malloc(size: usize) -> *mut u8 {
   unsafe { ALLOC.alloc(layout) }
 }
 
+/// # Safety
+///
+/// `ptr` must be null, the sentinel, or a live pointer returned by this allocator.
 #[unsafe(no_mangle)]
-pub extern "C" fn free(ptr: *mut u8) {
+pub unsafe extern "C" fn free(ptr: *mut u8) {
   if BaseAlloc::is_invalid(ptr) {
     return;
   }
@@ -48,14 +51,17 @@ pub extern "C" fn free(ptr: *mut u8) {
   unsafe { ALLOC.dealloc(ptr, layout) };
 }
 
+/// # Safety
+///
+/// `ptr` must be null, the sentinel, or a live pointer returned by this allocator.
 #[unsafe(no_mangle)]
-pub extern "C" fn realloc(ptr: *mut u8, size: usize) -> *mut u8 {
+pub unsafe extern "C" fn realloc(ptr: *mut u8, size: usize) -> *mut u8 {
   if BaseAlloc::is_invalid(ptr) {
     return malloc(size);
   }
 
   if size == 0 {
-    free(ptr);
+    unsafe { free(ptr) };
     return BaseAlloc::sentinel();
   }
 
@@ -64,23 +70,21 @@ pub extern "C" fn realloc(ptr: *mut u8, size: usize) -> *mut u8 {
     return ptr::null_mut();
   }
 
-  let old_layout = unsafe { Layout::from_size_align_unchecked(old_size.unwrap(), 1) };
-  let new_layout = match Layout::from_size_align(size, old_layout.align()) {
-    Ok(l) => l,
-    Err(_) => return ptr::null_mut(),
-  };
-
-  let new_ptr = unsafe { ALLOC.alloc(new_layout) };
-  if new_ptr.is_null() {
-    return new_ptr;
+  let old_size = old_size.unwrap();
+  let old_layout = unsafe { Layout::from_size_align_unchecked(old_size, 1) };
+  let new_layout = Layout::from_size_align(size, 1).ok();
+  if new_layout.is_none() {
+    return ptr::null_mut();
   }
 
-  let copy_size = core::cmp::min(old_layout.size(), new_layout.size());
-  unsafe { ptr::copy_nonoverlapping(ptr, new_ptr, copy_size) };
-  unsafe { ALLOC.dealloc(ptr, old_layout) };
-  new_ptr
+  if BaseAlloc::realloc_in_place(ptr, old_layout, size) {
+    return ptr;
+  }
+  unsafe { BaseAlloc::move_allocation(ptr, old_size, new_layout.unwrap()) }
 }
 
+/// Only recycled small slots get cleared; large requests are fresh demand-zero mappings
+/// and never written.
 #[unsafe(no_mangle)]
 pub extern "C" fn calloc(num: usize, size: usize) -> *mut u8 {
   let total_size = num.checked_mul(size);
@@ -97,14 +101,7 @@ pub extern "C" fn calloc(num: usize, size: usize) -> *mut u8 {
     return ptr::null_mut();
   }
 
-  let layout = layout.unwrap();
-  let ptr = unsafe { ALLOC.alloc(layout) };
-  if ptr.is_null() {
-    return ptr;
-  }
-
-  unsafe { ptr::write_bytes(ptr, 0, total_size) };
-  ptr
+  unsafe { ALLOC.alloc_zeroed(layout.unwrap()) }
 }
 
 #[unsafe(no_mangle)]
@@ -113,6 +110,11 @@ pub extern "C" fn aligned_alloc(align: usize, size: usize) -> *mut u8 {
     return BaseAlloc::sentinel();
   }
 
+  // C11 requires `size` to be a multiple of `align` (EINVAL otherwise).
+  if !size.is_multiple_of(align) {
+    return ptr::null_mut();
+  }
+
   let layout = Layout::from_size_align(size, align).ok();
   if layout.is_none() {
     return ptr::null_mut();
@@ -135,3 +137,32 @@ pub extern "C" fn malloc_usable_size(ptr: *mut u8) -> usize {
 
   sizeof.unwrap()
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn aligned_alloc_validates_arguments() {
+    assert!(aligned_alloc(16, 20).is_null());
+    assert!(aligned_alloc(3, 16).is_null());
+    assert!(aligned_alloc(0, 16).is_null());
+
+    let ptr = aligned_alloc(16, 32);
+    assert!(!ptr.is_null());
+    assert!((ptr as usize).is_multiple_of(16));
+    unsafe { free(ptr) };
+  }
+
+  #[test]
+  fn calloc_serves_huge_requests() {
+    let size = 64 << 20;
+    assert!(calloc(usize::MAX, 2).is_null());
+
+    let ptr = calloc(1, size);
+    assert!(!ptr.is_null());
+    let bytes = unsafe { core::slice::from_raw_parts(ptr, size) };
+    assert!(bytes.iter().step_by(4096).all(|b| *b == 0));
+    unsafe { free(ptr) };
+  }
+}
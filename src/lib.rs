@@ -1,4 +1,4 @@
-#![no_std]
+#![cfg_attr(not(test), no_std)]
 
 use core::{
   alloc::{
@@ -12,20 +12,51 @@ use core::{
   },
 };
 
+#[cfg(feature = "debug-cookies")]
+use basealloc_alloc::cookie;
 use basealloc_alloc::{
   CHUNK_SIZE,
+  arena,
   arena::{
     Arena,
     ArenaId,
+    ArenaUsage,
+    CommitPolicy,
+    large_map_size,
+  },
+  classes,
+  classes::{
+    ScIdx,
+    SizeClassPolicy,
+    class_at,
+    class_for_layout,
+    max_class_align,
+    padded_size,
   },
-  classes::class_for,
   lookup::OwnerInfo,
+  static_,
   static_::{
+    ArenaSelector,
     acquire_this_arena,
+    for_each_arena,
     get_arena,
     lookup_arena,
+    pin_this_arena,
+    validate_arenas,
+  },
+  stats,
+  tcache::{
+    TCacheError,
+    TCacheResult,
+    acquire_tcache,
   },
 };
+#[cfg(feature = "sampling")]
+use basealloc_alloc::{
+  classes::NSCLASSES,
+  sampling,
+};
+use basealloc_extent::Extent;
 use basealloc_sync::lazy::LazyLock;
 
 static FALLBACK: LazyLock<AtomicPtr<Arena>> = LazyLock::new(|| {
@@ -38,28 +69,335 @@ static FALLBACK: LazyLock<AtomicPtr<Arena>> = LazyLock::new(|| {
 
 pub struct BaseAlloc {}
 
+/// Forces every global of the allocator to initialize now, in a safe order.
+///
+/// Everything is otherwise set up lazily on the first allocation; calling this from
+/// `main` before spawning threads keeps that work off the hot path. Returns `false`
+/// if thread-local storage is unavailable. Safe to call more than once.
+pub fn init() -> bool {
+  let ready = basealloc_alloc::init();
+  LazyLock::force(&FALLBACK);
+  ready
+}
+
+/// Allocates straight from arena `arena_id`, skipping the tcache and arena acquisition.
+///
+/// Returns null if that arena was never created. Other threads may use the same arena
+/// meanwhile; its bins lock on their own.
+pub fn alloc_in(arena_id: ArenaId, layout: Layout) -> *mut u8 {
+  get_arena(arena_id).map_or(core::ptr::null_mut(), |arena| {
+    BaseAlloc::alloc_from(arena, layout)
+  })
+}
+
+/// Allocates `layout` in its own mapping, whatever its size, that is never pre-faulted.
+///
+/// The mapping is readable and writable but no page is populated up front, so sparse
+/// buffers only pay for the pages they touch. Free it with the regular `dealloc`.
+pub fn alloc_reserved(layout: Layout) -> *mut u8 {
+  if layout.size() == 0 {
+    return BaseAlloc::zero_sized(layout);
+  }
+
+  let arena = unsafe { BaseAlloc::acquire_arena().as_ref() };
+  arena
+    .allocate_large(layout)
+    .map_or(core::ptr::null_mut(), |p| p.as_ptr())
+}
+
+/// Returns how many bytes allocating `layout` would actually take, without allocating.
+///
+/// Small layouts report their size class; large ones their page-aligned mapping, which
+/// for alignments above the page size includes the slack the block slides within.
+pub fn layout_footprint(layout: Layout) -> usize {
+  match class_for_layout(layout) {
+    Some(class) => class_at(class).0,
+    None => large_map_size(layout).unwrap_or(usize::MAX),
+  }
+}
+
+/// Zeroes the allocator's statistics so a benchmark phase starts clean.
+///
+/// Live allocations stay valid; they just stop being counted. Racy if other threads
+/// allocate meanwhile. The tcache keeps no counters of its own yet.
+pub fn reset_stats() {
+  stats::reset();
+}
+
+/// Returns the arena owning `ptr`, if it came from this allocator.
+pub fn arena_for(ptr: *mut u8) -> Option<ArenaId> {
+  if BaseAlloc::is_invalid(ptr) {
+    return None;
+  }
+  lookup_arena(ptr as usize)
+}
+
+/// Returns what `ptr` lives in: its slab and class, or its large extent.
+///
+/// `None` for null, the sentinel and pointers this allocator never handed out. The
+/// pointers inside are only valid while the allocation is live.
+pub fn owner_of(ptr: *mut u8) -> Option<OwnerInfo> {
+  BaseAlloc::owner(ptr)
+}
+
+/// Returns the size class of a live small allocation; `None` for large or foreign pointers.
+pub fn class_of(ptr: *mut u8) -> Option<ScIdx> {
+  match owner_of(ptr)? {
+    OwnerInfo::Slab { size_class, .. } => Some(size_class),
+    OwnerInfo::Extent { .. } => None,
+  }
+}
+
+/// Calls `f` on every arena this allocator has created, idle or not.
+///
+/// Safe to call while other threads allocate; an arena created concurrently may be missed,
+/// but no arena is ever freed, so `f` never sees a dangling one.
+pub fn with_arenas(f: impl FnMut(&Arena)) {
+  for_each_arena(f);
+}
+
+/// Share of committed memory that holds no live data, over every arena: `0.0` when all
+/// of it is in use, towards `1.0` as slabs go sparse.
+///
+/// Live slots count at their class size, so padding inside a slot doesn't show; slots
+/// parked in thread caches count as live. Racy like `with_arenas`.
+pub fn fragmentation() -> f32 {
+  let mut usage = ArenaUsage::default();
+  with_arenas(|arena| usage += arena.usage());
+  if let Some(fallback) = LazyLock::get(&FALLBACK) {
+    usage += unsafe { &*fallback.load(Ordering::Acquire) }.usage();
+  }
+  usage.fragmentation()
+}
+
+/// Hands every slot parked in the calling thread's tcache back to its arena.
+///
+/// The cache itself survives, so idle workers can return memory without exiting.
+pub fn flush_thread_cache() -> TCacheResult<()> {
+  let Some(mut tcache) = acquire_tcache() else {
+    return Ok(());
+  };
+  let arena = unsafe { BaseAlloc::acquire_arena().as_ref() };
+  unsafe { tcache.as_mut() }.flush_all(arena)
+}
+
+/// Most pages one `maintenance` call hands back before it returns.
+pub const MAINTENANCE_PAGES: usize = 1024;
+
+/// One bounded pass of idle-time cleanup, for the host to call from a timer or idle hook;
+/// nothing here runs on its own.
+///
+/// Flushes the calling thread's tcache, then returns free slab pages to the system, arena
+/// by arena until `MAINTENANCE_PAGES` went back. Large extents are unmapped on free, so
+/// there is no cache of them to trim. Returns the pages reclaimed.
+pub fn maintenance() -> TCacheResult<usize> {
+  flush_thread_cache()?;
+  let mut reclaimed = Ok(0);
+  let mut reclaim = |arena: &Arena| {
+    if let Ok(done) = reclaimed
+      && done < MAINTENANCE_PAGES
+    {
+      reclaimed = arena
+        .reclaim_pages(MAINTENANCE_PAGES - done)
+        .map(|pages| done + pages);
+    }
+  };
+  with_arenas(&mut reclaim);
+  if let Some(fallback) = LazyLock::get(&FALLBACK) {
+    reclaim(unsafe { &*fallback.load(Ordering::Acquire) });
+  }
+  reclaimed.map_err(TCacheError::ArenaError)
+}
+
+/// Estimated allocations per size class since start, with large ones in the last slot.
+///
+/// Only every `sample_rate()`th allocation per thread is recorded; see
+/// `basealloc_alloc::sampling::set_sample_rate`.
+#[cfg(feature = "sampling")]
+pub fn sample_histogram() -> [u64; sampling::BUCKETS] {
+  sampling::histogram()
+}
+
+/// Faults in the calling thread's tcache up front, creating it if needed.
+///
+/// Latency-sensitive threads call this once at startup instead of paying a page fault on
+/// the first allocation of each class.
+pub fn prewarm_thread_cache() -> bool {
+  let Some(mut tcache) = acquire_tcache() else {
+    return false;
+  };
+  unsafe { tcache.as_mut() }.prewarm();
+  true
+}
+
+/// Forces the calling thread onto arena `arena_id`, creating it if needed, so runs
+/// that bind threads the same way allocate the same way.
+///
+/// Slots parked in the thread cache go back to the old arena first, so everything the
+/// thread allocates afterwards comes from `arena_id`. Other threads may share it.
+/// Returns `false` if the arena doesn't exist and can't be created, or thread-local
+/// storage is unavailable.
+pub fn pin_thread_to_arena(arena_id: ArenaId) -> bool {
+  if flush_thread_cache().is_err() {
+    return false;
+  }
+  pin_this_arena(arena_id)
+}
+
+/// `(class_size, slab_size)` for every size class this build uses, smallest first.
+///
+/// The boundaries depend on the target's word size and the class features, so heap
+/// analyzers read them from here to map addresses to classes offline.
+pub fn class_table() -> &'static [(usize, usize)] {
+  classes::class_table()
+}
+
+/// Writes `class_table()` as CSV, one class per row under a `class_size,slab_size` header.
+pub fn write_class_table(out: &mut impl core::fmt::Write) -> core::fmt::Result {
+  classes::write_class_table(out)
+}
+
+/// Sets whether arenas created from now on commit their slabs up front; see `CommitPolicy`.
+///
+/// Arenas that already exist keep the policy they were created with.
+pub fn set_default_commit_policy(policy: CommitPolicy) {
+  arena::set_default_commit_policy(policy);
+}
+
+/// Caps the bytes the allocator may map in total; `usize::MAX` lifts the cap.
+///
+/// Past it, allocations that need a new mapping return null instead of growing, while
+/// live ones stay valid and slots already mapped keep being reused. Reserved address
+/// space counts as well as committed memory, so leave room for the thread caches and
+/// arena metadata.
+pub fn set_memory_limit(bytes: usize) {
+  basealloc_extent::set_memory_limit(bytes);
+}
+
+/// Bytes the allocator currently has mapped; what `set_memory_limit` caps.
+pub fn memory_usage() -> usize {
+  basealloc_extent::mapped_bytes()
+}
+
+/// Installs the strategy threads use to pick their arena; see `ArenaSelector`.
+///
+/// Only threads that haven't allocated yet are affected. Pass
+/// `&basealloc_alloc::static_::DEFAULT_SELECTOR` to restore the default.
+pub fn set_arena_selector(selector: &'static &'static dyn ArenaSelector) {
+  static_::set_arena_selector(selector);
+}
+
+/// Walks the whole allocator and returns the first broken invariant it finds.
+///
+/// Checks the arena and slab bitmaps against their used counts, every bin's slab lists
+/// and that the arena map agrees with each arena's extent tree. Meant for fuzzers and
+/// CI to call after each operation; only reliable while no other thread allocates.
+pub fn check_invariants() -> Result<(), &'static str> {
+  validate_arenas()?;
+  let Some(fallback) = LazyLock::get(&FALLBACK) else {
+    return Ok(());
+  };
+  unsafe { &*fallback.load(Ordering::Acquire) }.validate()
+}
+
 impl BaseAlloc {
+  // Bytes in front of every small object.
+  #[cfg(feature = "debug-cookies")]
+  const HEADER: usize = cookie::COOKIE;
+  #[cfg(not(feature = "debug-cookies"))]
+  const HEADER: usize = 0;
+
   pub fn sizeof(pointer: *mut u8) -> Option<usize> {
-    if Self::is_invalid(pointer) {
-      return None;
+    match Self::owner(pointer)? {
+      OwnerInfo::Slab { size_class, .. } => Some(class_at(size_class).0 - Self::HEADER),
+      OwnerInfo::Extent { extent } => Some(Self::large_usable(extent, pointer)),
     }
+  }
+
+  // Bytes from `ptr` to the end of its large mapping; over-aligned blocks start past
+  // the base.
+  fn large_usable(extent: NonNull<Extent>, ptr: *mut u8) -> usize {
+    let mapping = unsafe { extent.as_ref() }.as_ref();
+    mapping.as_ptr() as usize + mapping.len() - ptr as usize
+  }
+
+  /// Reports whether `ptr` can hold `new_size` bytes without moving; never frees.
+  ///
+  /// True when a small allocation stays in its size class, or a large one still fits
+  /// its mapping (and is too big for a class). The caller may then treat the block as
+  /// `new_size` long; on false nothing changed.
+  pub fn realloc_in_place(ptr: *mut u8, old_layout: Layout, new_size: usize) -> bool {
+    let Ok(new_layout) = Layout::from_size_align(new_size, old_layout.align()) else {
+      return false;
+    };
 
-    let ptr_nn = unsafe { NonNull::new_unchecked(pointer) };
-    let arena_id = lookup_arena(pointer as usize).unwrap();
-    let arena = get_arena(arena_id).unwrap();
+    let fits = match (Self::owner(ptr), class_for_layout(new_layout)) {
+      (Some(OwnerInfo::Slab { size_class, .. }), Some(class)) => class == size_class,
+      (Some(OwnerInfo::Extent { extent }), None) => Self::large_usable(extent, ptr) >= new_size,
+      _ => false,
+    };
+    #[cfg(feature = "debug-cookies")]
+    if fits && class_for_layout(new_layout).is_some() {
+      Self::resize_sealed(ptr, new_size);
+    }
+    fits
+  }
 
-    let info = match arena.etree().lookup(ptr_nn.as_ptr() as usize) {
-      Some(i) => i.clone(),
-      None => return None,
+  /// Grows a large block that stays large to `new_size` bytes without moving it,
+  /// mapping the pages after its extent if it has to; false if they're taken.
+  pub fn grow_large_in_place(ptr: *mut u8, old_layout: Layout, new_layout: Layout) -> bool {
+    if class_for_layout(new_layout).is_some() || new_layout.size() <= old_layout.size() {
+      return false;
+    }
+    let (Some(OwnerInfo::Extent { extent }), Some(ptr_nn)) = (Self::owner(ptr), NonNull::new(ptr))
+    else {
+      return false;
     };
+    let Some(arena) = lookup_arena(ptr as usize).and_then(get_arena) else {
+      return false;
+    };
+    arena.grow_large(extent, ptr_nn, new_layout.size()).is_ok()
+  }
+
+  /// Moves `old_ptr` into a fresh allocation for `new_layout`: allocate, copy
+  /// `min(old_size, new_layout.size())` bytes, free the old block.
+  ///
+  /// Returns null and leaves `old_ptr` untouched if the new allocation fails. A freed
+  /// small slot goes to this thread's tcache when it can.
+  ///
+  /// # Safety
+  ///
+  /// `old_ptr` must be live, from this allocator, and hold at least `old_size` bytes;
+  /// `old_size == 0` means it came from a zero-size layout.
+  pub unsafe fn move_allocation(old_ptr: *mut u8, old_size: usize, new_layout: Layout) -> *mut u8 {
+    let new_ptr = unsafe { BaseAlloc {}.alloc(new_layout) };
+    if new_ptr.is_null() || old_size == 0 {
+      return new_ptr;
+    }
 
-    match info {
-      OwnerInfo::Slab { size_class, .. } => Some(size_class.0),
-      OwnerInfo::Extent { extent } => {
-        let extent_ref = unsafe { extent.as_ref() };
-        Some(extent_ref.size())
+    let copy_size = core::cmp::min(old_size, new_layout.size());
+    unsafe { core::ptr::copy_nonoverlapping(old_ptr, new_ptr, copy_size) };
+
+    let cached = match (class_of(old_ptr), NonNull::new(old_ptr)) {
+      (Some(class), Some(ptr_nn)) => {
+        Self::cache_free(Self::unseal(ptr_nn, class, Some(old_size)), class)
       }
+      _ => false,
+    };
+    if !cached {
+      let old_layout = unsafe { Layout::from_size_align_unchecked(old_size, 1) };
+      unsafe { BaseAlloc {}.dealloc(old_ptr, old_layout) };
+    }
+    new_ptr
+  }
+
+  fn owner(pointer: *mut u8) -> Option<OwnerInfo> {
+    if Self::is_invalid(pointer) {
+      return None;
     }
+
+    let addr = pointer as usize;
+    get_arena(lookup_arena(addr)?)?.etree().lookup(addr)
   }
 
   pub fn is_invalid(ptr: *mut u8) -> bool {
@@ -70,6 +408,115 @@ impl BaseAlloc {
     NonNull::dangling().as_ptr()
   }
 
+  /// What zero-size layouts get: never dereferenced, but aligned as asked.
+  ///
+  /// For byte alignment this is exactly the sentinel.
+  fn zero_sized(layout: Layout) -> *mut u8 {
+    core::ptr::without_provenance_mut(layout.align())
+  }
+
+  /// Allocates `layout` from a class picked by `policy` instead of the built-in spacing.
+  ///
+  /// Requests the policy has no class for (or whose class doesn't suit the alignment)
+  /// take the normal path. The result is freed like any other allocation.
+  pub fn alloc_with(policy: &impl SizeClassPolicy, layout: Layout) -> *mut u8 {
+    if layout.size() == 0 {
+      return Self::zero_sized(layout);
+    }
+
+    let bin = padded_size(layout.size())
+      .and_then(|size| policy.class_for(size))
+      .and_then(|idx| policy.bin_for(idx))
+      .filter(|_| layout.align() <= max_class_align())
+      .filter(|bin| class_at(*bin).0.is_multiple_of(layout.align()));
+
+    let Some(bin) = bin else {
+      return unsafe { BaseAlloc {}.alloc(layout) };
+    };
+    let arena = unsafe { Self::acquire_arena().as_ref() };
+    arena.allocate(bin).map_or(core::ptr::null_mut(), |p| {
+      Self::seal(p, bin, layout.size()).as_ptr()
+    })
+  }
+
+  fn take_cached(class: ScIdx) -> Option<NonNull<u8>> {
+    let mut tcache = acquire_tcache()?;
+    unsafe { tcache.as_mut() }.take(class)
+  }
+
+  /// Writes the header of a `size`-byte object at the start of a small `slot` and
+  /// returns the object behind it.
+  #[cfg(feature = "debug-cookies")]
+  fn seal(slot: NonNull<u8>, class: ScIdx, size: usize) -> NonNull<u8> {
+    cookie::seal(slot, class, size)
+  }
+
+  #[cfg(not(feature = "debug-cookies"))]
+  #[inline(always)]
+  fn seal(slot: NonNull<u8>, _class: ScIdx, _size: usize) -> NonNull<u8> {
+    slot
+  }
+
+  /// Checks the header of the small object `ptr` and returns the slot it starts.
+  #[cfg(feature = "debug-cookies")]
+  fn unseal(ptr: NonNull<u8>, class: ScIdx, size: Option<usize>) -> NonNull<u8> {
+    cookie::open(ptr, class, size)
+  }
+
+  #[cfg(not(feature = "debug-cookies"))]
+  #[inline(always)]
+  fn unseal(ptr: NonNull<u8>, _class: ScIdx, _size: Option<usize>) -> NonNull<u8> {
+    ptr
+  }
+
+  #[cfg(feature = "debug-cookies")]
+  fn resize_sealed(ptr: *mut u8, size: usize) {
+    if let Some(ptr) = NonNull::new(ptr) {
+      cookie::resize(ptr, size);
+    }
+  }
+
+  /// Parks a small slot in this thread's tcache if this thread's arena owns it.
+  fn cache_free(ptr: NonNull<u8>, class: ScIdx) -> bool {
+    let arena = unsafe { Self::acquire_arena().as_ref() };
+    if lookup_arena(ptr.as_ptr() as usize) != Some(arena.index()) {
+      return false;
+    }
+
+    let Some(mut tcache) = acquire_tcache() else {
+      return false;
+    };
+    unsafe { tcache.as_mut() }
+      .deallocate(arena, ptr, class)
+      .is_ok()
+  }
+
+  /// Frees a small slot directly if this thread owns `arena`, else queues it for the owner.
+  fn free_small(arena: &Arena, ptr: NonNull<u8>) {
+    let local = unsafe { Self::acquire_arena().as_ref() }.index();
+    if local == arena.index() {
+      _ = arena.deallocate(ptr)
+    } else {
+      _ = arena.free_remote(ptr)
+    }
+  }
+
+  fn alloc_from(arena: &Arena, layout: Layout) -> *mut u8 {
+    let ptr = match class_for_layout(layout) {
+      Some(class) => arena
+        .allocate(class)
+        .map(|slot| Self::seal(slot, class, layout.size())),
+      None => arena.allocate_large(layout),
+    };
+    ptr.map_or(core::ptr::null_mut(), |p| p.as_ptr())
+  }
+
+  #[cfg(feature = "sampling")]
+  fn sample(class: Option<ScIdx>) {
+    let shard = unsafe { Self::acquire_arena().as_ref() }.index().0;
+    sampling::record(shard, class.map_or(NSCLASSES, |c| c.0));
+  }
+
   fn acquire_arena() -> NonNull<Arena> {
     acquire_this_arena().unwrap_or_else(|| {
       let fallback_ptr = FALLBACK.load(Ordering::Acquire);
@@ -80,37 +527,774 @@ impl BaseAlloc {
 
 unsafe impl GlobalAlloc for BaseAlloc {
   unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
-    let class = class_for(layout.size());
-    if let Some(class) = class {
-      let arena = unsafe { Self::acquire_arena().as_mut() };
-      let ptr = arena.allocate(class);
-      return match ptr {
-        Ok(p) => p.as_ptr(),
-        Err(_) => core::ptr::null_mut(),
-      };
+    if layout.size() == 0 {
+      return Self::zero_sized(layout);
     }
 
-    let arena = unsafe { Self::acquire_arena().as_mut() };
-    let ptr = arena.allocate_large(layout);
-    match ptr {
-      Ok(p) => p.as_ptr(),
-      Err(_) => core::ptr::null_mut(),
+    let class = class_for_layout(layout);
+    #[cfg(feature = "sampling")]
+    Self::sample(class);
+    if let Some(class) = class
+      && let Some(cached) = Self::take_cached(class)
+    {
+      return Self::seal(cached, class, layout.size()).as_ptr();
     }
+
+    let arena = unsafe { Self::acquire_arena().as_ref() };
+    Self::alloc_from(arena, layout)
   }
 
-  unsafe fn dealloc(&self, ptr: *mut u8, _: Layout) {
-    if Self::is_invalid(ptr) {
+  unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+    if layout.size() == 0 {
+      return Self::zero_sized(layout);
+    }
+
+    let class = class_for_layout(layout);
+    #[cfg(feature = "sampling")]
+    Self::sample(class);
+    if let Some(class) = class
+      && let Some(cached) = Self::take_cached(class)
+    {
+      let ptr = Self::seal(cached, class, layout.size()).as_ptr();
+      unsafe { ptr.write_bytes(0, layout.size()) };
+      return ptr;
+    }
+
+    let arena = unsafe { Self::acquire_arena().as_ref() };
+    // Large extents are fresh mappings, which read as zero.
+    let ptr = match class {
+      Some(class) => arena
+        .allocate_zeroed(class)
+        .map(|slot| Self::seal(slot, class, layout.size())),
+      None => arena.allocate_large(layout),
+    };
+    ptr.map_or(core::ptr::null_mut(), |p| p.as_ptr())
+  }
+
+  unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+    if layout.size() == 0 || Self::is_invalid(ptr) {
       return;
     }
 
     let ptr_nn = unsafe { NonNull::new_unchecked(ptr) };
+    let Some(arena) = lookup_arena(ptr as usize).and_then(get_arena) else {
+      return;
+    };
+
+    // Large frees only touch the owner's extent tree, so they never need queueing.
+    match arena.etree().lookup(ptr as usize) {
+      Some(OwnerInfo::Extent { extent }) => _ = arena.deallocate_large(extent),
+      Some(OwnerInfo::Slab { size_class, .. }) => {
+        let slot = Self::unseal(ptr_nn, size_class, Some(layout.size()));
+        Self::free_small(arena, slot)
+      }
+      None => {}
+    }
+  }
+
+  unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+    let new_layout = unsafe { Layout::from_size_align_unchecked(new_size, layout.align()) };
+    // A zero-size block was never carved from a class, even if a redzone maps it to one.
+    let old_class = class_for_layout(layout).filter(|_| layout.size() != 0);
+    if old_class.is_some() && old_class == class_for_layout(new_layout) {
+      #[cfg(feature = "debug-cookies")]
+      Self::resize_sealed(ptr, new_size);
+      return ptr;
+    }
+    if Self::grow_large_in_place(ptr, layout, new_layout) {
+      return ptr;
+    }
+
+    unsafe { Self::move_allocation(ptr, layout.size(), new_layout) }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  #[cfg(not(feature = "debug-cookies"))]
+  use basealloc_alloc::classes::SizeClass;
+  use basealloc_alloc::static_::{
+    ArenaStats,
+    DEFAULT_SELECTOR,
+    RoundRobinSelector,
+  };
+  use basealloc_sys::prim::page_align;
+
+  fn cached(class: ScIdx) -> usize {
+    let tcache = acquire_tcache().unwrap();
+    unsafe { tcache.as_ref() }.cached(class)
+  }
+
+  #[test]
+  fn realloc_within_class_is_stable() {
+    let alloc = BaseAlloc {};
+    // Same class with or without the redzone.
+    let layout = Layout::from_size_align(36, 8).unwrap();
+    let class = class_for_layout(layout).unwrap();
+
+    let ptr = unsafe { alloc.alloc(layout) };
+    assert!(!ptr.is_null());
+    let before = cached(class);
+
+    let same = unsafe { alloc.realloc(ptr, layout, 40) };
+    assert_eq!(same, ptr);
+    assert_eq!(cached(class), before);
+
+    unsafe { alloc.dealloc(same, Layout::from_size_align(40, 8).unwrap()) };
+  }
+
+  #[cfg(not(feature = "debug-cookies"))]
+  struct PowerOfTwo;
+
+  #[cfg(not(feature = "debug-cookies"))]
+  impl SizeClassPolicy for PowerOfTwo {
+    fn class_for(&self, size: usize) -> Option<ScIdx> {
+      let class = size.max(16).checked_next_power_of_two()?;
+      let idx = class.trailing_zeros() as usize - 4;
+      (idx < self.num_classes()).then_some(ScIdx(idx))
+    }
 
-    let arena_id = match lookup_arena(ptr as usize) {
-      Some(id) => id,
-      None => return,
+    fn class_at(&self, idx: ScIdx) -> SizeClass {
+      SizeClass(16 << idx.0, idx)
+    }
+
+    fn num_classes(&self) -> usize {
+      17
+    }
+  }
+
+  // The header would push every size here into the next power of two.
+  #[cfg(not(feature = "debug-cookies"))]
+  #[test]
+  fn alloc_with_custom_policy() {
+    let alloc = BaseAlloc {};
+    for (size, class) in [(17, 32), (100, 128), (3000, 4096), (40000, 65536)] {
+      let layout = Layout::from_size_align(size, 8).unwrap();
+      let ptr = BaseAlloc::alloc_with(&PowerOfTwo, layout);
+      assert!(!ptr.is_null());
+      assert_eq!(BaseAlloc::sizeof(ptr), Some(class), "size {}", size);
+      unsafe { ptr.write_bytes(0xAB, size) };
+      unsafe { alloc.dealloc(ptr, layout) };
+    }
+
+    // Past the policy's last class the request takes the normal path.
+    let huge = Layout::from_size_align(1 << 21, 8).unwrap();
+    let ptr = BaseAlloc::alloc_with(&PowerOfTwo, huge);
+    assert!(BaseAlloc::sizeof(ptr).unwrap() >= 1 << 21);
+    unsafe { alloc.dealloc(ptr, huge) };
+  }
+
+  #[test]
+  fn zero_size_alloc_is_dangling() {
+    let alloc = BaseAlloc {};
+    let layout = Layout::from_size_align(0, 1).unwrap();
+    let aligned = Layout::from_size_align(0, 64).unwrap();
+
+    let ptr = unsafe { alloc.alloc(layout) };
+    assert_eq!(ptr, BaseAlloc::sentinel());
+    let wide = unsafe { alloc.alloc(aligned) };
+    assert!(!wide.is_null());
+    assert_eq!(wide as usize % 64, 0);
+    assert_eq!(BaseAlloc::sizeof(wide), None);
+
+    let grown = unsafe { alloc.realloc(wide, aligned, 48) };
+    assert_ne!(grown, wide);
+    assert!(BaseAlloc::sizeof(grown).is_some());
+
+    unsafe { alloc.dealloc(ptr, layout) };
+    unsafe { alloc.dealloc(grown, Layout::from_size_align(48, 64).unwrap()) };
+  }
+
+  #[test]
+  fn move_allocation_keeps_the_common_prefix() {
+    let alloc = BaseAlloc {};
+    let large = 4 << 20;
+    // Grow within classes, shrink, class to large and back.
+    for (old, new) in [
+      (40, 900),
+      (900, 40),
+      (3000, large),
+      (large, 100),
+      (large, 2 * large),
+    ] {
+      let layout = Layout::from_size_align(old, 8).unwrap();
+      let ptr = unsafe { alloc.alloc(layout) };
+      for i in 0..old {
+        unsafe { ptr.add(i).write(i as u8) };
+      }
+
+      let new_layout = Layout::from_size_align(new, 8).unwrap();
+      let moved = unsafe { BaseAlloc::move_allocation(ptr, old, new_layout) };
+      assert!(!moved.is_null() && moved != ptr, "{} -> {}", old, new);
+      assert!(BaseAlloc::sizeof(moved).unwrap() >= new);
+      let kept = unsafe { core::slice::from_raw_parts(moved, old.min(new)) };
+      assert!(
+        kept.iter().enumerate().all(|(i, b)| *b == i as u8),
+        "{} -> {}",
+        old,
+        new
+      );
+      unsafe { alloc.dealloc(moved, new_layout) };
+    }
+  }
+
+  #[test]
+  fn realloc_across_classes_recycles_slot() {
+    let alloc = BaseAlloc {};
+    let layout = Layout::from_size_align(32, 8).unwrap();
+    let class = class_for_layout(layout).unwrap();
+
+    let ptr = unsafe { alloc.alloc(layout) };
+    unsafe { core::ptr::write_bytes(ptr, 0x5A, 32) };
+    let before = cached(class);
+
+    let grown = unsafe { alloc.realloc(ptr, layout, 200) };
+    assert_ne!(grown, ptr);
+    assert_eq!(cached(class), before + 1);
+    let contents = unsafe { core::slice::from_raw_parts(grown, 32) };
+    assert!(contents.iter().all(|b| *b == 0x5A));
+
+    let reused = unsafe { alloc.alloc(layout) };
+    assert_eq!(reused, ptr);
+
+    unsafe { alloc.dealloc(reused, layout) };
+    unsafe { alloc.dealloc(grown, Layout::from_size_align(200, 8).unwrap()) };
+  }
+
+  #[test]
+  fn alloc_in_pins_arena() {
+    // Arenas are created in index order as threads first allocate.
+    while get_arena(ArenaId(3)).is_none() {
+      std::thread::spawn(|| {
+        BaseAlloc::acquire_arena();
+      })
+      .join()
+      .unwrap();
+    }
+
+    let alloc = BaseAlloc {};
+    for size in [64, 4096, 1 << 22] {
+      let layout = Layout::from_size_align(size, 8).unwrap();
+      let ptr = alloc_in(ArenaId(3), layout);
+      assert!(!ptr.is_null());
+      assert_eq!(arena_for(ptr), Some(ArenaId(3)));
+      unsafe { alloc.dealloc(ptr, layout) };
+    }
+
+    let layout = Layout::from_size_align(64, 8).unwrap();
+    assert!(alloc_in(ArenaId(usize::MAX), layout).is_null());
+  }
+
+  #[test]
+  fn owner_of_finds_the_slab() {
+    let alloc = BaseAlloc {};
+    let layout = Layout::from_size_align(200, 8).unwrap();
+    let ptr = unsafe { alloc.alloc(layout) };
+
+    let Some(OwnerInfo::Slab { slab, size_class }) = owner_of(ptr) else {
+      panic!("expected a slab owner");
     };
+    assert_eq!(Some(size_class), class_for_layout(layout));
+    let extent = unsafe { slab.as_ref() }.extent().as_ref().as_ptr_range();
+    assert!(extent.contains(&(ptr as *const u8)));
+    unsafe { alloc.dealloc(ptr, layout) };
+
+    let mut local = 0u8;
+    assert!(owner_of(core::ptr::null_mut()).is_none());
+    assert!(owner_of(BaseAlloc::sentinel()).is_none());
+    assert!(owner_of(&mut local).is_none());
+  }
+
+  #[test]
+  fn class_of_reports_small_classes() {
+    let alloc = BaseAlloc {};
+    for size in [1, 16, 100, 1000, 5000] {
+      let layout = Layout::from_size_align(size, 8).unwrap();
+      let ptr = unsafe { alloc.alloc(layout) };
+      assert_eq!(class_of(ptr), class_for_layout(layout), "size {}", size);
+      unsafe { alloc.dealloc(ptr, layout) };
+    }
+
+    let layout = Layout::from_size_align(4 << 20, 8).unwrap();
+    let large = unsafe { alloc.alloc(layout) };
+    assert!(!large.is_null());
+    assert_eq!(class_of(large), None);
+    unsafe { alloc.dealloc(large, layout) };
+
+    let mut local = 0u8;
+    assert_eq!(class_of(BaseAlloc::sentinel()), None);
+    assert_eq!(class_of(core::ptr::null_mut()), None);
+    assert_eq!(class_of(&mut local), None);
+  }
+
+  #[test]
+  fn alloc_reserved_faults_on_demand() {
+    const PAGES: usize = 64;
+    use basealloc_sys::{
+      GLOBAL_SYSTEM,
+      prim::page_size,
+    };
+
+    let ps = page_size();
+    let layout = Layout::from_size_align(PAGES * ps, 8).unwrap();
+    let empty = Layout::from_size_align(0, 64).unwrap();
+    assert_eq!(alloc_reserved(empty), BaseAlloc::zero_sized(empty));
+
+    let ptr = alloc_reserved(layout);
+    assert!(!ptr.is_null());
+    unsafe { ptr.add(5 * ps).write(1) };
+
+    let slice = unsafe { core::slice::from_raw_parts(ptr, PAGES * ps) };
+    assert_eq!(unsafe { GLOBAL_SYSTEM.resident_pages(slice) }.unwrap(), 1);
+
+    unsafe { BaseAlloc {}.dealloc(ptr, layout) };
+  }
+
+  #[test]
+  fn large_alloc_zeroed_leaves_pages_untouched() {
+    use basealloc_sys::{
+      GLOBAL_SYSTEM,
+      prim::page_size,
+    };
+
+    let size = 64 << 20;
+    let layout = Layout::from_size_align(size, 8).unwrap();
+    let ptr = unsafe { BaseAlloc {}.alloc_zeroed(layout) };
+    assert!(!ptr.is_null());
+
+    // A memset would have faulted in every page.
+    let slice = unsafe { core::slice::from_raw_parts(ptr, size) };
+    assert_eq!(unsafe { GLOBAL_SYSTEM.resident_pages(slice) }.unwrap(), 0);
+    assert!(slice.iter().step_by(page_size()).all(|b| *b == 0));
+
+    unsafe { BaseAlloc {}.dealloc(ptr, layout) };
+  }
+
+  #[test]
+  fn with_arenas_visits_created_arenas() {
+    let layout = Layout::from_size_align(96, 8).unwrap();
+    let ptrs: Vec<usize> = (0..4)
+      .map(|_| {
+        std::thread::spawn(move || unsafe { BaseAlloc {}.alloc(layout) } as usize)
+          .join()
+          .unwrap()
+      })
+      .collect();
+
+    let mut seen = Vec::new();
+    with_arenas(|arena| seen.push(arena.index()));
+    for ptr in ptrs.iter() {
+      let owner = arena_for(*ptr as *mut u8).expect("owned");
+      assert!(seen.contains(&owner));
+    }
+
+    for ptr in ptrs {
+      unsafe { BaseAlloc {}.dealloc(ptr as *mut u8, layout) };
+    }
+  }
+
+  #[test]
+  fn large_free_goes_to_owning_arena() {
+    let layout = Layout::from_size_align(1 << 21, 8).unwrap();
+    let local = unsafe { BaseAlloc::acquire_arena().as_ref() }.index();
+
+    let addr = std::thread::spawn(move || unsafe { BaseAlloc {}.alloc(layout) } as usize)
+      .join()
+      .unwrap();
+    let ptr = addr as *mut u8;
+    let owner = arena_for(ptr).expect("owned");
+    assert_ne!(owner, local);
+
+    unsafe { BaseAlloc {}.dealloc(ptr, layout) };
+    assert_eq!(arena_for(ptr), None);
+    assert!(get_arena(owner).unwrap().etree().lookup(addr).is_none());
+  }
+
+  #[test]
+  fn layout_footprint_matches_allocation() {
+    let alloc = BaseAlloc {};
+    let cases = [
+      (1, 1),
+      (24, 8),
+      (48, 32),
+      (700, 8),
+      (3000, 1024),
+      (70_000, 16),
+    ];
+    for (size, align) in cases {
+      let layout = Layout::from_size_align(size, align).unwrap();
+      let footprint = layout_footprint(layout);
+      assert!(footprint >= size, "size {}", size);
+
+      let ptr = unsafe { alloc.alloc(layout) };
+      assert!((ptr as usize).is_multiple_of(align));
+      let usable = footprint - class_for_layout(layout).map_or(0, |_| BaseAlloc::HEADER);
+      assert_eq!(BaseAlloc::sizeof(ptr), Some(usable), "size {}", size);
+      unsafe { alloc.dealloc(ptr, layout) };
+    }
+
+    let large = Layout::from_size_align((4 << 20) + 1, 8).unwrap();
+    assert_eq!(layout_footprint(large), page_align(large.size()).unwrap());
+  }
+
+  #[test]
+  fn init_then_allocate_from_threads() {
+    assert!(init());
+    assert!(init());
+
+    std::thread::scope(|scope| {
+      for _ in 0..8 {
+        scope.spawn(|| {
+          let alloc = BaseAlloc {};
+          for size in [8, 256, 4096, 1 << 20] {
+            let layout = Layout::from_size_align(size, 8).unwrap();
+            let ptr = unsafe { alloc.alloc(layout) };
+            assert!(!ptr.is_null());
+            unsafe { alloc.dealloc(ptr, layout) };
+          }
+        });
+      }
+    });
+  }
+
+  #[test]
+  fn realloc_in_place_keeps_or_refuses() {
+    let alloc = BaseAlloc {};
+    let layout = Layout::from_size_align(36, 8).unwrap();
+    let ptr = unsafe { alloc.alloc(layout) };
+    unsafe { core::ptr::write_bytes(ptr, 0x3C, 36) };
+
+    assert!(BaseAlloc::realloc_in_place(ptr, layout, 40));
+    assert!(!BaseAlloc::realloc_in_place(ptr, layout, 200));
+    assert_eq!(class_of(ptr), class_for_layout(layout));
+    let contents = unsafe { core::slice::from_raw_parts(ptr, 36) };
+    assert!(contents.iter().all(|b| *b == 0x3C));
+    unsafe { alloc.dealloc(ptr, layout) };
+
+    let large = Layout::from_size_align((4 << 20) + 1, 8).unwrap();
+    let big = unsafe { alloc.alloc(large) };
+    assert!(BaseAlloc::realloc_in_place(big, large, (4 << 20) + 100));
+    assert!(!BaseAlloc::realloc_in_place(big, large, 8 << 20));
+    assert!(!BaseAlloc::realloc_in_place(big, large, 64));
+    unsafe { alloc.dealloc(big, large) };
+  }
+
+  #[test]
+  fn reset_stats_zeroes_counters() {
+    let alloc = BaseAlloc {};
+    // No other test here uses this class.
+    let layout = Layout::from_size_align(1500, 8).unwrap();
+    let class = class_for_layout(layout).unwrap();
+
+    let ptrs: Vec<_> = (0..4).map(|_| unsafe { alloc.alloc(layout) }).collect();
+    assert!(stats::live(class) >= 4);
+
+    reset_stats();
+    assert_eq!(stats::live(class), 0);
+    for (i, ptr) in ptrs.iter().enumerate() {
+      unsafe { ptr.write_bytes(i as u8, 1500) };
+      assert_eq!(unsafe { ptr.add(1499).read() }, i as u8);
+    }
+
+    // Freeing the older slots first must not hide the ones handed out after.
+    for ptr in ptrs {
+      unsafe { alloc.dealloc(ptr, layout) };
+    }
+    flush_thread_cache().expect("flush");
+    assert_eq!(stats::live(class), -4);
+    let fresh: Vec<_> = (0..2).map(|_| unsafe { alloc.alloc(layout) }).collect();
+    assert_eq!(stats::live(class), -2);
+    for ptr in fresh {
+      unsafe { alloc.dealloc(ptr, layout) };
+    }
+  }
+
+  #[cfg(feature = "sampling")]
+  #[test]
+  fn sample_histogram_sees_global_allocations() {
+    std::thread::spawn(|| {
+      sampling::set_sample_rate(1);
+      let alloc = BaseAlloc {};
+      let layout = Layout::from_size_align(2500, 8).unwrap();
+      let class = class_for_layout(layout).unwrap();
+      let before = sample_histogram()[class.0];
+
+      for _ in 0..16 {
+        let ptr = unsafe { alloc.alloc(layout) };
+        unsafe { alloc.dealloc(ptr, layout) };
+      }
+      assert!(sample_histogram()[class.0] >= before + 16);
+    })
+    .join()
+    .unwrap();
+  }
+
+  #[test]
+  fn large_blocks_meet_alignments_above_a_page() {
+    let alloc = BaseAlloc {};
+    let layout = Layout::from_size_align(3 << 20, 2 << 20).unwrap();
+    let ptr = unsafe { alloc.alloc(layout) };
+    assert!(!ptr.is_null());
+    assert!((ptr as usize).is_multiple_of(2 << 20));
+
+    let usable = BaseAlloc::sizeof(ptr).unwrap();
+    assert!(usable >= layout.size() && usable <= layout_footprint(layout));
+    assert!(BaseAlloc::realloc_in_place(ptr, layout, layout.size()));
+    unsafe { ptr.add(layout.size() - 1).write(7) };
+
+    unsafe { alloc.dealloc(ptr, layout) };
+  }
+
+  #[test]
+  fn fragmentation_is_a_ratio() {
+    let alloc = BaseAlloc {};
+    let layout = Layout::from_size_align(96, 8).unwrap();
+    let ptr = unsafe { alloc.alloc(layout) };
+    assert!(!ptr.is_null());
+
+    let ratio = fragmentation();
+    assert!((0.0..1.0).contains(&ratio), "ratio {}", ratio);
+    unsafe { alloc.dealloc(ptr, layout) };
+  }
+
+  #[test]
+  fn prewarm_makes_tcache_resident() {
+    use basealloc_sys::{
+      GLOBAL_SYSTEM,
+      prim::page_size,
+    };
+
+    std::thread::spawn(|| {
+      assert!(prewarm_thread_cache());
+      let tcache = unsafe { acquire_tcache().unwrap().as_ref() };
+      let backing = tcache.backing().as_ref();
+      let pages = backing.len() / page_size();
+      assert_eq!(
+        unsafe { GLOBAL_SYSTEM.resident_pages(backing) }.unwrap(),
+        pages
+      );
+    })
+    .join()
+    .unwrap();
+  }
+
+  #[test]
+  fn flush_thread_cache_returns_slots() {
+    std::thread::spawn(|| {
+      let alloc = BaseAlloc {};
+      let layout = Layout::from_size_align(720, 8).unwrap();
+      let grown_layout = Layout::from_size_align(4000, 8).unwrap();
+      let class = class_for_layout(layout).unwrap();
+
+      let ptr = unsafe { alloc.alloc(layout) };
+      let grown = unsafe { alloc.realloc(ptr, layout, 4000) };
+      assert_eq!(cached(class), 1);
+
+      flush_thread_cache().expect("flush");
+      assert_eq!(cached(class), 0);
+      let again = unsafe { alloc.alloc(layout) };
+      assert_eq!(again, ptr);
+
+      let regrown = unsafe { alloc.realloc(again, layout, 4000) };
+      assert_eq!(cached(class), 1);
+      assert_eq!(unsafe { alloc.alloc(layout) }, ptr);
+      assert_eq!(cached(class), 0);
+
+      unsafe { alloc.dealloc(ptr, layout) };
+      unsafe { alloc.dealloc(regrown, grown_layout) };
+      unsafe { alloc.dealloc(grown, grown_layout) };
+    })
+    .join()
+    .unwrap();
+  }
+
+  // Poison builds keep their free pages, so there is nothing for maintenance to reclaim.
+  #[cfg(not(feature = "poison"))]
+  #[test]
+  fn maintenance_reclaims_after_a_burst() {
+    use basealloc_alloc::slab::Slab;
+    use basealloc_sys::GLOBAL_SYSTEM;
+
+    fn resident(slabs: &[NonNull<Slab>]) -> usize {
+      let pages = |slab: &NonNull<Slab>| {
+        let extent = unsafe { slab.as_ref() }.extent().as_ref();
+        unsafe { GLOBAL_SYSTEM.resident_pages(extent) }.unwrap()
+      };
+      slabs.iter().map(pages).sum()
+    }
+
+    std::thread::spawn(|| {
+      let alloc = BaseAlloc {};
+      // No other test here uses this class, so nobody else refills its pages.
+      let layout = Layout::from_size_align(1900, 8).unwrap();
+      let burst: Vec<_> = (0..256).map(|_| unsafe { alloc.alloc(layout) }).collect();
+      burst
+        .iter()
+        .for_each(|ptr| unsafe { ptr.write_bytes(0xA5, 1900) });
+
+      let (kept, freed): (Vec<_>, Vec<_>) =
+        burst.iter().enumerate().partition(|(i, _)| i % 16 == 0);
+      freed
+        .iter()
+        .for_each(|(_, ptr)| unsafe { alloc.dealloc(**ptr, layout) });
+      let mut slabs: Vec<_> = kept
+        .iter()
+        .filter_map(|(_, ptr)| match owner_of(**ptr)? {
+          OwnerInfo::Slab { slab, .. } => Some(slab),
+          OwnerInfo::Extent { .. } => None,
+        })
+        .collect();
+      slabs.dedup();
+
+      let before = resident(&slabs);
+      assert!(maintenance().expect("maintenance") > 0);
+      let after = resident(&slabs);
+      assert!(after < before, "resident {} -> {}", before, after);
+
+      for (_, ptr) in kept {
+        let bytes = unsafe { std::slice::from_raw_parts(*ptr, 1900) };
+        assert!(bytes.iter().all(|b| *b == 0xA5));
+        unsafe { alloc.dealloc(*ptr, layout) };
+      }
+    })
+    .join()
+    .unwrap();
+  }
+
+  #[test]
+  fn healthy_allocator_passes_invariants() {
+    let alloc = BaseAlloc {};
+    let layouts = [16, 200, 3000, 5 * 4096].map(|size| Layout::from_size_align(size, 8).unwrap());
+    let ptrs = layouts.map(|layout| unsafe { alloc.alloc(layout) });
+    assert_eq!(check_invariants(), Ok(()));
+
+    for (ptr, layout) in ptrs.into_iter().zip(layouts) {
+      unsafe { alloc.dealloc(ptr, layout) };
+    }
+    assert_eq!(check_invariants(), Ok(()));
+  }
+
+  #[test]
+  fn alloc_zeroed_clears_recycled_slots() {
+    let alloc = BaseAlloc {};
+    for size in [200, 3000, 5 * 4096] {
+      let layout = Layout::from_size_align(size, 8).unwrap();
+      let dirty = unsafe { alloc.alloc(layout) };
+      unsafe { dirty.write_bytes(0xAB, size) };
+      unsafe { alloc.dealloc(dirty, layout) };
+
+      let ptr = unsafe { alloc.alloc_zeroed(layout) };
+      let bytes = unsafe { core::slice::from_raw_parts(ptr, size) };
+      assert!(bytes.iter().all(|b| *b == 0), "size {}", size);
+      unsafe { alloc.dealloc(ptr, layout) };
+    }
+  }
+
+  std::thread_local! {
+    static PINNED: core::cell::Cell<bool> = const { core::cell::Cell::new(false) };
+  }
+
+  // Sends pinned threads to arena 2 and leaves every other thread to the default, so
+  // tests running alongside keep their own arenas.
+  struct PinToTwo;
+
+  impl ArenaSelector for PinToTwo {
+    fn select(&self, thread: usize, stats: &ArenaStats) -> Option<ArenaId> {
+      match PINNED.with(|pinned| pinned.get()) {
+        true => Some(ArenaId(2)),
+        false => RoundRobinSelector.select(thread, stats),
+      }
+    }
+  }
+
+  #[test]
+  fn installed_selector_picks_the_arena() {
+    static PIN: &dyn ArenaSelector = &PinToTwo;
+    set_arena_selector(&PIN);
+
+    let sizes = [64, 3000, 1 << 21];
+    let owners = std::thread::spawn(move || {
+      PINNED.with(|pinned| pinned.set(true));
+      sizes.map(|size| {
+        let layout = Layout::from_size_align(size, 8).unwrap();
+        let ptr = unsafe { BaseAlloc {}.alloc(layout) };
+        let owner = arena_for(ptr);
+        unsafe { BaseAlloc {}.dealloc(ptr, layout) };
+        owner
+      })
+    })
+    .join()
+    .unwrap();
+    set_arena_selector(&DEFAULT_SELECTOR);
+
+    assert_eq!(owners, [Some(ArenaId(2)); 3]);
+  }
+
+  #[test]
+  fn pinned_threads_allocate_from_their_arena() {
+    let sizes = [48, 3000, 1 << 21];
+    let owners: Vec<_> = [ArenaId(0), ArenaId(1)]
+      .map(|id| {
+        std::thread::spawn(move || {
+          // Something cached before pinning must not leak out afterwards.
+          let warm = Layout::from_size_align(48, 8).unwrap();
+          unsafe { BaseAlloc {}.dealloc(BaseAlloc {}.alloc(warm), warm) };
+          assert!(pin_thread_to_arena(id));
+
+          sizes.map(|size| {
+            let layout = Layout::from_size_align(size, 8).unwrap();
+            let ptr = unsafe { BaseAlloc {}.alloc(layout) };
+            let owner = arena_for(ptr);
+            unsafe { BaseAlloc {}.dealloc(ptr, layout) };
+            (id, owner)
+          })
+        })
+      })
+      .into_iter()
+      .flat_map(|handle| handle.join().unwrap())
+      .collect();
+
+    for (id, owner) in owners {
+      assert_eq!(owner, Some(id));
+    }
+    assert!(!pin_thread_to_arena(ArenaId(usize::MAX)));
+  }
+
+  #[cfg(feature = "debug-cookies")]
+  #[test]
+  fn cookies_catch_a_smashed_header() {
+    use basealloc_sys::hook::{
+      Fault,
+      set_error_hook,
+    };
+    use core::sync::atomic::AtomicUsize;
+
+    static SMASHED: AtomicUsize = AtomicUsize::new(0);
+    fn record(fault: Fault) {
+      if let Fault::CookieMismatch(addr) = fault {
+        SMASHED.store(addr, Ordering::Relaxed);
+      }
+    }
+    set_error_hook(Some(record));
+    let alloc = BaseAlloc {};
+    let layout = Layout::from_size_align(40, 8).unwrap();
+
+    let ptr = unsafe { alloc.alloc(layout) };
+    unsafe { ptr.write_bytes(0x5C, 40) };
+    let grown = unsafe { alloc.realloc(ptr, layout, 44) };
+    assert!(
+      unsafe { core::slice::from_raw_parts(grown, 40) }
+        .iter()
+        .all(|b| *b == 0x5C)
+    );
+    unsafe { alloc.dealloc(grown, Layout::from_size_align(44, 8).unwrap()) };
+    assert_eq!(SMASHED.load(Ordering::Relaxed), 0);
 
-    let arena = get_arena(arena_id).unwrap();
-    _ = arena.deallocate(ptr_nn)
+    let ptr = unsafe { alloc.alloc(layout) };
+    unsafe { ptr.sub(cookie::COOKIE).write(0) };
+    unsafe { alloc.dealloc(ptr, layout) };
+    assert_eq!(SMASHED.load(Ordering::Relaxed), ptr as usize);
+    set_error_hook(None);
   }
 }
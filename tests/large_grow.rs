@@ -0,0 +1,113 @@
+#![cfg(target_os = "linux")]
+
+use std::{
+  alloc::{
+    GlobalAlloc,
+    Layout,
+  },
+  sync::atomic::{
+    AtomicUsize,
+    Ordering,
+  },
+};
+
+use basealloc::BaseAlloc;
+use basealloc_alloc::classes::SCLASS_CUTOFF;
+use basealloc_sys::{
+  GLOBAL_SYSTEM,
+  set_system,
+  system::{
+    SysOption,
+    SysResult,
+    System,
+  },
+};
+
+// Where the next mapping of `HINT_SIZE` bytes goes; 0 once it is placed.
+static HINT: AtomicUsize = AtomicUsize::new(0);
+static HINT_SIZE: AtomicUsize = AtomicUsize::new(0);
+
+// The default backend, except one mapping lands at an address the test picked.
+struct Hinted;
+
+unsafe fn map_at(addr: usize, size: usize, prot: i32) -> Option<&'static mut [u8]> {
+  let flags = libc::MAP_PRIVATE | libc::MAP_ANONYMOUS | libc::MAP_FIXED_NOREPLACE;
+  let ptr = unsafe { libc::mmap(addr as *mut libc::c_void, size, prot, flags, -1, 0) };
+  (ptr as usize == addr).then(|| unsafe { std::slice::from_raw_parts_mut(ptr.cast(), size) })
+}
+
+unsafe impl System for Hinted {
+  unsafe fn alloc<'mem>(&self, size: usize, options: SysOption) -> SysResult<&'mem mut [u8]> {
+    if size == HINT_SIZE.load(Ordering::Relaxed) {
+      let addr = HINT.swap(0, Ordering::Relaxed);
+      let prot = libc::PROT_READ | libc::PROT_WRITE;
+      if let Some(slice) = unsafe { map_at(addr, size, prot) } {
+        return Ok(slice);
+      }
+    }
+    unsafe { GLOBAL_SYSTEM.alloc(size, options) }
+  }
+
+  unsafe fn modify(&self, slice: &[u8], options: SysOption) -> SysResult<()> {
+    unsafe { GLOBAL_SYSTEM.modify(slice, options) }
+  }
+
+  unsafe fn dealloc(&self, slice: &[u8]) -> SysResult<()> {
+    unsafe { GLOBAL_SYSTEM.dealloc(slice) }
+  }
+
+  unsafe fn grow(&self, slice: &[u8], extra: usize, options: SysOption) -> SysResult<()> {
+    unsafe { GLOBAL_SYSTEM.grow(slice, extra, options) }
+  }
+}
+
+static HINTED: Hinted = Hinted;
+static HINTED_DYN: &dyn System = &HINTED;
+
+fn filled(ptr: *mut u8, len: usize, byte: u8) -> bool {
+  unsafe { std::slice::from_raw_parts(ptr, len) }
+    .iter()
+    .all(|b| *b == byte)
+}
+
+// Its own test binary: it swaps the global backend and relies on nobody mapping into
+// the hole it reserves.
+#[test]
+fn large_realloc_grows_in_place_or_relocates() {
+  let block = 2 * SCLASS_CUTOFF;
+  let alloc = BaseAlloc {};
+  let layout = Layout::from_size_align(block, 8).unwrap();
+
+  // Builds this thread's arena and trees before the backend changes.
+  unsafe { alloc.dealloc(alloc.alloc(layout), layout) };
+
+  // Reserves room for three blocks: the first is freed for the allocation, the second
+  // just before it grows, and the third stays mapped to block the next growth.
+  let hole = unsafe { GLOBAL_SYSTEM.alloc(3 * block, SysOption::Reserve) }.unwrap();
+  let (head, tail) = hole.split_at_mut(block);
+  let (next, blocker) = tail.split_at_mut(block);
+  let base = head.as_ptr() as usize;
+  unsafe { GLOBAL_SYSTEM.dealloc(head) }.unwrap();
+
+  HINT_SIZE.store(block, Ordering::Relaxed);
+  HINT.store(base, Ordering::Relaxed);
+  set_system(&HINTED_DYN);
+  let ptr = unsafe { alloc.alloc(layout) };
+  set_system(&GLOBAL_SYSTEM);
+  assert_eq!(ptr as usize, base, "the block didn't land in the hole");
+  unsafe { ptr.write_bytes(0xA5, block) };
+
+  unsafe { GLOBAL_SYSTEM.dealloc(next) }.unwrap();
+  let grown = unsafe { alloc.realloc(ptr, layout, 2 * block) };
+  assert_eq!(grown, ptr, "next pages were free but the block moved");
+  assert!(filled(grown, block, 0xA5));
+  unsafe { grown.add(block).write_bytes(0x5A, block) };
+
+  let layout = Layout::from_size_align(2 * block, 8).unwrap();
+  let moved = unsafe { alloc.realloc(grown, layout, 3 * block) };
+  assert!(!moved.is_null() && moved != grown, "grew into a taken page");
+  assert!(filled(moved, block, 0xA5) && filled(unsafe { moved.add(block) }, block, 0x5A));
+
+  unsafe { alloc.dealloc(moved, Layout::from_size_align(3 * block, 8).unwrap()) };
+  unsafe { GLOBAL_SYSTEM.dealloc(blocker) }.unwrap();
+}
@@ -0,0 +1,57 @@
+use std::alloc::{
+  GlobalAlloc,
+  Layout,
+};
+
+use basealloc::{
+  BaseAlloc,
+  memory_usage,
+  set_memory_limit,
+};
+
+// Its own test binary: the limit is process-wide and would fail other tests' mappings.
+#[test]
+fn allocations_fail_cleanly_past_the_limit() {
+  const BLOCK: usize = 1 << 20;
+  const BUDGET: usize = 8 * BLOCK;
+  let alloc = BaseAlloc {};
+  let small = Layout::from_size_align(64, 8).unwrap();
+  let large = Layout::from_size_align(BLOCK, 8).unwrap();
+
+  // Builds this thread's arena and caches before the cap goes on.
+  let warm = [small, large].map(|layout| unsafe { alloc.alloc(layout) });
+  set_memory_limit(memory_usage() + BUDGET);
+
+  let mut live = Vec::new();
+  loop {
+    let ptr = unsafe { alloc.alloc(large) };
+    if ptr.is_null() {
+      break;
+    }
+    unsafe { ptr.write_bytes(live.len() as u8, BLOCK) };
+    live.push(ptr);
+    assert!(live.len() <= BUDGET / BLOCK, "allocated past the limit");
+  }
+  assert!(!live.is_empty());
+  assert!(unsafe { alloc.alloc(large) }.is_null());
+
+  for (tag, ptr) in live.iter().enumerate() {
+    let bytes = unsafe { core::slice::from_raw_parts(*ptr, BLOCK) };
+    assert!(bytes.iter().all(|b| *b == tag as u8));
+  }
+
+  // Freeing gives the budget back.
+  let freed = live.pop().unwrap();
+  unsafe { alloc.dealloc(freed, large) };
+  let again = unsafe { alloc.alloc(large) };
+  assert!(!again.is_null());
+  live.push(again);
+
+  set_memory_limit(usize::MAX);
+  for ptr in live {
+    unsafe { alloc.dealloc(ptr, large) };
+  }
+  for (ptr, layout) in warm.into_iter().zip([small, large]) {
+    unsafe { alloc.dealloc(ptr, layout) };
+  }
+}
@@ -0,0 +1,153 @@
+use std::{
+  alloc::{
+    GlobalAlloc,
+    Layout,
+  },
+  sync::mpsc::{
+    Receiver,
+    Sender,
+    channel,
+  },
+  thread,
+  time::{
+    Duration,
+    Instant,
+  },
+};
+
+use basealloc::BaseAlloc;
+use basealloc_alloc::{
+  classes::{
+    NSCLASSES,
+    ScIdx,
+  },
+  stats,
+};
+
+const STRESS_THREADS: usize = 8;
+const STRESS_TIME: Duration = Duration::from_millis(200);
+const STRESS_SIZES: [usize; 6] = [8, 48, 700, 3000, 100_000, 3 << 20];
+
+struct Live {
+  ptr: *mut u8,
+  layout: Layout,
+  tag: u8,
+}
+
+// Blocks are handed to a neighbour to free, which goes through its arena's remote queue.
+unsafe impl Send for Live {}
+
+#[derive(Default)]
+struct Tally {
+  allocs: usize,
+  frees: usize,
+  passed: usize,
+}
+
+fn stress_alloc(alloc: &BaseAlloc, seed: u64, tag: u8) -> Live {
+  let size = STRESS_SIZES[(seed % STRESS_SIZES.len() as u64) as usize];
+  let layout = Layout::from_size_align(size, 8).unwrap();
+  let ptr = unsafe { alloc.alloc(layout) };
+  assert!(!ptr.is_null(), "alloc of {} failed", size);
+  unsafe { core::ptr::write_bytes(ptr, tag, size) };
+  Live { ptr, layout, tag }
+}
+
+fn stress_free(alloc: &BaseAlloc, live: Live) {
+  let bytes = unsafe { core::slice::from_raw_parts(live.ptr, live.layout.size()) };
+  assert!(
+    bytes.iter().all(|b| *b == live.tag),
+    "slot handed out twice"
+  );
+  unsafe { alloc.dealloc(live.ptr, live.layout) };
+}
+
+/// One worker: random alloc/free/hand-off mix until the deadline, then hands half of
+/// what's left to its neighbour and frees the rest along with whatever it was handed.
+fn stress_worker(id: u64, next: Sender<Live>, inbox: Receiver<Live>) -> Tally {
+  let alloc = BaseAlloc {};
+  let deadline = Instant::now() + STRESS_TIME;
+  let (mut live, mut tally) = (Vec::new(), Tally::default());
+  let mut seed = id.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1;
+
+  while Instant::now() < deadline {
+    seed ^= seed << 13;
+    seed ^= seed >> 7;
+    seed ^= seed << 17;
+    stress_step(&alloc, seed, &mut live, &mut tally, &next);
+    inbox.try_iter().for_each(|l| stress_free(&alloc, l));
+  }
+
+  tally.passed += live.len() / 2;
+  live
+    .drain(..live.len() / 2)
+    .for_each(|l| next.send(l).unwrap());
+  drop(next);
+  tally.frees += live.len();
+  live
+    .into_iter()
+    .chain(inbox)
+    .for_each(|l| stress_free(&alloc, l));
+  basealloc::flush_thread_cache().unwrap();
+  tally
+}
+
+fn stress_step(
+  alloc: &BaseAlloc,
+  seed: u64,
+  live: &mut Vec<Live>,
+  tally: &mut Tally,
+  next: &Sender<Live>,
+) {
+  if live.len() < 64 && (seed & 3 != 0 || live.is_empty()) {
+    live.push(stress_alloc(alloc, seed >> 8, tally.allocs as u8));
+    tally.allocs += 1;
+    return;
+  }
+  let picked = live.swap_remove((seed >> 8) as usize % live.len());
+  if (seed >> 32) & 1 != 0 {
+    next.send(picked).unwrap();
+    tally.passed += 1;
+  } else {
+    stress_free(alloc, picked);
+    tally.frees += 1;
+  }
+}
+
+fn live_per_class() -> Vec<isize> {
+  (0..NSCLASSES).map(|i| stats::live(ScIdx(i))).collect()
+}
+
+/// Concurrent mixed small/large workload, each worker freeing blocks its neighbour
+/// allocated.
+///
+/// Invariants: no slot is live twice (tags survive until free), every allocation is
+/// freed exactly once (counts balance across threads), and once the remote queues are
+/// drained every class is back to its starting live count. mmap-backed, so not Miri.
+#[test]
+fn concurrent_stress() {
+  let before = live_per_class();
+  let (senders, inboxes): (Vec<_>, Vec<_>) = (0..STRESS_THREADS).map(|_| channel()).unzip();
+  let tallies: Vec<Tally> = thread::scope(|scope| {
+    let handles: Vec<_> = inboxes
+      .into_iter()
+      .enumerate()
+      .map(|(id, inbox)| {
+        let next = senders[(id + 1) % STRESS_THREADS].clone();
+        scope.spawn(move || stress_worker(id as u64, next, inbox))
+      })
+      .collect();
+    drop(senders);
+    handles.into_iter().map(|h| h.join().unwrap()).collect()
+  });
+
+  assert!(tallies.iter().all(|t| t.allocs > 0));
+  assert!(tallies.iter().any(|t| t.passed > 0));
+  let allocs: usize = tallies.iter().map(|t| t.allocs).sum();
+  let released: usize = tallies.iter().map(|t| t.frees + t.passed).sum();
+  assert_eq!(allocs, released);
+
+  // The last pass reclaims nothing, so it visited (and drained) every arena.
+  while basealloc::maintenance().unwrap() > 0 {}
+  assert_eq!(live_per_class(), before);
+}